@@ -1,6 +1,7 @@
 use std::{fs::File, io::{self, ErrorKind}};
 
 use pcap::{global_header::GlobalHeader, packet::{global_header::parse_global_header, header::{parse_packet, parse_packet_header}, Packet}};
+use protocol::{bgp, fragment, igmp, isotp, options::Options};
 
 
 /**
@@ -16,7 +17,7 @@ mod pcap;
 /**
  Various network protocol definitions and parsing functions.
  */
-mod protocol;
+pub mod protocol;
 
 
 /**
@@ -26,6 +27,10 @@ mod protocol;
 pub struct PCapA {
     pub global_header: GlobalHeader,
     pub packets: Vec<Packet>,
+    /**
+     The multicast group memberships seen in the capture, when opened with `igmp_tracking` enabled.
+     */
+    pub igmp_tracker: Option<igmp::Tracker>,
 }
 
 
@@ -34,6 +39,13 @@ impl PCapA {
      Loads a pcap file from the given path and tries to parse it into an `SPCap` struct. 
      */
     pub fn open(path: &str) -> io::Result<PCapA> {
+        PCapA::open_with_options(path, &Options::default())
+    }
+
+    /**
+     Loads a pcap file from the given path like `open`, but dissects the packets according to the given options.
+     */
+    pub fn open_with_options(path: &str, options: &Options) -> io::Result<PCapA> {
         let mut file = File::open(path)?;
 
         log::info!("Parsing global header...");
@@ -42,13 +54,29 @@ impl PCapA {
         let mut pcap_file = PCapA {
             global_header,
             packets: Vec::new(),
+            igmp_tracker: options.igmp_tracking.then(igmp::Tracker::new),
         };
 
+        // Reassembly keeps state across packets, so it runs after each packet has been dissected on its own.
+        let mut isotp_reassembler = options.isotp_reassembly.then(isotp::Reassembler::new);
+        let mut ip_reassembler = options.ip_reassembly.clone().map(fragment::Reassembler::new);
+        // BGP segments are fed to their stream while dissecting, as only then is the TCP payload at hand.
+        let mut bgp_reassembler = options.bgp_reassembly.then(bgp::Reassembler::new);
+
         log::info!("Parsing packets...");
         loop {
             match parse_packet_header(&mut file, &pcap_file.global_header) {
                 Ok(packet_header) => {
-                    let packet = parse_packet(&mut file, packet_header,&pcap_file.global_header)?;
+                    let mut packet = parse_packet(&mut file, packet_header,&pcap_file.global_header, options, bgp_reassembler.as_mut())?;
+                    if let Some(reassembler) = isotp_reassembler.as_mut() {
+                        reassembler.process(&mut packet.protocols);
+                    }
+                    if let Some(reassembler) = ip_reassembler.as_mut() {
+                        reassembler.process(&mut packet.protocols, packet.header.ts_secs, options);
+                    }
+                    if let Some(tracker) = pcap_file.igmp_tracker.as_mut() {
+                        tracker.process(&packet.protocols, packet.header.ts_secs);
+                    }
                     pcap_file.packets.push(packet);
                 }
                 Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
@@ -1,6 +1,7 @@
 use std::io::{self, Read};
 
 use crate::{
+    protocol::{bgp, options::Options},
     pcap::{
      global_header::GlobalHeader, packet_header::PacketHeader
     },
@@ -28,8 +29,8 @@ pub fn parse_packet_header<R: Read>(
     })
 }
 
-pub fn parse_packet<R: Read>(reader: &mut R, header: PacketHeader,global_header: &GlobalHeader) -> io::Result<Packet> {
+pub fn parse_packet<R: Read>(reader: &mut R, header: PacketHeader,global_header: &GlobalHeader, options: &Options, bgp_reassembler: Option<&mut bgp::Reassembler>) -> io::Result<Packet> {
     let mut data = vec![0u8; header.captured_bytes as usize];
     reader.read_exact(&mut data)?;
-    Ok(Packet::new(header, data,global_header))
+    Ok(Packet::new(header, data,global_header, options, bgp_reassembler))
 }
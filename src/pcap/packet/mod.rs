@@ -3,7 +3,7 @@ pub mod header;
 
 use crate::{
     pcap::packet_header::PacketHeader,
-    protocol::{parse::parse, Protocol},
+    protocol::{bgp, options::Options, parse::parse_with, Protocol},
 };
 
 use super::global_header::GlobalHeader;
@@ -16,8 +16,8 @@ pub struct Packet {
 }
 
 impl Packet {
-    pub fn new(header: PacketHeader, data: Vec<u8>, global_header: &GlobalHeader) -> Packet {
-        let protocols = parse(&data, global_header);
+    pub fn new(header: PacketHeader, data: Vec<u8>, global_header: &GlobalHeader, options: &Options, bgp_reassembler: Option<&mut bgp::Reassembler>) -> Packet {
+        let protocols = parse_with(&data, global_header, options, bgp_reassembler);
         Packet {
             header,
             data,
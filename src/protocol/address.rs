@@ -0,0 +1,258 @@
+use std::{fmt, io, net::{AddrParseError, IpAddr}, str::FromStr};
+
+use super::{ipv4, ipv6};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/**
+ ### IPv4 or IPv6 address
+
+ Converts to and from `std::net::IpAddr`, and orders IPv4 addresses before IPv6 addresses.
+ */
+pub enum IpAddress {
+    V4(ipv4::Address),
+    V6(ipv6::Address),
+}
+
+impl IpAddress {
+    /**
+     * Build an address from 4 or 16 bytes, as stored in fragment and connection keys.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Option<IpAddress> {
+        match bytes.len() {
+            4 => bytes.try_into().ok().map(|bytes| IpAddress::V4(ipv4::Address::new(bytes))),
+            16 => bytes.try_into().ok().map(|bytes| IpAddress::V6(ipv6::Address::new(bytes))),
+            _ => None,
+        }
+    }
+
+    pub fn octets(&self) -> Vec<u8> {
+        match self {
+            IpAddress::V4(address) => address.address.to_vec(),
+            IpAddress::V6(address) => address.address.to_vec(),
+        }
+    }
+
+    /**
+     * The number of bits of the address, 32 or 128.
+     */
+    pub fn bits(&self) -> u8 {
+        match self {
+            IpAddress::V4(_) => 32,
+            IpAddress::V6(_) => 128,
+        }
+    }
+
+    /**
+     * RFC 1918 addresses for IPv4, unique local addresses for IPv6.
+     */
+    pub fn is_private(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_private(),
+            IpAddress::V6(address) => address.is_private(),
+        }
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_loopback(),
+            IpAddress::V6(address) => address.is_loopback(),
+        }
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_multicast(),
+            IpAddress::V6(address) => address.is_multicast(),
+        }
+    }
+
+    pub fn is_link_local(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_link_local(),
+            IpAddress::V6(address) => address.is_link_local(),
+        }
+    }
+
+    pub fn is_documentation(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_documentation(),
+            IpAddress::V6(address) => address.is_documentation(),
+        }
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        match self {
+            IpAddress::V4(address) => address.is_unspecified(),
+            IpAddress::V6(address) => address.is_unspecified(),
+        }
+    }
+}
+
+impl From<ipv4::Address> for IpAddress {
+    fn from(address: ipv4::Address) -> IpAddress {
+        IpAddress::V4(address)
+    }
+}
+
+impl From<ipv6::Address> for IpAddress {
+    fn from(address: ipv6::Address) -> IpAddress {
+        IpAddress::V6(address)
+    }
+}
+
+impl From<IpAddr> for IpAddress {
+    fn from(address: IpAddr) -> IpAddress {
+        match address {
+            IpAddr::V4(address) => IpAddress::V4(address.into()),
+            IpAddr::V6(address) => IpAddress::V6(address.into()),
+        }
+    }
+}
+
+impl From<IpAddress> for IpAddr {
+    fn from(address: IpAddress) -> IpAddr {
+        match address {
+            IpAddress::V4(address) => IpAddr::V4(address.into()),
+            IpAddress::V6(address) => IpAddr::V6(address.into()),
+        }
+    }
+}
+
+impl fmt::Display for IpAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpAddress::V4(address) => address.fmt(f),
+            IpAddress::V6(address) => address.fmt(f),
+        }
+    }
+}
+
+impl FromStr for IpAddress {
+    type Err = AddrParseError;
+
+    fn from_str(text: &str) -> Result<IpAddress, AddrParseError> {
+        text.parse::<IpAddr>().map(IpAddress::from)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/**
+ ### CIDR prefix, e.g. `10.0.0.0/8` or `2001:db8::/32`
+ * Address: The network address. The host bits are cleared on construction.
+ * Length: The number of network bits, at most 32 for IPv4 and 128 for IPv6.
+ */
+pub struct IpPrefix {
+    address: IpAddress,
+    length: u8,
+}
+
+fn mask(bytes: &mut [u8], length: u8) {
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        let bits = (length as usize).saturating_sub(index * 8).min(8);
+        *byte &= !(0xFFu8.checked_shr(bits as u32).unwrap_or(0));
+    }
+}
+
+impl IpPrefix {
+    /**
+     * Build a prefix, or return `None` if the length exceeds the size of the address.
+     */
+    pub fn new(address: IpAddress, length: u8) -> Option<IpPrefix> {
+        if length > address.bits() {
+            return None;
+        }
+        let mut bytes = address.octets();
+        mask(&mut bytes, length);
+        Some(IpPrefix { address: IpAddress::from_bytes(&bytes)?, length })
+    }
+
+    pub fn address(&self) -> IpAddress {
+        self.address
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+
+    /**
+     * Whether the address lies within the prefix. Addresses of the other IP version never do.
+     */
+    pub fn contains(&self, address: &IpAddress) -> bool {
+        if address.bits() != self.address.bits() {
+            return false;
+        }
+        let mut bytes = address.octets();
+        mask(&mut bytes, self.length);
+        bytes == self.address.octets()
+    }
+
+    /**
+     * Whether the other prefix is the same as or lies within this prefix.
+     */
+    pub fn contains_prefix(&self, other: &IpPrefix) -> bool {
+        other.length >= self.length && self.contains(&other.address)
+    }
+}
+
+impl fmt::Display for IpPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.length)
+    }
+}
+
+impl FromStr for IpPrefix {
+    type Err = io::Error;
+
+    /**
+     * Parse a prefix in CIDR notation. An address without a length is a host prefix.
+     */
+    fn from_str(text: &str) -> io::Result<IpPrefix> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid prefix: {}", text));
+        let (address, length) = match text.split_once('/') {
+            Some((address, length)) => (address, Some(length)),
+            None => (text, None),
+        };
+        let address: IpAddress = address.parse().map_err(|_| invalid())?;
+        let length = match length {
+            Some(length) => length.parse().map_err(|_| invalid())?,
+            None => address.bits(),
+        };
+        IpPrefix::new(address, length).ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_and_classifies_addresses() {
+        let address: IpAddress = "fe80::1".parse().unwrap();
+        assert!(address.is_link_local());
+        assert_eq!(address.to_string(), "fe80::1");
+        assert_eq!(IpAddr::from(address), "fe80::1".parse::<IpAddr>().unwrap());
+
+        let address = IpAddress::from(ipv4::Address::new([192, 168, 1, 10]));
+        assert!(address.is_private());
+        assert!(!address.is_multicast());
+        assert_eq!(address.to_string(), "192.168.1.10");
+        assert!("198.51.100.7".parse::<IpAddress>().unwrap().is_documentation());
+        assert!(address < "::1".parse().unwrap());
+    }
+
+    #[test]
+    fn checks_prefix_containment() {
+        let prefix: IpPrefix = "10.1.2.3/16".parse().unwrap();
+        assert_eq!(prefix.to_string(), "10.1.0.0/16");
+        assert!(prefix.contains(&"10.1.255.1".parse().unwrap()));
+        assert!(!prefix.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!prefix.contains(&"::ffff:10.1.0.1".parse().unwrap()));
+        assert!(prefix.contains_prefix(&"10.1.4.0/24".parse().unwrap()));
+        assert!(!prefix.contains_prefix(&"10.0.0.0/8".parse().unwrap()));
+
+        let prefix: IpPrefix = "2001:db8::/33".parse().unwrap();
+        assert!(prefix.contains(&"2001:db8:7fff::1".parse().unwrap()));
+        assert!(!prefix.contains(&"2001:db8:8000::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpPrefix>().is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::io;
+
+use aipn::AIPN;
+
+use crate::read_bytes::read_u32_be;
+
+#[derive(Debug)]
+/**
+ ### Authentication header structure (RFC 4302)
+ * Next Header (8 bits): The protocol that follows the authentication header.
+ * Payload Length (8 bits): The length of the header in 4 byte units, minus 2.
+ * Reserved (16 bits)
+ * Security Parameters Index (SPI) (32 bits): Identifies the security association.
+ * Sequence Number (32 bits): Protects against replays.
+ * Integrity Check Value (ICV) (variable): The authentication data.
+ */
+pub struct Header {
+    pub next_header: AIPN,
+    pub payload_length: u8,
+    pub spi: u32,
+    pub sequence_number: u32,
+    pub icv: Vec<u8>,
+}
+
+impl Header {
+    pub fn size(&self) -> usize {
+        (self.payload_length as usize + 2) * 4
+    }
+}
+
+/**
+ ### Parse the authentication header from the data
+
+ The header is followed by the protected protocol, which is not encrypted.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 12 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse authentication header",
+        ));
+    }
+
+    let payload_length = data[1];
+    let size = (payload_length as usize + 2) * 4;
+    let icv = match data.get(12..size) {
+        Some(icv) => icv.to_vec(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated authentication header")),
+    };
+
+    Ok(Header {
+        next_header: AIPN::from(data[0]),
+        payload_length,
+        spi: read_u32_be(data, 4)?,
+        sequence_number: read_u32_be(data, 8)?,
+        icv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_icv() {
+        let mut data = vec![6, 4, 0x00, 0x00, 0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x05];
+        data.extend_from_slice(&[0xAB; 12]);
+        let header = parse(&data).unwrap();
+
+        assert!(matches!(header.next_header, AIPN::TCP));
+        assert_eq!(header.spi, 0x1001);
+        assert_eq!(header.sequence_number, 5);
+        assert_eq!(header.icv, [0xAB; 12]);
+        assert_eq!(header.size(), 24);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io;
+
+use crate::read_bytes::read_u16_be;
+
+use super::{ethernet::{EtherType, MacAddress}, ipv4};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * ARP operation codes, including the RARP (RFC 903) and InARP (RFC 2390) ones.
+ */
+pub enum Operation {
+    Request,
+    Reply,
+    RarpRequest,
+    RarpReply,
+    InArpRequest,
+    InArpReply,
+    Unsupported(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Classification of an ARP packet
+ * `Probe`: A request with an all-zero sender protocol address, used to check whether an address is in use (RFC 5227).
+ * `Gratuitous`: The sender and target protocol addresses are equal, used to announce an address or update caches.
+ * `Regular`: Any other packet.
+ */
+pub enum Classification {
+    Probe,
+    Gratuitous,
+    Regular,
+}
+
+#[derive(Debug)]
+/**
+ ### ARP header structure
+ * Hardware Type (16 bits): The link-layer type, 1 for Ethernet.
+ * Protocol Type (16 bits): The EtherType of the protocol addresses, IPv4 for regular ARP.
+ * Hardware Length (8 bits): The length of a hardware address.
+ * Protocol Length (8 bits): The length of a protocol address.
+ * Operation (16 bits): The operation of the packet.
+ * Sender Hardware Address, Sender Protocol Address, Target Hardware Address, Target Protocol Address (variable).
+ */
+pub struct Header {
+    pub hardware_type: u16,
+    pub protocol_type: EtherType,
+    pub hardware_length: u8,
+    pub protocol_length: u8,
+    pub operation: Operation,
+    pub sender_hardware_address: Vec<u8>,
+    pub sender_protocol_address: Vec<u8>,
+    pub target_hardware_address: Vec<u8>,
+    pub target_protocol_address: Vec<u8>,
+}
+
+fn to_mac(address: &[u8]) -> Option<MacAddress> {
+    address.try_into().ok().map(MacAddress::new)
+}
+
+fn to_ipv4(address: &[u8]) -> Option<ipv4::Address> {
+    address.try_into().ok().map(ipv4::Address::new)
+}
+
+impl Header {
+    pub fn size(&self) -> usize {
+        8 + 2 * (self.hardware_length as usize + self.protocol_length as usize)
+    }
+
+    pub fn classify(&self) -> Classification {
+        let request = self.operation == Operation::Request;
+        if request && self.sender_protocol_address.iter().all(|byte| *byte == 0) {
+            Classification::Probe
+        } else if matches!(self.operation, Operation::Request | Operation::Reply)
+            && self.sender_protocol_address == self.target_protocol_address
+        {
+            Classification::Gratuitous
+        } else {
+            Classification::Regular
+        }
+    }
+
+    /**
+     * The sender hardware address as a MAC address, if it is 6 bytes long.
+     */
+    pub fn sender_mac(&self) -> Option<MacAddress> {
+        to_mac(&self.sender_hardware_address)
+    }
+
+    /**
+     * The target hardware address as a MAC address, if it is 6 bytes long.
+     */
+    pub fn target_mac(&self) -> Option<MacAddress> {
+        to_mac(&self.target_hardware_address)
+    }
+
+    /**
+     * The sender protocol address as an IPv4 address, if it is 4 bytes long.
+     */
+    pub fn sender_ip(&self) -> Option<ipv4::Address> {
+        to_ipv4(&self.sender_protocol_address)
+    }
+
+    /**
+     * The target protocol address as an IPv4 address, if it is 4 bytes long.
+     */
+    pub fn target_ip(&self) -> Option<ipv4::Address> {
+        to_ipv4(&self.target_protocol_address)
+    }
+}
+
+/**
+ ### Parse the ARP or RARP header from the data
+
+ The address sizes are taken from the header, so any hardware and protocol combination is supported.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse ARP header",
+        ));
+    }
+
+    let hardware_type = read_u16_be(data, 0)?;
+    let protocol_type = EtherType::from_u16(read_u16_be(data, 2)?);
+    let hardware_length = data[4];
+    let protocol_length = data[5];
+    let operation = match read_u16_be(data, 6)? {
+        1 => Operation::Request,
+        2 => Operation::Reply,
+        3 => Operation::RarpRequest,
+        4 => Operation::RarpReply,
+        8 => Operation::InArpRequest,
+        9 => Operation::InArpReply,
+        other => Operation::Unsupported(other),
+    };
+
+    let hardware = hardware_length as usize;
+    let protocol = protocol_length as usize;
+    let addresses = match data.get(8..8 + 2 * (hardware + protocol)) {
+        Some(addresses) => addresses,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not enough data to parse ARP addresses",
+            ))
+        }
+    };
+    let (sender_hardware_address, rest) = addresses.split_at(hardware);
+    let (sender_protocol_address, rest) = rest.split_at(protocol);
+    let (target_hardware_address, target_protocol_address) = rest.split_at(hardware);
+
+    Ok(Header {
+        hardware_type,
+        protocol_type,
+        hardware_length,
+        protocol_length,
+        operation,
+        sender_hardware_address: sender_hardware_address.to_vec(),
+        sender_protocol_address: sender_protocol_address.to_vec(),
+        target_hardware_address: target_hardware_address.to_vec(),
+        target_protocol_address: target_protocol_address.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(operation: u8, sender_ip: [u8; 4], target_ip: [u8; 4]) -> Vec<u8> {
+        let mut data = vec![0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, operation];
+        data.extend_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&sender_ip);
+        data.extend_from_slice(&[0x00; 6]);
+        data.extend_from_slice(&target_ip);
+        data
+    }
+
+    #[test]
+    fn parses_request() {
+        let header = parse(&packet(1, [192, 168, 1, 1], [192, 168, 1, 2])).unwrap();
+
+        assert_eq!(header.operation, Operation::Request);
+        assert!(matches!(header.protocol_type, EtherType::IPv4));
+        assert_eq!(header.sender_mac().unwrap().bytes, [0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(header.target_ip().unwrap().address, [192, 168, 1, 2]);
+        assert_eq!(header.classify(), Classification::Regular);
+    }
+
+    #[test]
+    fn classifies_probe_and_gratuitous() {
+        assert_eq!(parse(&packet(1, [0, 0, 0, 0], [10, 0, 0, 5])).unwrap().classify(), Classification::Probe);
+        assert_eq!(parse(&packet(2, [10, 0, 0, 5], [10, 0, 0, 5])).unwrap().classify(), Classification::Gratuitous);
+    }
+}
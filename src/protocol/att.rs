@@ -0,0 +1,127 @@
+use std::io;
+
+use crate::read_bytes::read_u16_le;
+
+#[derive(Debug, PartialEq)]
+/**
+ ### Attribute protocol PDU
+ Handles and MTUs are little-endian 16 bit values. Attribute types are UUIDs of 2 or 16 bytes and kept as raw bytes.
+ PDUs that are not decoded keep their opcode and raw parameters in `Other`.
+ */
+pub enum Pdu {
+    ErrorResponse { request_opcode: u8, handle: u16, error_code: u8 },
+    ExchangeMtuRequest { mtu: u16 },
+    ExchangeMtuResponse { mtu: u16 },
+    FindInformationRequest { start_handle: u16, end_handle: u16 },
+    ReadByTypeRequest { start_handle: u16, end_handle: u16, attribute_type: Vec<u8> },
+    ReadRequest { handle: u16 },
+    ReadResponse { value: Vec<u8> },
+    ReadBlobRequest { handle: u16, offset: u16 },
+    ReadByGroupTypeRequest { start_handle: u16, end_handle: u16, group_type: Vec<u8> },
+    WriteRequest { handle: u16, value: Vec<u8> },
+    WriteResponse,
+    WriteCommand { handle: u16, value: Vec<u8> },
+    HandleValueNotification { handle: u16, value: Vec<u8> },
+    HandleValueIndication { handle: u16, value: Vec<u8> },
+    HandleValueConfirmation,
+    Other { opcode: u8, parameters: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### Attribute protocol header structure
+ * Opcode (8 bits): The method (6 bits), the command flag and the authentication signature flag.
+ * PDU: The decoded parameters.
+ */
+pub struct Header {
+    pub opcode: u8,
+    pub pdu: Pdu,
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated ATT PDU")
+}
+
+fn read_u16(parameters: &[u8], offset: usize) -> io::Result<u16> {
+    read_u16_le(parameters, offset).map_err(|_| truncated())
+}
+
+/**
+ ### Parse an ATT PDU from the data
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    let opcode = match data.first() {
+        Some(opcode) => *opcode,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not enough data to parse ATT opcode",
+            ))
+        }
+    };
+    let parameters = &data[1..];
+    let tail = |offset: usize| parameters.get(offset..).unwrap_or_default().to_vec();
+
+    let pdu = match opcode {
+        0x01 => Pdu::ErrorResponse {
+            request_opcode: *parameters.first().ok_or_else(truncated)?,
+            handle: read_u16(parameters, 1)?,
+            error_code: *parameters.get(3).ok_or_else(truncated)?,
+        },
+        0x02 => Pdu::ExchangeMtuRequest { mtu: read_u16(parameters, 0)? },
+        0x03 => Pdu::ExchangeMtuResponse { mtu: read_u16(parameters, 0)? },
+        0x04 => Pdu::FindInformationRequest {
+            start_handle: read_u16(parameters, 0)?,
+            end_handle: read_u16(parameters, 2)?,
+        },
+        0x08 => Pdu::ReadByTypeRequest {
+            start_handle: read_u16(parameters, 0)?,
+            end_handle: read_u16(parameters, 2)?,
+            attribute_type: tail(4),
+        },
+        0x0A => Pdu::ReadRequest { handle: read_u16(parameters, 0)? },
+        0x0B => Pdu::ReadResponse { value: tail(0) },
+        0x0C => Pdu::ReadBlobRequest {
+            handle: read_u16(parameters, 0)?,
+            offset: read_u16(parameters, 2)?,
+        },
+        0x10 => Pdu::ReadByGroupTypeRequest {
+            start_handle: read_u16(parameters, 0)?,
+            end_handle: read_u16(parameters, 2)?,
+            group_type: tail(4),
+        },
+        0x12 => Pdu::WriteRequest { handle: read_u16(parameters, 0)?, value: tail(2) },
+        0x13 => Pdu::WriteResponse,
+        0x52 => Pdu::WriteCommand { handle: read_u16(parameters, 0)?, value: tail(2) },
+        0x1B => Pdu::HandleValueNotification { handle: read_u16(parameters, 0)?, value: tail(2) },
+        0x1D => Pdu::HandleValueIndication { handle: read_u16(parameters, 0)?, value: tail(2) },
+        0x1E => Pdu::HandleValueConfirmation,
+        _ => Pdu::Other { opcode, parameters: parameters.to_vec() },
+    };
+
+    Ok(Header { opcode, pdu })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_notification() {
+        let header = parse(&[0x1B, 0x2A, 0x00, 0x64]).unwrap();
+
+        assert_eq!(header.pdu, Pdu::HandleValueNotification { handle: 0x002A, value: vec![0x64] });
+    }
+
+    #[test]
+    fn parses_error_response() {
+        let header = parse(&[0x01, 0x0A, 0x03, 0x00, 0x02]).unwrap();
+
+        assert_eq!(header.pdu, Pdu::ErrorResponse { request_opcode: 0x0A, handle: 3, error_code: 0x02 });
+    }
+
+    #[test]
+    fn rejects_truncated_mtu_exchange() {
+        assert!(parse(&[0x02, 0x17]).is_err());
+    }
+}
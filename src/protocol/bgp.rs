@@ -0,0 +1,658 @@
+use std::{collections::{BTreeMap, HashMap}, io};
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::{address::IpAddress, ipv4::Address, tcp};
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### Capability advertised in an OPEN message
+ * `Multiprotocol` (1): An address family (AFI) and subsequent address family (SAFI) the speaker supports (RFC 4760).
+ * `RouteRefresh` (2): The speaker supports ROUTE-REFRESH messages (RFC 2918).
+ * `GracefulRestart` (64): The restart flags (4 bits), restart time in seconds (12 bits) and the preserved address families (RFC 4724).
+ * `FourOctetAs` (65): The 4 octet AS number of the speaker (RFC 6793).
+ * `AddPath` (69): The address families with whether the speaker can receive (1), send (2) or both (3) multiple paths (RFC 7911).
+ * `Unknown`: Any other capability, with its code and value.
+ */
+pub enum Capability {
+    Multiprotocol { afi: u16, safi: u8 },
+    RouteRefresh,
+    GracefulRestart { flags: u8, restart_time: u16, families: Vec<(u16, u8, u8)> },
+    FourOctetAs(u32),
+    AddPath(Vec<(u16, u8, u8)>),
+    Unknown { code: u8, value: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ * An IP prefix of NLRI or withdrawn routes. Only the significant bytes of the prefix are kept.
+ */
+pub struct Prefix {
+    pub length: u8,
+    pub prefix: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Igp,
+    Egp,
+    Incomplete,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ * A segment of the AS path. AS_SET segments are unordered, AS_SEQUENCE segments list the ASes the route traversed.
+ */
+pub struct AsPathSegment {
+    pub set: bool,
+    pub asns: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### Path attribute value
+ * `Origin` (1), `AsPath` (2), `NextHop` (3), `MultiExitDisc` (4), `LocalPref` (5), `AtomicAggregate` (6), `Aggregator` (7): RFC 4271.
+ * `Communities` (8): RFC 1997.
+ * `MpReachNlri` (14), `MpUnreachNlri` (15): Reachable and withdrawn routes of other address families (RFC 4760).
+ * `Unknown`: Any other attribute, with its type code and value.
+
+ The AS numbers in `AsPath` and `Aggregator` are 2 or 4 bytes long depending on the capabilities the peers agreed on.
+ They are read as 4 bytes if the attribute length allows it, and as 2 bytes otherwise.
+ */
+pub enum AttributeValue {
+    Origin(Origin),
+    AsPath(Vec<AsPathSegment>),
+    NextHop(Address),
+    MultiExitDisc(u32),
+    LocalPref(u32),
+    AtomicAggregate,
+    Aggregator { asn: u32, address: Address },
+    Communities(Vec<u32>),
+    MpReachNlri { afi: u16, safi: u8, next_hop: Vec<u8>, nlri: Vec<Prefix> },
+    MpUnreachNlri { afi: u16, safi: u8, withdrawn: Vec<Prefix> },
+    Unknown { type_code: u8, value: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### Path attribute
+ * Flags (8 bits): Optional, transitive, partial and extended length.
+ * Value: The decoded attribute.
+ */
+pub struct PathAttribute {
+    pub flags: u8,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug)]
+/**
+ ### BGP message
+ * `Open` (1): The BGP version, the AS number (AS_TRANS 23456 when the 4 octet AS capability carries the real one), the hold time,
+   the BGP identifier, the capabilities and any other optional parameters.
+ * `Update` (2): Withdrawn routes, path attributes and the reachable prefixes (NLRI).
+ * `Notification` (3): The error code, subcode and data. The connection is closed after it.
+ * `Keepalive` (4): No content.
+ * `RouteRefresh` (5): The address family to resend the routes of.
+ * `Unknown`: Any other message, with its type and data.
+ */
+pub enum Message {
+    Open {
+        version: u8,
+        my_as: u16,
+        hold_time: u16,
+        bgp_identifier: Address,
+        capabilities: Vec<Capability>,
+        parameters: Vec<(u8, Vec<u8>)>,
+    },
+    Update { withdrawn_routes: Vec<Prefix>, path_attributes: Vec<PathAttribute>, nlri: Vec<Prefix> },
+    Notification { error_code: u8, error_subcode: u8, data: Vec<u8> },
+    Keepalive,
+    RouteRefresh { afi: u16, safi: u8 },
+    Unknown { message_type: u8, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### BGP header structure (RFC 4271)
+ * Marker (128 bits): All ones.
+ * Length (16 bits): The length of the message including the header, 19 to 4096 bytes.
+ * Type (8 bits): The type of the message.
+ * Message: The decoded message.
+ */
+pub struct Header {
+    pub length: u16,
+    pub message_type: u8,
+    pub message: Message,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        19
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated BGP message")
+}
+
+fn read_address(data: &[u8], offset: usize) -> io::Result<Address> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(Address::new([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(truncated()),
+    }
+}
+
+fn read_families(data: &[u8]) -> Vec<(u16, u8, u8)> {
+    data.chunks_exact(4).map(|family| (u16::from_be_bytes([family[0], family[1]]), family[2], family[3])).collect()
+}
+
+fn parse_prefixes(data: &[u8]) -> io::Result<Vec<Prefix>> {
+    let mut prefixes = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = data[offset];
+        let bytes = (length as usize + 7) / 8;
+        let prefix = data.get(offset + 1..offset + 1 + bytes).ok_or_else(truncated)?.to_vec();
+        prefixes.push(Prefix { length, prefix });
+        offset += 1 + bytes;
+    }
+    Ok(prefixes)
+}
+
+fn parse_capabilities(data: &[u8]) -> io::Result<Vec<Capability>> {
+    let mut capabilities = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let code = data[offset];
+        let length = *data.get(offset + 1).ok_or_else(truncated)? as usize;
+        let value = data.get(offset + 2..offset + 2 + length).ok_or_else(truncated)?;
+        let capability = match code {
+            1 if length == 4 => Capability::Multiprotocol { afi: read_u16_be(value, 0)?, safi: value[3] },
+            2 => Capability::RouteRefresh,
+            64 if length >= 2 => {
+                let flags_time = read_u16_be(value, 0)?;
+                Capability::GracefulRestart {
+                    flags: (flags_time >> 12) as u8,
+                    restart_time: flags_time & 0x0FFF,
+                    families: read_families(&value[2..]),
+                }
+            }
+            65 if length == 4 => Capability::FourOctetAs(read_u32_be(value, 0)?),
+            69 => Capability::AddPath(read_families(value)),
+            _ => Capability::Unknown { code, value: value.to_vec() },
+        };
+        capabilities.push(capability);
+        offset += 2 + length;
+    }
+    Ok(capabilities)
+}
+
+fn parse_as_path(value: &[u8], asn_size: usize) -> Option<Vec<AsPathSegment>> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    while offset < value.len() {
+        let segment_type = value[offset];
+        let count = *value.get(offset + 1)? as usize;
+        let asns = value.get(offset + 2..offset + 2 + count * asn_size)?;
+        let asns = asns
+            .chunks_exact(asn_size)
+            .map(|asn| asn.iter().fold(0u32, |value, byte| (value << 8) | *byte as u32))
+            .collect();
+        segments.push(AsPathSegment { set: segment_type == 1, asns });
+        offset += 2 + count * asn_size;
+    }
+    Some(segments)
+}
+
+fn parse_attribute_value(type_code: u8, value: &[u8]) -> io::Result<AttributeValue> {
+    let attribute = match (type_code, value.len()) {
+        (1, 1) => AttributeValue::Origin(match value[0] {
+            0 => Origin::Igp,
+            1 => Origin::Egp,
+            2 => Origin::Incomplete,
+            other => Origin::Unsupported(other),
+        }),
+        (2, _) => {
+            let segments = parse_as_path(value, 4).or_else(|| parse_as_path(value, 2));
+            AttributeValue::AsPath(segments.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed AS path"))?)
+        }
+        (3, 4) => AttributeValue::NextHop(read_address(value, 0)?),
+        (4, 4) => AttributeValue::MultiExitDisc(read_u32_be(value, 0)?),
+        (5, 4) => AttributeValue::LocalPref(read_u32_be(value, 0)?),
+        (6, 0) => AttributeValue::AtomicAggregate,
+        (7, 6) => AttributeValue::Aggregator { asn: read_u16_be(value, 0)? as u32, address: read_address(value, 2)? },
+        (7, 8) => AttributeValue::Aggregator { asn: read_u32_be(value, 0)?, address: read_address(value, 4)? },
+        (8, length) if length % 4 == 0 => {
+            AttributeValue::Communities(value.chunks_exact(4).map(|community| u32::from_be_bytes([community[0], community[1], community[2], community[3]])).collect())
+        }
+        (14, length) if length >= 5 => {
+            let next_hop_length = value[3] as usize;
+            let next_hop = value.get(4..4 + next_hop_length).ok_or_else(truncated)?.to_vec();
+            // A reserved byte follows the next hop.
+            let nlri = value.get(5 + next_hop_length..).ok_or_else(truncated)?;
+            AttributeValue::MpReachNlri { afi: read_u16_be(value, 0)?, safi: value[2], next_hop, nlri: parse_prefixes(nlri)? }
+        }
+        (15, length) if length >= 3 => AttributeValue::MpUnreachNlri {
+            afi: read_u16_be(value, 0)?,
+            safi: value[2],
+            withdrawn: parse_prefixes(&value[3..])?,
+        },
+        _ => AttributeValue::Unknown { type_code, value: value.to_vec() },
+    };
+    Ok(attribute)
+}
+
+fn parse_path_attributes(data: &[u8]) -> io::Result<Vec<PathAttribute>> {
+    let mut attributes = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let flags = data[offset];
+        let type_code = *data.get(offset + 1).ok_or_else(truncated)?;
+        let (length, value_start) = if flags & 0x10 != 0 {
+            (read_u16_be(data, offset + 2).map_err(|_| truncated())? as usize, offset + 4)
+        } else {
+            (*data.get(offset + 2).ok_or_else(truncated)? as usize, offset + 3)
+        };
+        let value = data.get(value_start..value_start + length).ok_or_else(truncated)?;
+        attributes.push(PathAttribute { flags, value: parse_attribute_value(type_code, value)? });
+        offset = value_start + length;
+    }
+    Ok(attributes)
+}
+
+fn parse_message(message_type: u8, body: &[u8]) -> io::Result<Message> {
+    let message = match message_type {
+        1 => {
+            if body.len() < 10 {
+                return Err(truncated());
+            }
+            let parameters_length = body[9] as usize;
+            let parameters_data = body.get(10..10 + parameters_length).ok_or_else(truncated)?;
+            let mut capabilities = Vec::new();
+            let mut parameters = Vec::new();
+            let mut offset = 0;
+            while offset < parameters_data.len() {
+                let parameter_type = parameters_data[offset];
+                let length = *parameters_data.get(offset + 1).ok_or_else(truncated)? as usize;
+                let value = parameters_data.get(offset + 2..offset + 2 + length).ok_or_else(truncated)?;
+                match parameter_type {
+                    2 => capabilities.extend(parse_capabilities(value)?),
+                    _ => parameters.push((parameter_type, value.to_vec())),
+                }
+                offset += 2 + length;
+            }
+            Message::Open {
+                version: body[0],
+                my_as: read_u16_be(body, 1)?,
+                hold_time: read_u16_be(body, 3)?,
+                bgp_identifier: read_address(body, 5)?,
+                capabilities,
+                parameters,
+            }
+        }
+        2 => {
+            let withdrawn_length = read_u16_be(body, 0).map_err(|_| truncated())? as usize;
+            let withdrawn = body.get(2..2 + withdrawn_length).ok_or_else(truncated)?;
+            let attributes_length = read_u16_be(body, 2 + withdrawn_length).map_err(|_| truncated())? as usize;
+            let attributes_start = 4 + withdrawn_length;
+            let attributes = body.get(attributes_start..attributes_start + attributes_length).ok_or_else(truncated)?;
+            Message::Update {
+                withdrawn_routes: parse_prefixes(withdrawn)?,
+                path_attributes: parse_path_attributes(attributes)?,
+                nlri: parse_prefixes(&body[attributes_start + attributes_length..])?,
+            }
+        }
+        3 if body.len() >= 2 => Message::Notification { error_code: body[0], error_subcode: body[1], data: body[2..].to_vec() },
+        4 => Message::Keepalive,
+        5 if body.len() >= 4 => Message::RouteRefresh { afi: read_u16_be(body, 0)?, safi: body[3] },
+        3 | 5 => return Err(truncated()),
+        _ => Message::Unknown { message_type, data: body.to_vec() },
+    };
+    Ok(message)
+}
+
+/**
+ * Parse one BGP message from the start of the data. The data may hold more messages after it.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse BGP header",
+        ));
+    }
+    if data[..16].iter().any(|byte| *byte != 0xFF) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid BGP marker"));
+    }
+
+    let length = read_u16_be(data, 16)?;
+    if (length as usize) < Header::size() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid BGP message length"));
+    }
+    let message_type = data[18];
+    let body = data.get(Header::size()..length as usize).ok_or_else(truncated)?;
+
+    Ok(Header {
+        length,
+        message_type,
+        message: parse_message(message_type, body)?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/**
+ * One direction of a TCP connection, identified by the IP addresses and ports.
+ */
+pub struct Connection {
+    pub source: IpAddress,
+    pub destination: IpAddress,
+    pub source_port: u16,
+    pub destination_port: u16,
+}
+
+/**
+ * The most payload held back behind a missing segment before it is given up on.
+ * This leaves room for an extended message (RFC 8654) of 65535 bytes.
+ */
+const MAX_PENDING_BYTES: usize = 1 << 16;
+
+#[derive(Default)]
+struct Stream {
+    next_sequence_number: Option<u32>,
+    buffer: Vec<u8>,
+    pending: BTreeMap<u32, Vec<u8>>,
+    pending_bytes: usize,
+}
+
+impl Stream {
+    /**
+     * Add the payload of a segment, trimming retransmitted bytes and holding back segments that arrived early.
+     * When more than `MAX_PENDING_BYTES` are held back, the missing data is skipped.
+     */
+    fn push(&mut self, sequence_number: u32, data: &[u8]) {
+        let next = *self.next_sequence_number.get_or_insert(sequence_number);
+        if sequence_number.wrapping_sub(next) as i32 > 0 {
+            self.pending_bytes += data.len();
+            if let Some(previous) = self.pending.insert(sequence_number, data.to_vec()) {
+                self.pending_bytes -= previous.len();
+            }
+            if self.pending_bytes > MAX_PENDING_BYTES {
+                self.skip_gap(next);
+            }
+        } else {
+            self.append(sequence_number, data);
+        }
+        self.drain_pending();
+    }
+
+    /**
+     * Append the bytes of a segment starting at or before the next sequence number that the buffer doesn't hold yet.
+     */
+    fn append(&mut self, sequence_number: u32, data: &[u8]) {
+        let next = *self.next_sequence_number.get_or_insert(sequence_number);
+        let skip = next.wrapping_sub(sequence_number) as usize;
+        if skip < data.len() {
+            self.buffer.extend_from_slice(&data[skip..]);
+            self.next_sequence_number = Some(next.wrapping_add((data.len() - skip) as u32));
+        }
+    }
+
+    /**
+     * Continue the stream at the earliest held back segment. The message cut by the gap can't be completed,
+     * so the buffer is dropped and `messages` resyncs on the next marker.
+     */
+    fn skip_gap(&mut self, next: u32) {
+        let Some(earliest) = self.first_pending(next.wrapping_add(1), next.wrapping_add(i32::MAX as u32)) else {
+            return;
+        };
+        log::debug!("Skipping {} missing bytes of BGP stream", earliest.wrapping_sub(next));
+        self.buffer.clear();
+        self.next_sequence_number = Some(earliest);
+    }
+
+    /**
+     * Append the held back segments that the stream has caught up with, one at a time.
+     */
+    fn drain_pending(&mut self) {
+        while let Some(next) = self.next_sequence_number {
+            let Some(sequence_number) = self.first_pending(next.wrapping_sub(i32::MAX as u32), next) else {
+                break;
+            };
+            let data = self.pending.remove(&sequence_number).unwrap_or_default();
+            self.pending_bytes -= data.len();
+            self.append(sequence_number, &data);
+        }
+    }
+
+    /**
+     * The first held back segment from `start` through `end`, where the range may wrap around the sequence numbers.
+     */
+    fn first_pending(&self, start: u32, end: u32) -> Option<u32> {
+        let first = if start <= end {
+            self.pending.range(start..=end).next()
+        } else {
+            self.pending.range(start..).next().or_else(|| self.pending.range(..=end).next())
+        };
+        first.map(|(sequence_number, _)| *sequence_number)
+    }
+
+    /**
+     * Take the complete messages off the buffer. When joining a connection midway, data before the first marker is dropped.
+     */
+    fn messages(&mut self) -> Vec<Header> {
+        let mut messages = Vec::new();
+        loop {
+            match self.buffer.windows(16).position(|window| window.iter().all(|byte| *byte == 0xFF)) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    let keep = self.buffer.len().min(15);
+                    self.buffer.drain(..self.buffer.len() - keep);
+                    break;
+                }
+            }
+            if self.buffer.len() < Header::size() {
+                break;
+            }
+            let length = u16::from_be_bytes([self.buffer[16], self.buffer[17]]) as usize;
+            if length < Header::size() {
+                self.buffer.drain(..1);
+                continue;
+            }
+            if self.buffer.len() < length {
+                break;
+            }
+            match parse(&self.buffer[..length]) {
+                Ok(header) => messages.push(header),
+                Err(e) => log::error!("Failed to parse BGP message: {}", e),
+            }
+            self.buffer.drain(..length);
+        }
+        messages
+    }
+}
+
+#[derive(Default)]
+/**
+ ### BGP stream reassembler
+
+ BGP messages span TCP segments, and segments carry several messages. The reassembler orders the payloads of the segments
+ per connection by sequence number and takes the complete messages off the resulting byte stream.
+ */
+pub struct Reassembler {
+    streams: HashMap<Connection, Stream>,
+}
+
+impl Reassembler {
+    pub fn new() -> Reassembler {
+        Reassembler::default()
+    }
+
+    /**
+     * Feed the payload of a TCP segment to the reassembler, and return the messages it completed.
+     */
+    pub fn push(&mut self, connection: &Connection, sequence_number: u32, data: &[u8]) -> Vec<Header> {
+        let stream = self.streams.entry(connection.clone()).or_default();
+        stream.push(sequence_number, data);
+        stream.messages()
+    }
+
+    /**
+     * Forget a connection, e.g. after it was closed.
+     */
+    pub fn close(&mut self, connection: &Connection) {
+        self.streams.remove(connection);
+    }
+
+    /**
+     * Feed a TCP segment between the given addresses to the reassembler, and return the messages it completed.
+     * The stream is dropped when the segment closes or resets the connection.
+     */
+    pub fn push_segment(&mut self, source: IpAddress, destination: IpAddress, tcp: &tcp::Header, data: &[u8]) -> Vec<Header> {
+        let connection = Connection {
+            source,
+            destination,
+            source_port: tcp.source_port,
+            destination_port: tcp.destination_port,
+        };
+        let messages = if data.is_empty() { Vec::new() } else { self.push(&connection, tcp.sequence_number, data) };
+        if tcp.has_flag(tcp::Flag::Finish) || tcp.has_flag(tcp::Flag::Reset) {
+            self.close(&connection);
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(message_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xFF; 16];
+        data.extend_from_slice(&((19 + body.len()) as u16).to_be_bytes());
+        data.push(message_type);
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn parses_open_with_capabilities() {
+        let data = message(1, &[
+            4, 0x5B, 0xA0, 0x00, 0xB4, 10, 0, 0, 1, 16, // Version 4, AS 23456, hold time 180, 10.0.0.1
+            2, 14, 1, 4, 0x00, 0x01, 0x00, 0x01, 2, 0, 65, 4, 0x00, 0x01, 0x00, 0x00, // Multiprotocol, route refresh, AS 65536
+        ]);
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Message::Open { my_as, hold_time, capabilities, .. } => {
+                assert_eq!(my_as, 23456);
+                assert_eq!(hold_time, 180);
+                assert_eq!(capabilities, vec![
+                    Capability::Multiprotocol { afi: 1, safi: 1 },
+                    Capability::RouteRefresh,
+                    Capability::FourOctetAs(65536),
+                ]);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_update_attributes_and_nlri() {
+        let data = message(2, &[
+            0x00, 0x00, 0x00, 0x14, // No withdrawn routes, 20 bytes of path attributes
+            0x40, 1, 1, 0, // Origin IGP
+            0x40, 2, 6, 2, 1, 0x00, 0x00, 0xFD, 0xE8, // AS sequence 65000
+            0x40, 3, 4, 192, 0, 2, 1, // Next hop
+            24, 198, 51, 100, // 198.51.100.0/24
+        ]);
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Message::Update { withdrawn_routes, path_attributes, nlri } => {
+                assert!(withdrawn_routes.is_empty());
+                assert_eq!(path_attributes[0].value, AttributeValue::Origin(Origin::Igp));
+                assert_eq!(path_attributes[1].value, AttributeValue::AsPath(vec![AsPathSegment { set: false, asns: vec![65000] }]));
+                assert!(matches!(&path_attributes[2].value, AttributeValue::NextHop(address) if address.address == [192, 0, 2, 1]));
+                assert_eq!(nlri, vec![Prefix { length: 24, prefix: vec![198, 51, 100] }]);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reassembles_messages_across_segments() {
+        let connection = Connection {
+            source: "10.0.0.1".parse().unwrap(),
+            destination: "10.0.0.2".parse().unwrap(),
+            source_port: 179,
+            destination_port: 50000,
+        };
+        let mut stream = message(4, &[]);
+        stream.extend(message(3, &[6, 2]));
+
+        let mut reassembler = Reassembler::new();
+        // The end arrives before the middle, which is retransmitted together with the start.
+        assert!(reassembler.push(&connection, 1000, &stream[..10]).is_empty());
+        assert!(reassembler.push(&connection, 1025, &stream[25..]).is_empty());
+        let messages = reassembler.push(&connection, 1000, &stream[..25]);
+
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[0].message, Message::Keepalive));
+        assert!(matches!(messages[1].message, Message::Notification { error_code: 6, error_subcode: 2, .. }));
+    }
+
+    #[test]
+    fn skips_missing_segments() {
+        let connection = Connection {
+            source: "10.0.0.1".parse().unwrap(),
+            destination: "10.0.0.2".parse().unwrap(),
+            source_port: 179,
+            destination_port: 50000,
+        };
+        let mut stream = message(4, &[]);
+        stream.extend(message(3, &[6, 2]));
+        for _ in 0..3500 {
+            stream.extend(message(4, &[]));
+        }
+
+        let mut reassembler = Reassembler::new();
+        assert_eq!(reassembler.push(&connection, 1000, &stream[..29]).len(), 1);
+        // The segment with the end of the notification and the start of a keepalive is lost.
+        let mut messages = Vec::new();
+        for (index, chunk) in stream[45..].chunks(1460).enumerate() {
+            messages.extend(reassembler.push(&connection, 1045 + index as u32 * 1460, chunk));
+        }
+
+        assert_eq!(messages.len(), 3499);
+        assert!(messages.iter().all(|header| matches!(header.message, Message::Keepalive)));
+        assert!(reassembler.streams[&connection].pending.is_empty());
+        let next = 1000 + stream.len() as u32;
+        assert_eq!(reassembler.push(&connection, next, &message(4, &[])).len(), 1);
+    }
+
+    #[test]
+    fn drains_many_held_back_segments() {
+        let connection = Connection {
+            source: "10.0.0.1".parse().unwrap(),
+            destination: "10.0.0.2".parse().unwrap(),
+            source_port: 179,
+            destination_port: 50000,
+        };
+        let stream: Vec<u8> = (0..1000).flat_map(|_| message(4, &[])).collect();
+        // The sequence numbers wrap around in the middle of the stream.
+        let start = u32::MAX - 5000;
+
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.push(&connection, start, &stream[..1]).is_empty());
+        for offset in (2..stream.len()).rev() {
+            assert!(reassembler.push(&connection, start.wrapping_add(offset as u32), &stream[offset..offset + 1]).is_empty());
+        }
+        let messages = reassembler.push(&connection, start.wrapping_add(1), &stream[1..2]);
+
+        assert_eq!(messages.len(), 1000);
+        assert_eq!(reassembler.streams[&connection].pending_bytes, 0);
+    }
+}
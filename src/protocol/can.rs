@@ -0,0 +1,158 @@
+use std::io;
+
+use crate::read_bytes::read_u32_be;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### CAN frame flags
+ * `Extended`: The identifier is 29 bits instead of 11.
+ * `RemoteTransmission`: Remote transmission request, the frame carries no data.
+ * `Error`: Error frame generated by the controller, the data describes the error.
+ * `BitRateSwitch`: CAN FD only, the data phase uses a higher bit rate.
+ * `ErrorStateIndicator`: CAN FD only, the transmitting node is error passive.
+ */
+pub enum Flag {
+    Extended,
+    RemoteTransmission,
+    Error,
+    BitRateSwitch,
+    ErrorStateIndicator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Classic CAN frames carry up to 8 bytes, CAN FD frames up to 64 bytes.
+ */
+pub enum FrameFormat {
+    Classic,
+    Fd,
+}
+
+#[derive(Debug)]
+/**
+ ### SocketCAN frame structure
+ * CAN ID (32 bits, network byte order): The identifier in the lower 29 bits and the EFF/RTR/ERR flags in the upper 3 bits.
+ * Payload Length (8 bits): The number of data bytes.
+ * FD Flags (8 bits): Bit rate switch, error state indicator and the CAN FD frame marker.
+ * Reserved (8 bits)
+ * Len8 DLC (8 bits): Classic CAN only, the DLC when it is 9 to 15 and the payload length is 8.
+ * Data (up to 8 or 64 bytes)
+ */
+pub struct Header {
+    pub id: u32,
+    pub flags: Vec<Flag>,
+    pub format: FrameFormat,
+    pub length: u8,
+    pub len8_dlc: Option<u8>,
+    pub data: Vec<u8>,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+}
+
+/**
+ ### Parse a SocketCAN frame from the data
+
+ A frame is CAN FD when the FD frame marker is set or when it carries more than 8 bytes of data.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse SocketCAN header",
+        ));
+    }
+
+    let id_flags = read_u32_be(data, 0)?;
+    let length = data[4];
+    let fd_flags = data[5];
+    let format = if fd_flags & 0x04 != 0 || length > 8 {
+        FrameFormat::Fd
+    } else {
+        FrameFormat::Classic
+    };
+
+    let mut flags = Vec::new();
+    if id_flags & 0x8000_0000 != 0 {
+        flags.push(Flag::Extended);
+    }
+    if id_flags & 0x4000_0000 != 0 {
+        flags.push(Flag::RemoteTransmission);
+    }
+    if id_flags & 0x2000_0000 != 0 {
+        flags.push(Flag::Error);
+    }
+    if format == FrameFormat::Fd {
+        if fd_flags & 0x01 != 0 {
+            flags.push(Flag::BitRateSwitch);
+        }
+        if fd_flags & 0x02 != 0 {
+            flags.push(Flag::ErrorStateIndicator);
+        }
+    }
+
+    let id = if flags.contains(&Flag::Extended) {
+        id_flags & 0x1FFF_FFFF
+    } else {
+        id_flags & 0x7FF
+    };
+    let len8_dlc = match format {
+        FrameFormat::Classic if length == 8 && (9..=15).contains(&data[7]) => Some(data[7]),
+        _ => None,
+    };
+
+    // Remote frames announce a length but carry no data.
+    let data = if flags.contains(&Flag::RemoteTransmission) {
+        Vec::new()
+    } else {
+        match data.get(Header::size()..Header::size() + length as usize) {
+            Some(payload) => payload.to_vec(),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "CAN payload length exceeds the captured data",
+                ))
+            }
+        }
+    };
+
+    Ok(Header {
+        id,
+        flags,
+        format,
+        length,
+        len8_dlc,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_classic_frame() {
+        let data = [0x00, 0x00, 0x07, 0xDF, 0x02, 0x00, 0x00, 0x00, 0x01, 0x0C];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.id, 0x7DF);
+        assert!(header.flags.is_empty());
+        assert_eq!(header.format, FrameFormat::Classic);
+        assert_eq!(header.data, vec![0x01, 0x0C]);
+    }
+
+    #[test]
+    fn parses_extended_fd_frame() {
+        let mut data = vec![0x98, 0xDA, 0xF1, 0x10, 0x0C, 0x05, 0x00, 0x00];
+        data.extend_from_slice(&[0xAA; 12]);
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.id, 0x18DAF110);
+        assert_eq!(header.format, FrameFormat::Fd);
+        assert_eq!(header.flags, vec![Flag::Extended, Flag::BitRateSwitch]);
+        assert_eq!(header.data.len(), 12);
+    }
+}
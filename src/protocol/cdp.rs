@@ -0,0 +1,171 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+#[derive(Debug)]
+/**
+ ### CDP address entry
+ * Protocol Type (8 bits): 1 for NLPID, 2 for 802.2.
+ * Protocol (variable): The protocol identifier, `0xCC` for IPv4 when the type is NLPID.
+ * Address (variable): The address bytes.
+ */
+pub struct Address {
+    pub protocol_type: u8,
+    pub protocol: Vec<u8>,
+    pub address: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### Decoded CDP TLV
+ Every TLV consists of a type (16 bits), a length including the type and length fields (16 bits) and a value.
+ Unknown TLVs keep their type and raw value.
+ */
+pub enum Tlv {
+    DeviceId(String),
+    Addresses(Vec<Address>),
+    PortId(String),
+    Capabilities(u32),
+    SoftwareVersion(String),
+    Platform(String),
+    VtpDomain(String),
+    NativeVlan(u16),
+    FullDuplex(bool),
+    ManagementAddresses(Vec<Address>),
+    Unknown { tlv_type: u16, value: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### Cisco Discovery Protocol header structure
+ * Version (8 bits): The CDP version, usually 2.
+ * TTL (8 bits): How many seconds the receiver should keep the information.
+ * Checksum (16 bits): Checksum over the whole CDP packet.
+ * TLVs (variable): The advertised information.
+ */
+pub struct Header {
+    pub version: u8,
+    pub ttl: u8,
+    pub checksum: u16,
+    pub tlvs: Vec<Tlv>,
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated CDP address")
+}
+
+fn parse_addresses(data: &[u8]) -> io::Result<Vec<Address>> {
+    let count = read_u32_be(data, 0)?;
+    let mut offset = 4;
+    let mut addresses = Vec::new();
+
+    for _ in 0..count {
+        let protocol_type = *data.get(offset).ok_or_else(truncated)?;
+        let protocol_length = *data.get(offset + 1).ok_or_else(truncated)? as usize;
+        let protocol = data.get(offset + 2..offset + 2 + protocol_length).ok_or_else(truncated)?.to_vec();
+        offset += 2 + protocol_length;
+
+        let address_length = read_u16_be(data, offset)? as usize;
+        let address = data.get(offset + 2..offset + 2 + address_length).ok_or_else(truncated)?.to_vec();
+        offset += 2 + address_length;
+
+        addresses.push(Address {
+            protocol_type,
+            protocol,
+            address,
+        });
+    }
+
+    Ok(addresses)
+}
+
+fn parse_tlv(tlv_type: u16, value: &[u8]) -> io::Result<Tlv> {
+    let text = || String::from_utf8_lossy(value).into_owned();
+    let tlv = match tlv_type {
+        0x0001 => Tlv::DeviceId(text()),
+        0x0002 => Tlv::Addresses(parse_addresses(value)?),
+        0x0003 => Tlv::PortId(text()),
+        0x0004 => Tlv::Capabilities(read_u32_be(value, 0)?),
+        0x0005 => Tlv::SoftwareVersion(text()),
+        0x0006 => Tlv::Platform(text()),
+        0x0009 => Tlv::VtpDomain(text()),
+        0x000A => Tlv::NativeVlan(read_u16_be(value, 0)?),
+        0x000B => Tlv::FullDuplex(value.first().ok_or_else(truncated)? == &1),
+        0x0016 => Tlv::ManagementAddresses(parse_addresses(value)?),
+        _ => Tlv::Unknown {
+            tlv_type,
+            value: value.to_vec(),
+        },
+    };
+    Ok(tlv)
+}
+
+/**
+ ### Parse the CDP packet from the data
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse CDP header",
+        ));
+    }
+
+    let version = data[0];
+    let ttl = data[1];
+    let checksum = read_u16_be(data, 2)?;
+
+    let mut tlvs = Vec::new();
+    let mut offset = 4;
+    while offset + 4 <= data.len() {
+        let tlv_type = read_u16_be(data, offset)?;
+        let tlv_length = read_u16_be(data, offset + 2)? as usize;
+        if tlv_length < 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid CDP TLV length"));
+        }
+        let value = match data.get(offset + 4..offset + tlv_length) {
+            Some(value) => value,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated CDP TLV")),
+        };
+        tlvs.push(parse_tlv(tlv_type, value)?);
+        offset += tlv_length;
+    }
+
+    Ok(Header {
+        version,
+        ttl,
+        checksum,
+        tlvs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tlvs() {
+        let data = [
+            0x02, 0xB4, 0x12, 0x34, // Version, TTL, checksum
+            0x00, 0x01, 0x00, 0x06, b's', b'w', // Device ID
+            0x00, 0x02, 0x00, 0x11, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0xCC, 0x00, 0x04, 0x0A,
+            0x00, 0x00, 0x01, // Addresses
+            0x00, 0x0A, 0x00, 0x06, 0x00, 0x64, // Native VLAN
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.version, 2);
+        assert_eq!(header.ttl, 180);
+        assert_eq!(header.tlvs.len(), 3);
+        assert!(matches!(&header.tlvs[0], Tlv::DeviceId(id) if id == "sw"));
+        match &header.tlvs[1] {
+            Tlv::Addresses(addresses) => {
+                assert_eq!(addresses.len(), 1);
+                assert_eq!(addresses[0].protocol, vec![0xCC]);
+                assert_eq!(addresses[0].address, vec![10, 0, 0, 1]);
+            }
+            other => panic!("Unexpected TLV: {:?}", other),
+        }
+        assert!(matches!(header.tlvs[2], Tlv::NativeVlan(100)));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{fmt, fs, io};
+
+use aes::{
+    cipher::{block_padding::NoPadding, consts::{U12, U16}, BlockCipher, BlockDecryptMut, BlockEncrypt, BlockSizeUser, InnerIvInit, KeyInit},
+    Aes128, Aes192, Aes256,
+};
+use aes_gcm::{AeadInPlace, AesGcm};
+use aipn::AIPN;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::read_bytes::read_u32_be;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### ESP encryption and authentication algorithm
+ * `AesGcm`: AES-GCM with a 16 byte ICV (RFC 4106). The key is followed by a 4 byte salt.
+ * `AesCbcHmacSha1`: AES-CBC with HMAC-SHA1-96 (RFC 3602, RFC 2404).
+ * `AesCbcHmacSha256`: AES-CBC with HMAC-SHA256-128 (RFC 4868).
+ */
+pub enum Algorithm {
+    AesGcm,
+    AesCbcHmacSha1,
+    AesCbcHmacSha256,
+}
+
+impl Algorithm {
+    pub fn icv_length(&self) -> usize {
+        match self {
+            Algorithm::AesCbcHmacSha1 => 12,
+            Algorithm::AesGcm | Algorithm::AesCbcHmacSha256 => 16,
+        }
+    }
+
+    fn iv_length(&self) -> usize {
+        match self {
+            Algorithm::AesGcm => 8,
+            Algorithm::AesCbcHmacSha1 | Algorithm::AesCbcHmacSha256 => 16,
+        }
+    }
+}
+
+#[derive(Clone)]
+/**
+ * The AES key of a security association, expanded once when the association is created.
+ */
+enum Cipher {
+    Gcm128(AesGcm<Aes128, U12>),
+    Gcm192(AesGcm<Aes192, U12>),
+    Gcm256(AesGcm<Aes256, U12>),
+    Cbc128(Aes128),
+    Cbc192(Aes192),
+    Cbc256(Aes256),
+}
+
+#[derive(Clone)]
+/**
+ * The HMAC of a security association, keyed once when the association is created.
+ */
+enum Authenticator {
+    Sha1(Hmac<Sha1>),
+    Sha256(Hmac<Sha256>),
+}
+
+impl Authenticator {
+    /**
+     * Whether the ICV is the HMAC of the data truncated to the length of the ICV, compared in constant time.
+     */
+    fn verify(&self, data: &[u8], icv: &[u8]) -> bool {
+        match self {
+            Authenticator::Sha1(mac) => mac.clone().chain_update(data).verify_truncated_left(icv).is_ok(),
+            Authenticator::Sha256(mac) => mac.clone().chain_update(data).verify_truncated_left(icv).is_ok(),
+        }
+    }
+}
+
+#[derive(Clone)]
+/**
+ ### Security association used to decrypt ESP packets
+ * SPI: The security parameters index the association applies to.
+ * Algorithm: The encryption and authentication algorithm.
+ * Encryption Key: The AES key, followed by the salt for AES-GCM.
+ * Authentication Key: The HMAC key, empty for AES-GCM.
+
+ Created with `SecurityAssociation::new`, which checks the keys and expands them for all packets of the association.
+ */
+pub struct SecurityAssociation {
+    pub spi: u32,
+    pub algorithm: Algorithm,
+    pub encryption_key: Vec<u8>,
+    pub authentication_key: Vec<u8>,
+    cipher: Cipher,
+    authenticator: Option<Authenticator>,
+}
+
+impl fmt::Debug for SecurityAssociation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SecurityAssociation")
+            .field("spi", &self.spi)
+            .field("algorithm", &self.algorithm)
+            .field("encryption_key", &self.encryption_key)
+            .field("authentication_key", &self.authentication_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecurityAssociation {
+    /**
+     * Check the keys of a security association and expand them. AES-CBC is only accepted with an authentication key.
+     */
+    pub fn new(spi: u32, algorithm: Algorithm, encryption_key: Vec<u8>, authentication_key: Vec<u8>) -> io::Result<SecurityAssociation> {
+        let invalid_length = |_| invalid("Invalid encryption key length");
+        let cipher = match (algorithm, encryption_key.len()) {
+            (Algorithm::AesGcm, 20) => Cipher::Gcm128(AesGcm::new_from_slice(&encryption_key[..16]).map_err(invalid_length)?),
+            (Algorithm::AesGcm, 28) => Cipher::Gcm192(AesGcm::new_from_slice(&encryption_key[..24]).map_err(invalid_length)?),
+            (Algorithm::AesGcm, 36) => Cipher::Gcm256(AesGcm::new_from_slice(&encryption_key[..32]).map_err(invalid_length)?),
+            (Algorithm::AesGcm, _) => return Err(invalid("Invalid encryption key length")),
+            (_, 16) => Cipher::Cbc128(Aes128::new_from_slice(&encryption_key).map_err(invalid_length)?),
+            (_, 24) => Cipher::Cbc192(Aes192::new_from_slice(&encryption_key).map_err(invalid_length)?),
+            (_, 32) => Cipher::Cbc256(Aes256::new_from_slice(&encryption_key).map_err(invalid_length)?),
+            _ => return Err(invalid("Invalid encryption key length")),
+        };
+        let authenticator = match algorithm {
+            Algorithm::AesGcm => None,
+            _ if authentication_key.is_empty() => return Err(invalid("Missing authentication key")),
+            // HMAC takes keys of any length.
+            Algorithm::AesCbcHmacSha1 => <Hmac<Sha1> as Mac>::new_from_slice(&authentication_key).ok().map(Authenticator::Sha1),
+            Algorithm::AesCbcHmacSha256 => <Hmac<Sha256> as Mac>::new_from_slice(&authentication_key).ok().map(Authenticator::Sha256),
+        };
+        Ok(SecurityAssociation {
+            spi,
+            algorithm,
+            encryption_key,
+            authentication_key,
+            cipher,
+            authenticator,
+        })
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### Decrypted ESP payload
+ * Payload: The protected packet, without padding and trailer.
+ * Padding Length (8 bits): The number of padding bytes.
+ * Next Header (8 bits): The protocol of the payload, IPv4 or IPv6 in tunnel mode.
+ * ICV: The integrity check value at the end of the packet.
+ * Authenticated: Whether the ICV matched. For AES-CBC, the HMAC is computed over the ESP header, IV and ciphertext,
+   and truncated to the ICV length. AES-GCM payloads that fail authentication are not decrypted at all.
+ */
+pub struct Decrypted {
+    pub payload: Vec<u8>,
+    pub padding_length: u8,
+    pub next_header: AIPN,
+    pub icv: Vec<u8>,
+    pub authenticated: bool,
+}
+
+#[derive(Debug)]
+/**
+ ### ESP header structure (RFC 4303)
+ * Security Parameters Index (SPI) (32 bits): Identifies the security association.
+ * Sequence Number (32 bits): Protects against replays.
+ * Decrypted: The decrypted payload, when a security association for the SPI was supplied.
+
+ Everything after the sequence number is encrypted. Without a security association not even the length of the ICV is known.
+ */
+pub struct Header {
+    pub spi: u32,
+    pub sequence_number: u32,
+    pub decrypted: Option<Decrypted>,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn parse_hex(text: &str) -> io::Result<Vec<u8>> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    if !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid("Invalid hexadecimal key"));
+    }
+    if text.len() % 2 != 0 {
+        return Err(invalid("Hexadecimal key has an odd number of digits"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).map_err(|_| invalid("Invalid hexadecimal key")))
+        .collect()
+}
+
+/**
+ ### Parse security associations, one per line
+
+ Each line holds the SPI, the algorithm (`aes-gcm`, `aes-cbc-hmac-sha1` or `aes-cbc-hmac-sha256`), the encryption key and,
+ for AES-CBC, the required authentication key. Numbers and keys are hexadecimal, optionally prefixed with `0x`.
+ Empty lines and lines starting with `#` are ignored.
+ */
+pub fn parse_security_associations(text: &str) -> io::Result<Vec<SecurityAssociation>> {
+    let mut associations = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let spi = fields.first().ok_or_else(|| invalid("Missing SPI"))?;
+        let spi = u32::from_str_radix(spi.strip_prefix("0x").unwrap_or(spi), 16).map_err(|_| invalid("Invalid SPI"))?;
+        let algorithm = match fields.get(1) {
+            Some(&"aes-gcm") => Algorithm::AesGcm,
+            Some(&"aes-cbc-hmac-sha1") => Algorithm::AesCbcHmacSha1,
+            Some(&"aes-cbc-hmac-sha256") => Algorithm::AesCbcHmacSha256,
+            _ => return Err(invalid("Unsupported ESP algorithm")),
+        };
+        let encryption_key = parse_hex(fields.get(2).ok_or_else(|| invalid("Missing encryption key"))?)?;
+        let authentication_key = match fields.get(3) {
+            Some(key) => parse_hex(key)?,
+            None => Vec::new(),
+        };
+        associations.push(SecurityAssociation::new(spi, algorithm, encryption_key, authentication_key)?);
+    }
+    Ok(associations)
+}
+
+/**
+ * Load security associations from a file in the format of `parse_security_associations`.
+ */
+pub fn load_security_associations(path: &str) -> io::Result<Vec<SecurityAssociation>> {
+    parse_security_associations(&fs::read_to_string(path)?)
+}
+
+fn gcm_decrypt<C>(cipher: &AesGcm<C, U12>, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8], icv: &[u8]) -> io::Result<Vec<u8>>
+where
+    C: BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt,
+{
+    let mut plaintext = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(nonce.into(), aad, &mut plaintext, icv.into())
+        .map_err(|_| invalid("ESP ICV mismatch, the payload failed authentication"))?;
+    Ok(plaintext)
+}
+
+fn cbc_decrypt<C>(cipher: &C, iv: &[u8], ciphertext: &[u8]) -> io::Result<Vec<u8>>
+where
+    C: BlockCipher + BlockDecryptMut + BlockSizeUser<BlockSize = U16> + Clone,
+{
+    let mut plaintext = ciphertext.to_vec();
+    cbc::Decryptor::inner_iv_slice_init(cipher.clone(), iv)
+        .map_err(|_| invalid("Invalid ESP IV"))?
+        .decrypt_padded_mut::<NoPadding>(&mut plaintext)
+        .map_err(|_| invalid("ESP ciphertext is not a multiple of the block size"))?;
+    Ok(plaintext)
+}
+
+/**
+ ### Decrypt the payload of an ESP packet with a security association
+
+ The data starts with the ESP header. The payload is followed by padding, the padding length, the next header and the ICV.
+ */
+pub fn decrypt(data: &[u8], association: &SecurityAssociation) -> io::Result<Decrypted> {
+    let algorithm = association.algorithm;
+    let iv_end = Header::size() + algorithm.iv_length();
+    let icv_start = data.len().checked_sub(algorithm.icv_length()).filter(|start| *start >= iv_end + 2);
+    let icv_start = icv_start.ok_or_else(|| invalid("Not enough data to decrypt ESP payload"))?;
+    let iv = &data[Header::size()..iv_end];
+    let ciphertext = &data[iv_end..icv_start];
+    let icv = &data[icv_start..];
+
+    // RFC 4106 nonces are the salt followed by the IV.
+    let mut nonce = [0u8; 12];
+    if algorithm == Algorithm::AesGcm {
+        nonce[..4].copy_from_slice(&association.encryption_key[association.encryption_key.len() - 4..]);
+        nonce[4..].copy_from_slice(iv);
+    }
+    let aad = &data[..Header::size()];
+    let plaintext = match &association.cipher {
+        Cipher::Gcm128(cipher) => gcm_decrypt(cipher, &nonce, aad, ciphertext, icv)?,
+        Cipher::Gcm192(cipher) => gcm_decrypt(cipher, &nonce, aad, ciphertext, icv)?,
+        Cipher::Gcm256(cipher) => gcm_decrypt(cipher, &nonce, aad, ciphertext, icv)?,
+        Cipher::Cbc128(cipher) => cbc_decrypt(cipher, iv, ciphertext)?,
+        Cipher::Cbc192(cipher) => cbc_decrypt(cipher, iv, ciphertext)?,
+        Cipher::Cbc256(cipher) => cbc_decrypt(cipher, iv, ciphertext)?,
+    };
+    // AES-GCM checked the ICV while decrypting, AES-CBC is followed by an HMAC.
+    let authenticated = match &association.authenticator {
+        Some(authenticator) => authenticator.verify(&data[..icv_start], icv),
+        None => true,
+    };
+
+    let next_header = plaintext[plaintext.len() - 1];
+    let padding_length = plaintext[plaintext.len() - 2];
+    let payload_end = (plaintext.len() - 2)
+        .checked_sub(padding_length as usize)
+        .ok_or_else(|| invalid("ESP padding exceeds the payload, the key is probably wrong"))?;
+
+    Ok(Decrypted {
+        payload: plaintext[..payload_end].to_vec(),
+        padding_length,
+        next_header: AIPN::from(next_header),
+        icv: icv.to_vec(),
+        authenticated,
+    })
+}
+
+/**
+ ### Parse the ESP header from the data
+
+ The payload is decrypted when one of the security associations matches the SPI. A failed decryption is logged, not an error.
+ */
+pub fn parse(data: &[u8], associations: &[SecurityAssociation]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse ESP header",
+        ));
+    }
+
+    let spi = read_u32_be(data, 0)?;
+    let decrypted = match associations.iter().find(|association| association.spi == spi) {
+        Some(association) => match decrypt(data, association) {
+            Ok(decrypted) => Some(decrypted),
+            Err(e) => {
+                log::warn!("Failed to decrypt ESP payload of SPI {:#x}: {}", spi, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(Header {
+        spi,
+        sequence_number: read_u32_be(data, 4)?,
+        decrypted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_security_associations() {
+        let text = "# Lab tunnel\n0x1001 aes-gcm 0x000102030405060708090a0b0c0d0e0f01020304\n\n2002 aes-cbc-hmac-sha1 000102030405060708090a0b0c0d0e0f 0xaabb\n";
+        let associations = parse_security_associations(text).unwrap();
+
+        assert_eq!(associations.len(), 2);
+        assert_eq!(associations[0].spi, 0x1001);
+        assert_eq!(associations[0].algorithm, Algorithm::AesGcm);
+        assert_eq!(associations[0].encryption_key.len(), 20);
+        assert_eq!(associations[1].algorithm, Algorithm::AesCbcHmacSha1);
+        assert_eq!(associations[1].authentication_key, [0xAA, 0xBB]);
+
+        assert!(parse_security_associations("1 aes-gcm 0011").is_err());
+        assert!(parse_security_associations("1 aes-cbc-hmac-sha1 a\u{e9}a").is_err());
+        // AES-CBC can't be used without its HMAC.
+        assert!(parse_security_associations("1 aes-cbc-hmac-sha256 000102030405060708090a0b0c0d0e0f").is_err());
+    }
+
+    #[test]
+    fn decrypts_aes_cbc_payload() {
+        let key = [0x42; 16];
+        let iv = [0x07; 16];
+
+        // One block of plaintext: 12 payload bytes, 2 bytes of padding, the padding length and next header 59 (no next header).
+        let mut block = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 1, 2, 2, 59];
+        for (byte, iv) in block.iter_mut().zip(iv.iter()) {
+            *byte ^= iv;
+        }
+        Aes128::new(&key.into()).encrypt_block((&mut block).into());
+
+        let mut data = vec![0x00, 0x00, 0x20, 0x02, 0x00, 0x00, 0x00, 0x01];
+        data.extend_from_slice(&iv);
+        data.extend_from_slice(&block);
+        let icv = <Hmac<Sha1> as Mac>::new_from_slice(&[0xAA; 20]).unwrap().chain_update(&data).finalize().into_bytes();
+        data.extend_from_slice(&icv[..12]);
+
+        let association = SecurityAssociation::new(0x2002, Algorithm::AesCbcHmacSha1, key.to_vec(), vec![0xAA; 20]).unwrap();
+        let header = parse(&data, std::slice::from_ref(&association)).unwrap();
+        let decrypted = header.decrypted.unwrap();
+
+        assert_eq!(header.sequence_number, 1);
+        assert_eq!(decrypted.payload, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(decrypted.padding_length, 2);
+        assert!(matches!(decrypted.next_header, AIPN::Ipv6NoNxt));
+        assert_eq!(decrypted.icv, icv[..12]);
+        assert!(decrypted.authenticated);
+
+        // A flipped bit in the IV still decrypts, but fails authentication.
+        data[10] ^= 0x01;
+        let header = parse(&data, &[association]).unwrap();
+        assert!(!header.decrypted.unwrap().authenticated);
+    }
+
+    #[test]
+    fn decrypts_aes_gcm_payload() {
+        let key = [0x42; 16];
+        let salt = [0x01, 0x02, 0x03, 0x04];
+        let iv = [0x07; 8];
+        let header = [0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x02];
+
+        // Four payload bytes, no padding and next header 59 (no next header).
+        let mut ciphertext = vec![1, 2, 3, 4, 0, 59];
+        let nonce = [salt.as_slice(), &iv].concat();
+        let icv = AesGcm::<Aes128, U12>::new(&key.into())
+            .encrypt_in_place_detached(nonce.as_slice().into(), &header, &mut ciphertext)
+            .unwrap();
+        let mut data = [header.as_slice(), &iv, &ciphertext, &icv].concat();
+
+        let association = SecurityAssociation::new(0x1001, Algorithm::AesGcm, [key.as_slice(), &salt].concat(), Vec::new()).unwrap();
+        let decrypted = parse(&data, std::slice::from_ref(&association)).unwrap().decrypted.unwrap();
+
+        assert_eq!(decrypted.payload, [1, 2, 3, 4]);
+        assert!(matches!(decrypted.next_header, AIPN::Ipv6NoNxt));
+        assert!(decrypted.authenticated);
+
+        // A payload that fails authentication isn't decrypted.
+        data[8] ^= 0x01;
+        assert!(parse(&data, &[association]).unwrap().decrypted.is_none());
+    }
+}
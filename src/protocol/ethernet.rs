@@ -1,7 +1,9 @@
-use std::io::{self, ErrorKind};
+use std::{fmt, io::{self, ErrorKind}, str::FromStr};
 
+use super::oui::{Registry, Vendor};
 
-#[derive(Debug,Clone, Copy)]
+
+#[derive(Debug,Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MacAddress {
     pub bytes: [u8; 6],
 }
@@ -11,35 +13,224 @@ impl MacAddress {
         MacAddress { bytes }
     }
 
+    /**
+     * ff:ff:ff:ff:ff:ff.
+     */
+    pub fn is_broadcast(&self) -> bool {
+        self.bytes == [0xFF; 6]
+    }
+
+    /**
+     * The individual/group bit is set. Broadcast is a multicast address too.
+     */
+    pub fn is_multicast(&self) -> bool {
+        self.bytes[0] & 0x01 != 0
+    }
+
+    /**
+     * The universal/local bit is set, so the address was not assigned by the vendor.
+     */
+    pub fn is_locally_administered(&self) -> bool {
+        self.bytes[0] & 0x02 != 0
+    }
+
+    /**
+     * The organizationally unique identifier, the first 3 bytes.
+     */
+    pub fn oui(&self) -> [u8; 3] {
+        [self.bytes[0], self.bytes[1], self.bytes[2]]
+    }
+
+    /**
+     * The vendor from the embedded OUI registry. Use `vendor_with` to look it up in a registry loaded from a manuf file instead.
+     */
+    pub fn vendor(&self) -> Option<&'static Vendor> {
+        self.vendor_with(Registry::embedded())
+    }
+
+    /**
+     * The vendor from the given OUI registry, e.g. one loaded with `oui::Registry::load`.
+     */
+    pub fn vendor_with<'a>(&self, registry: &'a Registry) -> Option<&'a Vendor> {
+        registry.lookup(self)
+    }
+
+    /**
+     * The address with the vendor in place of the OUI, e.g. `Intel_3a:4f:1c`, from the embedded OUI registry.
+     */
+    pub fn resolved(&self) -> String {
+        self.resolved_with(Registry::embedded())
+    }
+
+    /**
+     * The address with the vendor in place of the OUI, from the given OUI registry.
+     */
+    pub fn resolved_with(&self, registry: &Registry) -> String {
+        registry.resolve(self)
+    }
 }
 
-#[derive(Debug,Clone, Copy)]
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.bytes;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", a, b, c, d, e, g)
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = io::Error;
+
+    /**
+     * Parse six hexadecimal bytes separated by colons or dashes.
+     */
+    fn from_str(text: &str) -> io::Result<MacAddress> {
+        let invalid = || io::Error::new(ErrorKind::InvalidData, format!("Invalid MAC address: {}", text));
+        let parts: Vec<&str> = text.split([':', '-']).collect();
+        if parts.len() != 6 {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 6];
+        for (byte, part) in bytes.iter_mut().zip(parts) {
+            if part.is_empty() || part.len() > 2 {
+                return Err(invalid());
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        Ok(MacAddress::new(bytes))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/**
+ ### How to treat the last 4 bytes of a frame
+ * `Absent`: The capture never includes the frame check sequence.
+ * `Detect`: The last 4 bytes are treated as FCS only if they match the CRC32 of the frame.
+ * `Present`: The capture always includes the frame check sequence, and it is verified.
+ */
+pub enum FcsMode {
+    Absent,
+    #[default]
+    Detect,
+    Present,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Frame check sequence status
+ * `Absent`: No FCS was found at the end of the frame.
+ * `Good`: The FCS matches the CRC32 of the frame.
+ * `Bad`: The FCS does not match, `expected` holds the CRC32 computed over the frame.
+ */
+pub enum Fcs {
+    Absent,
+    Good(u32),
+    Bad { found: u32, expected: u32 },
+}
+
+#[derive(Debug,Clone)]
 /**
  * Ethernet header structure
+ * `fcs`: The status of the frame check sequence at the end of the frame.
+ * `trailer`: Bytes between the end of the payload and the FCS, usually padding up to the minimum frame size.
  */
 pub struct Header {
     pub destination_mac: MacAddress,
     pub source_mac: MacAddress,
     pub ether_type: EtherType,
+    pub fcs: Fcs,
+    pub trailer: Vec<u8>,
 }
 
 impl Header {
     pub fn size() -> usize {
         14
     }
+
+    /**
+     * Return the payload of the frame, without the Ethernet header, trailer and FCS.
+     */
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let fcs = match self.fcs {
+            Fcs::Absent => 0,
+            _ => 4,
+        };
+        &data[Header::size()..data.len() - fcs - self.trailer.len()]
+    }
+}
+
+/**
+ * Compute the IEEE 802.3 CRC32 used as the Ethernet frame check sequence.
+ */
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+/**
+ * Determine the length of the payload from the length fields of the carried protocol, if it has any.
+ */
+fn payload_length(ether_type: EtherType, payload: &[u8]) -> Option<usize> {
+    match ether_type {
+        EtherType::Length(length) => Some(length as usize),
+        // A total length of 0 means segmentation offload, and the whole payload is the packet.
+        EtherType::IPv4 if payload.len() >= 4 && payload[0] >> 4 == 4 => {
+            Some(u16::from_be_bytes([payload[2], payload[3]]) as usize).filter(|length| *length != 0)
+        }
+        EtherType::IPv6 if payload.len() >= 6 && payload[0] >> 4 == 6 => {
+            Some(u16::from_be_bytes([payload[4], payload[5]]) as usize + 40)
+        }
+        EtherType::Arp | EtherType::Rarp if payload.len() >= 6 => {
+            Some(8 + 2 * (payload[4] as usize + payload[5] as usize))
+        }
+        _ => None,
+    }
 }
 
 
 #[derive(Debug,Clone, Copy)]
 /**
- * EtherType enumeration. It can be either IPv4, IPv6, or Unsupported in which case it stores the actual value.
+ * EtherType enumeration. It can be either IPv4, IPv6, ARP, RARP, MPLS, PPPoE, an IEEE 802.3 length, or Unsupported in which case it stores the actual value.
  */
 pub enum EtherType {
     IPv4,
     IPv6,
+    Arp,
+    Rarp,
+    MplsUnicast,
+    MplsMulticast,
+    PppoeDiscovery,
+    PppoeSession,
+    Length(u16),  // IEEE 802.3 frame, stores the length of the LLC payload
     Unsupported(u16),  // Stores the actual value for unsupported or unrecognized EtherTypes
 }
 
+impl EtherType {
+    /**
+     * Values up to 1500 are IEEE 802.3 lengths, values from 1536 and up are Ethernet II EtherTypes.
+     */
+    pub fn from_u16(ether_type: u16) -> EtherType {
+        match ether_type {
+            0x0000..=0x05DC => EtherType::Length(ether_type),
+            0x0800 => EtherType::IPv4,
+            0x86DD => EtherType::IPv6,
+            0x0806 => EtherType::Arp,
+            0x8035 => EtherType::Rarp,
+            0x8847 => EtherType::MplsUnicast,
+            0x8848 => EtherType::MplsMulticast,
+            0x8863 => EtherType::PppoeDiscovery,
+            0x8864 => EtherType::PppoeSession,
+            _      => EtherType::Unsupported(ether_type),
+        }
+    }
+}
+
 /**
  ### Parse the Ethernet header from the data
 
@@ -47,8 +238,13 @@ pub enum EtherType {
     * Destination MAC address (6 bytes)
     * Source MAC address (6 bytes)
     * EtherType (2 bytes)
+
+ IEEE 802.3 frames share the layout, but the last field holds the length of the LLC payload instead of an EtherType.
+
+ The frame may end with a 4 byte frame check sequence, which is handled according to `fcs_mode`.
+ When the carried protocol announces its length (IPv4, IPv6 or an 802.3 length), the bytes beyond it are returned as the trailer.
  */
-pub fn parse(data: &[u8]) -> io::Result<Header> {
+pub fn parse(data: &[u8], fcs_mode: FcsMode) -> io::Result<Header> {
     // Make sure there's enough data to parse an Ethernet header.
     if data.len() < 14 {
         return Err(io::Error::new(
@@ -86,10 +282,31 @@ pub fn parse(data: &[u8]) -> io::Result<Header> {
     let ether_type = u16::from_be_bytes([data[12], data[13]]);
     
     // Match the EtherType to the corresponding enum variant.
-    let ether_type = match ether_type {
-        0x0800 => EtherType::IPv4,
-        0x86DD => EtherType::IPv6,
-        _      => EtherType::Unsupported(ether_type),
+    let ether_type = EtherType::from_u16(ether_type);
+
+    // Check the last 4 bytes against the CRC32 of everything before them.
+    let fcs = if data.len() >= Header::size() + 4 && fcs_mode != FcsMode::Absent {
+        let (frame, found) = data.split_at(data.len() - 4);
+        let found = u32::from_le_bytes([found[0], found[1], found[2], found[3]]);
+        let expected = crc32(frame);
+        match (found == expected, fcs_mode) {
+            (true, _) => Fcs::Good(found),
+            (false, FcsMode::Present) => Fcs::Bad { found, expected },
+            (false, _) => Fcs::Absent,
+        }
+    } else {
+        Fcs::Absent
+    };
+    let end = match fcs {
+        Fcs::Absent => data.len(),
+        _ => data.len() - 4,
+    };
+
+    // Everything between the announced end of the payload and the FCS is trailer.
+    let payload = &data[Header::size()..end];
+    let trailer = match payload_length(ether_type, payload) {
+        Some(length) if length < payload.len() => payload[length..].to_vec(),
+        _ => Vec::new(),
     };
 
     // Store the parsed data.
@@ -97,5 +314,68 @@ pub fn parse(data: &[u8]) -> io::Result<Header> {
         destination_mac,
         source_mac,
         ether_type,
+        fcs,
+        trailer,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with_padding() -> Vec<u8> {
+        let mut frame = vec![0xFF; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        // Minimal IPv4 header with a total length of 20, followed by padding.
+        frame.extend_from_slice(&[0x45, 0x00, 0x00, 0x14]);
+        frame.extend_from_slice(&[0x00; 16]);
+        frame.extend_from_slice(&[0x00; 26]);
+        frame
+    }
+
+    #[test]
+    fn computes_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn detects_fcs_and_trailer() {
+        let mut frame = frame_with_padding();
+        let fcs = crc32(&frame);
+        frame.extend_from_slice(&fcs.to_le_bytes());
+
+        let header = parse(&frame, FcsMode::Detect).unwrap();
+
+        assert_eq!(header.fcs, Fcs::Good(fcs));
+        assert_eq!(header.trailer.len(), 26);
+        assert_eq!(header.payload(&frame).len(), 20);
+    }
+
+    #[test]
+    fn reports_bad_fcs_when_present() {
+        let mut frame = frame_with_padding();
+        let fcs = crc32(&frame);
+        frame.extend_from_slice(&(fcs ^ 1).to_le_bytes());
+
+        assert_eq!(parse(&frame, FcsMode::Detect).unwrap().fcs, Fcs::Absent);
+        assert_eq!(
+            parse(&frame, FcsMode::Present).unwrap().fcs,
+            Fcs::Bad { found: fcs ^ 1, expected: fcs }
+        );
+    }
+
+    #[test]
+    fn classifies_and_formats_mac_addresses() {
+        let mac: MacAddress = "01-00-5E-7F-00-01".parse().unwrap();
+        assert!(mac.is_multicast());
+        assert!(!mac.is_broadcast());
+        assert_eq!(mac.oui(), [0x01, 0x00, 0x5E]);
+        assert_eq!(mac.to_string(), "01:00:5e:7f:00:01");
+
+        let mac = MacAddress::new([0x02, 0x42, 0xAC, 0x11, 0x00, 0x02]);
+        assert!(mac.is_locally_administered());
+        assert!(!mac.is_multicast());
+        assert!(MacAddress::new([0xFF; 6]).is_broadcast());
+        assert!("00:11:22:33:44".parse::<MacAddress>().is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use super::{ipv4::internet_checksum, options::Options, parse::parse_datagram, Protocol};
+
+/**
+ * Largest datagram that can be reassembled, bounded by the 16 bit length fields of the IPv4 and IPv6 headers.
+ */
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/**
+ ### How overlapping fragments are handled
+ * `KeepFirst`: Bytes that were already received are kept. The default.
+ * `KeepLast`: Later fragments overwrite the bytes of earlier ones.
+ * `Discard`: The whole datagram is dropped, as RFC 5722 requires for IPv6. Exact duplicates are ignored.
+ */
+pub enum OverlapPolicy {
+    #[default]
+    KeepFirst,
+    KeepLast,
+    Discard,
+}
+
+#[derive(Debug, Clone)]
+/**
+ ### Settings of the fragment reassembler
+ * `timeout`: Seconds after its first fragment after which an incomplete datagram is dropped. 30 by default, like Linux.
+ * `memory_limit`: Bytes of fragment data that are buffered at most. The oldest datagrams are dropped to make room. 4 MiB by default.
+ * `overlap_policy`: How overlapping fragments are handled.
+ */
+pub struct Settings {
+    pub timeout: u32,
+    pub memory_limit: usize,
+    pub overlap_policy: OverlapPolicy,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            timeout: 30,
+            memory_limit: 4 * 1024 * 1024,
+            overlap_policy: OverlapPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/**
+ * Identifies the datagram a fragment belongs to. For IPv6, the protocol is the next header of the fragment header.
+ */
+pub struct Key {
+    pub source: Vec<u8>,
+    pub destination: Vec<u8>,
+    pub protocol: u8,
+    pub identification: u32,
+}
+
+#[derive(Debug)]
+/**
+ ### Fragment of an IPv4 or IPv6 datagram, collected when `ip_reassembly` is enabled
+ * Key: The datagram the fragment belongs to.
+ * Offset: The offset of the data in the reassembled payload, in bytes.
+ * More Fragments: Whether fragments follow this one.
+ * Header: The header bytes that precede the fragmentable part. For IPv6, the fragment header is removed and the preceding next header field points to the fragmented protocol.
+ * Data: The fragment data.
+ */
+pub struct Fragment {
+    pub key: Key,
+    pub offset: usize,
+    pub more_fragments: bool,
+    pub header: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### Reassembled datagram
+ * Key: The datagram the fragments belonged to.
+ * Fragments: The number of fragments the datagram was reassembled from.
+ * Data: The whole datagram, with the header of the first fragment and its length, fragment and checksum fields updated.
+ */
+pub struct Datagram {
+    pub key: Key,
+    pub fragments: usize,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    header: Option<Vec<u8>>,
+    data: Vec<u8>,
+    received: Vec<bool>,
+    length: Option<usize>,
+    fragments: usize,
+    first_seen: u32,
+}
+
+#[derive(Debug)]
+/**
+ ### IPv4 and IPv6 fragment reassembler
+
+ Keeps track of fragmented datagrams across packets. A datagram is complete once its first and last fragment and every byte
+ in between have arrived. Incomplete datagrams are dropped after the timeout, or when the memory limit is reached.
+ */
+pub struct Reassembler {
+    settings: Settings,
+    pending: HashMap<Key, Pending>,
+    memory: usize,
+}
+
+impl Reassembler {
+    pub fn new(settings: Settings) -> Reassembler {
+        Reassembler {
+            settings,
+            pending: HashMap::new(),
+            memory: 0,
+        }
+    }
+
+    /**
+     * Feed a fragment captured at the given time in seconds to the reassembler, and return the datagram it completes if any.
+     */
+    pub fn push(&mut self, fragment: &Fragment, time: u32) -> Option<Datagram> {
+        self.expire(time);
+
+        let start = fragment.offset;
+        let end = start + fragment.data.len();
+        if end > MAX_DATAGRAM_SIZE {
+            log::warn!("Dropping fragment beyond the maximum datagram size: {:?}", fragment.key);
+            self.remove(&fragment.key);
+            return None;
+        }
+
+        let pending = self.pending.entry(fragment.key.clone()).or_insert_with(|| Pending {
+            header: None,
+            data: Vec::new(),
+            received: Vec::new(),
+            length: None,
+            fragments: 0,
+            first_seen: time,
+        });
+        if pending.data.len() < end {
+            self.memory += end - pending.data.len();
+            pending.data.resize(end, 0);
+            pending.received.resize(end, false);
+        }
+
+        let overlaps = pending.received[start..end].iter().any(|received| *received);
+        let duplicate = pending.received[start..end].iter().all(|received| *received)
+            && pending.data[start..end] == fragment.data[..];
+        let inconsistent_end = !fragment.more_fragments && pending.length.is_some_and(|length| length != end);
+        if inconsistent_end || (overlaps && !duplicate && self.settings.overlap_policy == OverlapPolicy::Discard) {
+            log::warn!("Dropping datagram with overlapping or inconsistent fragments: {:?}", fragment.key);
+            self.remove(&fragment.key);
+            return None;
+        }
+
+        let keep_last = self.settings.overlap_policy == OverlapPolicy::KeepLast;
+        for (index, byte) in fragment.data.iter().enumerate() {
+            if keep_last || !pending.received[start + index] {
+                pending.data[start + index] = *byte;
+                pending.received[start + index] = true;
+            }
+        }
+        if start == 0 && (keep_last || pending.header.is_none()) {
+            pending.header = Some(fragment.header.clone());
+        }
+        if !fragment.more_fragments {
+            pending.length = Some(end);
+        }
+        pending.fragments += 1;
+
+        self.enforce_memory_limit(&fragment.key);
+        self.complete(&fragment.key)
+    }
+
+    /**
+     * Run the reassembler on the protocols of a packet. When the packet completes a datagram, the datagram is appended
+     * to them followed by its dissection, as if it had arrived whole.
+     */
+    pub fn process(&mut self, protocols: &mut Vec<Protocol>, time: u32, options: &Options) {
+        let fragment = protocols.iter().rev().find_map(|protocol| match protocol {
+            Protocol::Fragment(fragment) => Some(fragment),
+            _ => None,
+        });
+        let datagram = match fragment {
+            Some(fragment) => self.push(fragment, time),
+            None => None,
+        };
+        if let Some(datagram) = datagram {
+            let dissected = parse_datagram(&datagram.data, options);
+            protocols.push(Protocol::Reassembled(datagram));
+            protocols.extend(dissected);
+        }
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Pending> {
+        let pending = self.pending.remove(key)?;
+        self.memory -= pending.data.len();
+        Some(pending)
+    }
+
+    fn expire(&mut self, time: u32) {
+        let timeout = self.settings.timeout;
+        let expired: Vec<Key> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| time.saturating_sub(pending.first_seen) > timeout)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            log::debug!("Fragment reassembly timed out: {:?}", key);
+            self.remove(&key);
+        }
+    }
+
+    /**
+     * Drop the oldest datagrams until the buffered data fits the memory limit, dropping the current one last.
+     */
+    fn enforce_memory_limit(&mut self, current: &Key) {
+        while self.memory > self.settings.memory_limit {
+            let oldest = self
+                .pending
+                .iter()
+                .filter(|(key, _)| *key != current)
+                .min_by_key(|(_, pending)| pending.first_seen)
+                .map(|(key, _)| key.clone())
+                .unwrap_or_else(|| current.clone());
+            log::warn!("Fragment reassembly memory limit reached, dropping {:?}", oldest);
+            self.remove(&oldest);
+        }
+    }
+
+    fn complete(&mut self, key: &Key) -> Option<Datagram> {
+        let pending = self.pending.get(key)?;
+        let length = pending.length?;
+        if pending.header.is_none() || pending.received.iter().any(|received| !*received) {
+            return None;
+        }
+        let pending = self.remove(key)?;
+        if pending.data.len() > length {
+            log::warn!("Dropping datagram with fragments beyond its end: {:?}", key);
+            return None;
+        }
+        let data = rebuild(&pending.header?, &pending.data)?;
+        Some(Datagram {
+            key: key.clone(),
+            fragments: pending.fragments,
+            data,
+        })
+    }
+}
+
+/**
+ * Put the header of the first fragment in front of the payload, and fix up its length, fragment and checksum fields.
+ */
+fn rebuild(header: &[u8], payload: &[u8]) -> Option<Vec<u8>> {
+    let mut datagram = header.to_vec();
+    datagram.extend_from_slice(payload);
+    match header.first()? >> 4 {
+        4 => {
+            let total_length = u16::try_from(datagram.len()).ok()?;
+            datagram[2..4].copy_from_slice(&total_length.to_be_bytes());
+            // Keep the don't fragment flag, and clear the more fragments flag and the fragment offset.
+            datagram[6] &= 0x40;
+            datagram[7] = 0;
+            datagram[10..12].fill(0);
+            let checksum = internet_checksum(&datagram[..header.len()]);
+            datagram[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+        _ => {
+            let payload_length = u16::try_from(datagram.len() - 40).ok()?;
+            datagram[4..6].copy_from_slice(&payload_length.to_be_bytes());
+        }
+    }
+    Some(datagram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(offset: usize, more_fragments: bool, data: &[u8]) -> Fragment {
+        let mut header = vec![0x45, 0x00, 0x00, 0x00, 0x12, 0x34, 0x20, 0x00, 0x40, 0x11, 0x00, 0x00];
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        Fragment {
+            key: Key {
+                source: vec![10, 0, 0, 1],
+                destination: vec![10, 0, 0, 2],
+                protocol: 17,
+                identification: 0x1234,
+            },
+            offset,
+            more_fragments,
+            header,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn reassembles_out_of_order_fragments() {
+        let mut reassembler = Reassembler::new(Settings::default());
+
+        assert!(reassembler.push(&fragment(8, false, &[9, 10]), 0).is_none());
+        let datagram = reassembler.push(&fragment(0, true, &[1, 2, 3, 4, 5, 6, 7, 8]), 1).unwrap();
+
+        assert_eq!(datagram.fragments, 2);
+        assert_eq!(&datagram.data[20..], &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(u16::from_be_bytes([datagram.data[2], datagram.data[3]]), 30);
+        assert_eq!(datagram.data[6..8], [0, 0]);
+        assert_eq!(internet_checksum(&datagram.data[..20]), 0);
+        assert_eq!(reassembler.memory, 0);
+    }
+
+    #[test]
+    fn applies_overlap_policy() {
+        let mut keep_first = Reassembler::new(Settings::default());
+        keep_first.push(&fragment(0, true, &[1; 16]), 0);
+        let datagram = keep_first.push(&fragment(8, false, &[2; 16]), 0).unwrap();
+        assert_eq!(&datagram.data[20..], [&[1; 16][..], &[2; 8]].concat());
+
+        let mut discard = Reassembler::new(Settings { overlap_policy: OverlapPolicy::Discard, ..Settings::default() });
+        discard.push(&fragment(0, true, &[1; 16]), 0);
+        assert!(discard.push(&fragment(8, false, &[2; 16]), 0).is_none());
+        assert!(discard.push(&fragment(16, false, &[2; 8]), 0).is_none());
+    }
+
+    #[test]
+    fn drops_expired_and_oversized_datagrams() {
+        let mut reassembler = Reassembler::new(Settings { memory_limit: 16, ..Settings::default() });
+
+        reassembler.push(&fragment(0, true, &[1; 8]), 0);
+        assert!(reassembler.push(&fragment(8, false, &[2; 8]), 31).is_none());
+
+        assert!(reassembler.push(&fragment(16, false, &[3; 8]), 40).is_none());
+        assert!(reassembler.pending.is_empty());
+        assert_eq!(reassembler.memory, 0);
+    }
+}
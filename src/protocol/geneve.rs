@@ -0,0 +1,135 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+#[derive(Debug)]
+/**
+ ### Geneve option
+ * Option Class (16 bits): The namespace of the type, e.g. 0x0103 for Open vSwitch.
+ * Type (8 bits): The option type. The high bit marks the option as critical.
+ * Length (5 bits): The length of the data in 4 byte units.
+ * Data (variable)
+ */
+pub struct GeneveOption {
+    pub option_class: u16,
+    pub option_type: u8,
+    pub critical: bool,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### Geneve header structure (RFC 8926)
+ * Version (2 bits): Always 0.
+ * Options Length (6 bits): The length of the options in 4 byte units.
+ * OAM (1 bit): The packet carries control messages.
+ * Critical Options Present (1 bit): At least one option is critical.
+ * Protocol Type (16 bits): The EtherType of the payload, 0x6558 for Ethernet.
+ * Virtual Network Identifier (VNI) (24 bits): The overlay network the packet belongs to.
+ * Options (variable)
+ */
+pub struct Header {
+    pub version: u8,
+    pub options_length: u8,
+    pub oam: bool,
+    pub critical_options_present: bool,
+    pub protocol_type: u16,
+    pub vni: u32,
+    pub options: Vec<GeneveOption>,
+}
+
+impl Header {
+    /**
+     * Size of the header including the options.
+     */
+    pub fn size(&self) -> usize {
+        8 + self.options_length as usize * 4
+    }
+}
+
+fn parse_options(data: &[u8]) -> io::Result<Vec<GeneveOption>> {
+    let mut options = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        if offset + 4 > data.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated Geneve option"));
+        }
+        let option_type = data[offset + 2];
+        let length = (data[offset + 3] & 0x1F) as usize * 4;
+        let option_data = match data.get(offset + 4..offset + 4 + length) {
+            Some(option_data) => option_data,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Geneve option exceeds the header")),
+        };
+        options.push(GeneveOption {
+            option_class: read_u16_be(data, offset)?,
+            option_type,
+            critical: option_type & 0x80 != 0,
+            data: option_data.to_vec(),
+        });
+        offset += 4 + length;
+    }
+    Ok(options)
+}
+
+/**
+ ### Parse the Geneve header and its options from the data
+
+ The header is followed by the payload announced by the protocol type, usually an Ethernet frame.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse Geneve header",
+        ));
+    }
+
+    let version = data[0] >> 6;
+    if version != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported Geneve version"));
+    }
+    let options_length = data[0] & 0x3F;
+    let end = 8 + options_length as usize * 4;
+    let options = match data.get(8..end) {
+        Some(options) => parse_options(options)?,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Not enough data to parse Geneve options")),
+    };
+
+    Ok(Header {
+        version,
+        options_length,
+        oam: data[1] & 0x80 != 0,
+        critical_options_present: data[1] & 0x40 != 0,
+        protocol_type: read_u16_be(data, 2)?,
+        vni: read_u32_be(data, 4)? >> 8,
+        options,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_options() {
+        let data = [
+            0x02, 0x40, 0x65, 0x58, 0x00, 0x00, 0x64, 0x00, // Two words of options, critical, Ethernet, VNI 100
+            0x01, 0x03, 0x80, 0x01, 0xDE, 0xAD, 0xBE, 0xEF, // Critical Open vSwitch option with 4 bytes of data
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.size(), 16);
+        assert_eq!(header.protocol_type, 0x6558);
+        assert_eq!(header.vni, 100);
+        assert!(header.critical_options_present);
+        assert_eq!(header.options.len(), 1);
+        assert_eq!(header.options[0].option_class, 0x0103);
+        assert!(header.options[0].critical);
+        assert_eq!(header.options[0].data, [0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn rejects_truncated_options() {
+        assert!(parse(&[0x02, 0x00, 0x65, 0x58, 0x00, 0x00, 0x64, 0x00, 0x01, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Protocol type of the GRE payload. Mostly EtherTypes, plus the GRE specific ones for bridging and ERSPAN.
+ */
+pub enum ProtocolType {
+    IPv4,
+    IPv6,
+    TransparentEthernetBridging,
+    ErspanTypeII,
+    ErspanTypeIII,
+    Ppp,
+    Mpls,
+    Unsupported(u16),
+}
+
+impl ProtocolType {
+    pub fn from_u16(protocol_type: u16) -> ProtocolType {
+        match protocol_type {
+            0x0800 => ProtocolType::IPv4,
+            0x86DD => ProtocolType::IPv6,
+            0x6558 => ProtocolType::TransparentEthernetBridging,
+            0x88BE => ProtocolType::ErspanTypeII,
+            0x22EB => ProtocolType::ErspanTypeIII,
+            0x880B => ProtocolType::Ppp,
+            0x8847 => ProtocolType::Mpls,
+            _ => ProtocolType::Unsupported(protocol_type),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### ERSPAN header, preceding the mirrored Ethernet frame
+ * `TypeI`: No header at all. Used when protocol type 0x88BE is sent without a sequence number.
+ * `TypeII`: VLAN (12 bits), class of service (3 bits), encapsulation type (2 bits), truncated flag, session ID (10 bits) and port index (20 bits).
+ * `TypeIII`: Like type II, plus a timestamp, the security group tag, the hardware ID, the direction and timestamp granularity,
+   and the platform specific subheader when present.
+ */
+pub enum Erspan {
+    TypeI,
+    TypeII {
+        vlan: u16,
+        cos: u8,
+        encapsulation: u8,
+        truncated: bool,
+        session_id: u16,
+        index: u32,
+    },
+    TypeIII {
+        vlan: u16,
+        cos: u8,
+        bad_or_short: u8,
+        truncated: bool,
+        session_id: u16,
+        timestamp: u32,
+        security_group_tag: u16,
+        frame_type: u8,
+        hardware_id: u8,
+        egress: bool,
+        granularity: u8,
+        platform: Option<Vec<u8>>,
+    },
+}
+
+impl Erspan {
+    pub fn size(&self) -> usize {
+        match self {
+            Erspan::TypeI => 0,
+            Erspan::TypeII { .. } => 8,
+            Erspan::TypeIII { platform, .. } => 12 + platform.as_ref().map_or(0, |platform| platform.len()),
+        }
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### GRE header structure
+ * Checksum Present, Routing Present, Key Present, Sequence Number Present (1 bit each): Which optional fields follow.
+ * Recursion Control (3 bits): Deprecated.
+ * Acknowledgment Present (1 bit): Only used by the enhanced GRE of PPTP (version 1).
+ * Version (3 bits): 0 for GRE, 1 for the enhanced GRE of PPTP (RFC 2637).
+ * Protocol Type (16 bits): The type of the payload.
+ * Checksum (16 bits) and Offset (16 bits): Present if the checksum or routing present bit is set.
+ * Key (32 bits): Present if the key present bit is set. For version 1, the payload length (16 bits) and call ID (16 bits).
+ * Sequence Number (32 bits): Present if the sequence number present bit is set.
+ * Acknowledgment Number (32 bits): Present if the acknowledgment present bit is set.
+ * Routing (variable): The source route entries of RFC 1701, if the routing present bit is set.
+ * ERSPAN: The ERSPAN header for ERSPAN payloads.
+ */
+pub struct Header {
+    pub recursion_control: u8,
+    pub version: u8,
+    pub protocol_type: ProtocolType,
+    pub checksum: Option<u16>,
+    pub offset: Option<u16>,
+    pub key: Option<u32>,
+    pub sequence_number: Option<u32>,
+    pub acknowledgment_number: Option<u32>,
+    pub routing: Option<Vec<u8>>,
+    pub erspan: Option<Erspan>,
+}
+
+impl Header {
+    /**
+     * Size of the GRE header including the optional fields and the ERSPAN header, i.e. the offset of the payload.
+     */
+    pub fn size(&self) -> usize {
+        let optional = [self.checksum.is_some(), self.key.is_some(), self.sequence_number.is_some(), self.acknowledgment_number.is_some()];
+        4 + optional.iter().filter(|present| **present).count() * 4
+            + self.routing.as_ref().map_or(0, |routing| routing.len())
+            + self.erspan.as_ref().map_or(0, |erspan| erspan.size())
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated GRE header")
+}
+
+fn parse_erspan(protocol_type: ProtocolType, sequenced: bool, data: &[u8]) -> io::Result<Option<Erspan>> {
+    let erspan = match protocol_type {
+        ProtocolType::ErspanTypeII if !sequenced => Erspan::TypeI,
+        ProtocolType::ErspanTypeII => {
+            let first = read_u32_be(data, 0).map_err(|_| truncated())?;
+            let second = read_u32_be(data, 4).map_err(|_| truncated())?;
+            Erspan::TypeII {
+                vlan: ((first >> 16) & 0x0FFF) as u16,
+                cos: ((first >> 13) & 0x07) as u8,
+                encapsulation: ((first >> 11) & 0x03) as u8,
+                truncated: first & 0x0400 != 0,
+                session_id: (first & 0x03FF) as u16,
+                index: second & 0x000F_FFFF,
+            }
+        }
+        ProtocolType::ErspanTypeIII => {
+            let first = read_u32_be(data, 0).map_err(|_| truncated())?;
+            let flags = read_u16_be(data, 10).map_err(|_| truncated())?;
+            let platform = if flags & 0x0001 != 0 {
+                Some(data.get(12..20).ok_or_else(truncated)?.to_vec())
+            } else {
+                None
+            };
+            Erspan::TypeIII {
+                vlan: ((first >> 16) & 0x0FFF) as u16,
+                cos: ((first >> 13) & 0x07) as u8,
+                bad_or_short: ((first >> 11) & 0x03) as u8,
+                truncated: first & 0x0400 != 0,
+                session_id: (first & 0x03FF) as u16,
+                timestamp: read_u32_be(data, 4)?,
+                security_group_tag: read_u16_be(data, 8)?,
+                frame_type: ((flags >> 10) & 0x1F) as u8,
+                hardware_id: ((flags >> 4) & 0x3F) as u8,
+                egress: flags & 0x0008 != 0,
+                granularity: ((flags >> 1) & 0x03) as u8,
+                platform,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(erspan))
+}
+
+/**
+ ### Parse the GRE header from the data
+
+ The optional fields follow the fixed header in the order checksum and offset, key, sequence number, acknowledgment number and routing.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse GRE header",
+        ));
+    }
+
+    let flags_version = read_u16_be(data, 0)?;
+    let checksum_present = flags_version & 0x8000 != 0;
+    let routing_present = flags_version & 0x4000 != 0;
+    let key_present = flags_version & 0x2000 != 0;
+    let sequence_present = flags_version & 0x1000 != 0;
+    let recursion_control = ((flags_version >> 8) & 0x07) as u8;
+    let acknowledgment_present = flags_version & 0x0080 != 0;
+    let version = (flags_version & 0x07) as u8;
+    if version > 1 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported GRE version"));
+    }
+    let protocol_type = ProtocolType::from_u16(read_u16_be(data, 2)?);
+
+    let mut offset = 4;
+    let mut read_optional = |present: bool| -> io::Result<Option<u32>> {
+        if !present {
+            return Ok(None);
+        }
+        let value = read_u32_be(data, offset).map_err(|_| truncated())?;
+        offset += 4;
+        Ok(Some(value))
+    };
+    let checksum_offset = read_optional(checksum_present || routing_present)?;
+    let key = read_optional(key_present)?;
+    let sequence_number = read_optional(sequence_present)?;
+    let acknowledgment_number = read_optional(version == 1 && acknowledgment_present)?;
+
+    let routing = if routing_present {
+        // Source route entries end with a null entry with address family 0 and length 0.
+        let start = offset;
+        loop {
+            let address_family = read_u16_be(data, offset).map_err(|_| truncated())?;
+            let length = *data.get(offset + 3).ok_or_else(truncated)? as usize;
+            offset += 4 + length;
+            if address_family == 0 && length == 0 {
+                break;
+            }
+        }
+        Some(data.get(start..offset).ok_or_else(truncated)?.to_vec())
+    } else {
+        None
+    };
+
+    let erspan = parse_erspan(protocol_type, sequence_present, &data[offset.min(data.len())..])?;
+
+    Ok(Header {
+        recursion_control,
+        version,
+        protocol_type,
+        checksum: checksum_offset.map(|value| (value >> 16) as u16),
+        offset: checksum_offset.map(|value| value as u16),
+        key,
+        sequence_number,
+        acknowledgment_number,
+        routing,
+        erspan,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_optional_fields() {
+        let data = [
+            0xB0, 0x00, 0x08, 0x00, // Checksum, key and sequence number present, IPv4
+            0x12, 0x34, 0x00, 0x00, // Checksum and offset
+            0x00, 0x00, 0x00, 0x2A, // Key
+            0x00, 0x00, 0x00, 0x07, // Sequence number
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.protocol_type, ProtocolType::IPv4);
+        assert_eq!(header.checksum, Some(0x1234));
+        assert_eq!(header.key, Some(42));
+        assert_eq!(header.sequence_number, Some(7));
+        assert_eq!(header.size(), 16);
+    }
+
+    #[test]
+    fn parses_erspan_type_ii() {
+        let data = [
+            0x10, 0x00, 0x88, 0xBE, 0x00, 0x00, 0x00, 0x01, // Sequence number present, ERSPAN
+            0x10, 0x64, 0x20, 0x05, 0x00, 0x00, 0x00, 0x03, // VLAN 100, COS 1, session 5, index 3
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.size(), 16);
+        assert_eq!(
+            header.erspan,
+            Some(Erspan::TypeII { vlan: 100, cos: 1, encapsulation: 0, truncated: false, session_id: 5, index: 3 })
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_le, read_u32_be};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Direction of the packet as seen from the host, taken from the pseudo-header.
+ */
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * H4 packet indicator, the first byte of every HCI packet on a UART transport.
+ */
+pub enum PacketType {
+    Command,
+    AclData,
+    ScoData,
+    Event,
+    IsoData,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Commonly seen HCI event codes. Everything else is stored as `Unsupported` with its code.
+ */
+pub enum EventCode {
+    InquiryComplete,
+    ConnectionComplete,
+    DisconnectionComplete,
+    EncryptionChange,
+    CommandComplete,
+    CommandStatus,
+    HardwareError,
+    NumberOfCompletedPackets,
+    LeMeta,
+    Unsupported(u8),
+}
+
+impl EventCode {
+    pub fn from_u8(event_code: u8) -> EventCode {
+        match event_code {
+            0x01 => EventCode::InquiryComplete,
+            0x03 => EventCode::ConnectionComplete,
+            0x05 => EventCode::DisconnectionComplete,
+            0x08 => EventCode::EncryptionChange,
+            0x0E => EventCode::CommandComplete,
+            0x0F => EventCode::CommandStatus,
+            0x10 => EventCode::HardwareError,
+            0x13 => EventCode::NumberOfCompletedPackets,
+            0x3E => EventCode::LeMeta,
+            _ => EventCode::Unsupported(event_code),
+        }
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### HCI command packet
+ * Opcode (16 bits): The OpCode Group Field (6 bits) and OpCode Command Field (10 bits).
+ * Parameter Total Length (8 bits): The length of the parameters.
+ * Parameters (variable): Command specific parameters.
+ */
+pub struct Command {
+    pub opcode: u16,
+    pub ogf: u8,
+    pub ocf: u16,
+    pub parameters: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### HCI event packet
+ * Event Code (8 bits): The type of the event.
+ * Parameter Total Length (8 bits): The length of the parameters.
+ * Parameters (variable): Event specific parameters.
+
+ For Command Complete and Command Status events the opcode of the command they answer is extracted, along with the status where it is at a fixed position.
+ For LE Meta events the subevent code is extracted.
+ */
+pub struct Event {
+    pub event_code: EventCode,
+    pub parameters: Vec<u8>,
+    pub command_opcode: Option<u16>,
+    pub status: Option<u8>,
+    pub le_subevent: Option<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### HCI ACL data packet
+ * Handle (12 bits): The connection handle.
+ * Packet Boundary Flag (2 bits): 0b00 and 0b10 mark the first fragment of an L2CAP packet, 0b01 a continuation.
+ * Broadcast Flag (2 bits): Point-to-point or broadcast.
+ * Data Total Length (16 bits): The length of the data.
+ */
+pub struct AclData {
+    pub handle: u16,
+    pub packet_boundary: u8,
+    pub broadcast: u8,
+    pub length: u16,
+}
+
+impl AclData {
+    /**
+     * Whether the data starts a new L2CAP packet, as opposed to continuing a fragmented one.
+     */
+    pub fn is_start(&self) -> bool {
+        self.packet_boundary == 0b00 || self.packet_boundary == 0b10
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### HCI synchronous (SCO) data packet
+ * Handle (12 bits): The connection handle.
+ * Packet Status Flag (2 bits): Reports missing or corrupted data.
+ * Data Total Length (8 bits): The length of the data.
+ */
+pub struct ScoData {
+    pub handle: u16,
+    pub packet_status: u8,
+    pub length: u8,
+}
+
+#[derive(Debug)]
+/**
+ * The HCI packet following the packet indicator.
+ */
+pub enum Packet {
+    Command(Command),
+    Event(Event),
+    AclData(AclData),
+    ScoData(ScoData),
+    Unsupported,
+}
+
+#[derive(Debug)]
+/**
+ ### Bluetooth HCI H4 header structure
+ * Direction (32 bits, optional): Pseudo-header of LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR, the lowest bit is set for received packets.
+ * Packet Type (8 bits): The H4 packet indicator.
+ * Packet: The decoded HCI packet.
+ */
+pub struct Header {
+    pub direction: Option<Direction>,
+    pub packet_type: PacketType,
+    pub packet: Packet,
+}
+
+impl Header {
+    /**
+     * Size of the pseudo-header, packet indicator and HCI header, i.e. the offset of the ACL or SCO payload.
+     */
+    pub fn size(&self) -> usize {
+        let pseudo_header = if self.direction.is_some() { 4 } else { 0 };
+        let hci_header = match self.packet {
+            Packet::Command(_) => 3,
+            Packet::Event(_) => 2,
+            Packet::AclData(_) => 4,
+            Packet::ScoData(_) => 3,
+            Packet::Unsupported => 0,
+        };
+        pseudo_header + 1 + hci_header
+    }
+}
+
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Truncated HCI {}", what))
+}
+
+fn parse_command(data: &[u8]) -> io::Result<Command> {
+    let opcode = read_u16_le(data, 0).map_err(|_| truncated("command"))?;
+    let length = *data.get(2).ok_or_else(|| truncated("command"))? as usize;
+    let parameters = data.get(3..3 + length).ok_or_else(|| truncated("command"))?.to_vec();
+    Ok(Command {
+        opcode,
+        ogf: (opcode >> 10) as u8,
+        ocf: opcode & 0x03FF,
+        parameters,
+    })
+}
+
+fn parse_event(data: &[u8]) -> io::Result<Event> {
+    if data.len() < 2 {
+        return Err(truncated("event"));
+    }
+    let event_code = EventCode::from_u8(data[0]);
+    let length = data[1] as usize;
+    let parameters = data.get(2..2 + length).ok_or_else(|| truncated("event"))?.to_vec();
+
+    // Command Complete: Num_HCI_Command_Packets, Command_Opcode, Return_Parameters (usually starting with the status).
+    // Command Status: Status, Num_HCI_Command_Packets, Command_Opcode.
+    let (command_opcode, status) = match event_code {
+        EventCode::CommandComplete => (read_u16_le(&parameters, 1).ok(), parameters.get(3).copied()),
+        EventCode::CommandStatus => (read_u16_le(&parameters, 2).ok(), parameters.first().copied()),
+        EventCode::ConnectionComplete | EventCode::DisconnectionComplete | EventCode::EncryptionChange => {
+            (None, parameters.first().copied())
+        }
+        _ => (None, None),
+    };
+    let le_subevent = match event_code {
+        EventCode::LeMeta => parameters.first().copied(),
+        _ => None,
+    };
+
+    Ok(Event {
+        event_code,
+        parameters,
+        command_opcode,
+        status,
+        le_subevent,
+    })
+}
+
+/**
+ ### Parse a Bluetooth HCI H4 packet from the data
+
+ When `with_pseudo_header` is set the packet is preceded by the 4 byte direction pseudo-header in network byte order.
+ The HCI fields themselves are little-endian. ACL and SCO payloads are left to the caller.
+ */
+pub fn parse(data: &[u8], with_pseudo_header: bool) -> io::Result<Header> {
+    let (direction, data) = if with_pseudo_header {
+        let direction = read_u32_be(data, 0).map_err(|_| truncated("pseudo-header"))?;
+        let direction = if direction & 0x01 == 1 { Direction::Received } else { Direction::Sent };
+        (Some(direction), &data[4..])
+    } else {
+        (None, data)
+    };
+
+    let packet_type = match data.first() {
+        Some(0x01) => PacketType::Command,
+        Some(0x02) => PacketType::AclData,
+        Some(0x03) => PacketType::ScoData,
+        Some(0x04) => PacketType::Event,
+        Some(0x05) => PacketType::IsoData,
+        Some(other) => PacketType::Unsupported(*other),
+        None => return Err(truncated("packet indicator")),
+    };
+
+    let body = &data[1..];
+    let packet = match packet_type {
+        PacketType::Command => Packet::Command(parse_command(body)?),
+        PacketType::Event => Packet::Event(parse_event(body)?),
+        PacketType::AclData => {
+            let handle = read_u16_le(body, 0).map_err(|_| truncated("ACL header"))?;
+            let length = read_u16_le(body, 2).map_err(|_| truncated("ACL header"))?;
+            Packet::AclData(AclData {
+                handle: handle & 0x0FFF,
+                packet_boundary: ((handle >> 12) & 0x03) as u8,
+                broadcast: ((handle >> 14) & 0x03) as u8,
+                length,
+            })
+        }
+        PacketType::ScoData => {
+            let handle = read_u16_le(body, 0).map_err(|_| truncated("SCO header"))?;
+            let length = *body.get(2).ok_or_else(|| truncated("SCO header"))?;
+            Packet::ScoData(ScoData {
+                handle: handle & 0x0FFF,
+                packet_status: ((handle >> 12) & 0x03) as u8,
+                length,
+            })
+        }
+        _ => Packet::Unsupported,
+    };
+
+    Ok(Header {
+        direction,
+        packet_type,
+        packet,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_command_complete_event() {
+        // Received, event, Command Complete for HCI_Reset (0x0C03) with status success.
+        let data = [0x00, 0x00, 0x00, 0x01, 0x04, 0x0E, 0x04, 0x01, 0x03, 0x0C, 0x00];
+        let header = parse(&data, true).unwrap();
+
+        assert_eq!(header.direction, Some(Direction::Received));
+        assert_eq!(header.packet_type, PacketType::Event);
+        match header.packet {
+            Packet::Event(event) => {
+                assert_eq!(event.event_code, EventCode::CommandComplete);
+                assert_eq!(event.command_opcode, Some(0x0C03));
+                assert_eq!(event.status, Some(0));
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_acl_header() {
+        let data = [0x00, 0x00, 0x00, 0x00, 0x02, 0x40, 0x20, 0x07, 0x00];
+        let header = parse(&data, true).unwrap();
+
+        assert_eq!(header.direction, Some(Direction::Sent));
+        assert_eq!(header.size(), 9);
+        match header.packet {
+            Packet::AclData(acl) => {
+                assert_eq!(acl.handle, 0x040);
+                assert_eq!(acl.packet_boundary, 0b10);
+                assert!(acl.is_start());
+                assert_eq!(acl.length, 7);
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+    }
+}
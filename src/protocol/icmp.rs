@@ -0,0 +1,269 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::{ipv4, Protocol};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the destination unreachable message.
+ */
+pub enum UnreachableCode {
+    NetworkUnreachable,
+    HostUnreachable,
+    ProtocolUnreachable,
+    PortUnreachable,
+    FragmentationNeeded,
+    SourceRouteFailed,
+    DestinationNetworkUnknown,
+    DestinationHostUnknown,
+    SourceHostIsolated,
+    NetworkProhibited,
+    HostProhibited,
+    NetworkUnreachableForTos,
+    HostUnreachableForTos,
+    CommunicationProhibited,
+    HostPrecedenceViolation,
+    PrecedenceCutoff,
+    Unsupported(u8),
+}
+
+impl UnreachableCode {
+    pub fn from_u8(code: u8) -> UnreachableCode {
+        match code {
+            0 => UnreachableCode::NetworkUnreachable,
+            1 => UnreachableCode::HostUnreachable,
+            2 => UnreachableCode::ProtocolUnreachable,
+            3 => UnreachableCode::PortUnreachable,
+            4 => UnreachableCode::FragmentationNeeded,
+            5 => UnreachableCode::SourceRouteFailed,
+            6 => UnreachableCode::DestinationNetworkUnknown,
+            7 => UnreachableCode::DestinationHostUnknown,
+            8 => UnreachableCode::SourceHostIsolated,
+            9 => UnreachableCode::NetworkProhibited,
+            10 => UnreachableCode::HostProhibited,
+            11 => UnreachableCode::NetworkUnreachableForTos,
+            12 => UnreachableCode::HostUnreachableForTos,
+            13 => UnreachableCode::CommunicationProhibited,
+            14 => UnreachableCode::HostPrecedenceViolation,
+            15 => UnreachableCode::PrecedenceCutoff,
+            _ => UnreachableCode::Unsupported(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the redirect message.
+ */
+pub enum RedirectCode {
+    Network,
+    Host,
+    TosAndNetwork,
+    TosAndHost,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the time exceeded message.
+ */
+pub enum TimeExceededCode {
+    TtlExceeded,
+    FragmentReassemblyTimeExceeded,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the parameter problem message.
+ */
+pub enum ParameterProblemCode {
+    PointerIndicatesError,
+    MissingRequiredOption,
+    BadLength,
+    Unsupported(u8),
+}
+
+#[derive(Debug)]
+/**
+ ### ICMP message
+ * `EchoRequest` (8) and `EchoReply` (0): Ping, with the identifier, sequence number and data.
+ * `DestinationUnreachable` (3): The next-hop MTU is set when fragmentation was needed (RFC 1191).
+ * `SourceQuench` (4): Deprecated congestion signal.
+ * `Redirect` (5): The gateway that should be used instead.
+ * `TimeExceeded` (11): TTL expired in transit or reassembly timed out.
+ * `ParameterProblem` (12): The pointer to the offending octet of the original header.
+ * `TimestampRequest` (13) and `TimestampReply` (14): Originate, receive and transmit timestamps in milliseconds since midnight UT.
+ * `Unknown`: Any other message, with its type, code, the rest of the header and the data.
+ */
+pub enum Message {
+    EchoReply { identifier: u16, sequence_number: u16, data: Vec<u8> },
+    EchoRequest { identifier: u16, sequence_number: u16, data: Vec<u8> },
+    DestinationUnreachable { code: UnreachableCode, next_hop_mtu: Option<u16> },
+    SourceQuench,
+    Redirect { code: RedirectCode, gateway: ipv4::Address },
+    TimeExceeded { code: TimeExceededCode },
+    ParameterProblem { code: ParameterProblemCode, pointer: u8 },
+    TimestampRequest { identifier: u16, sequence_number: u16, originate: u32, receive: u32, transmit: u32 },
+    TimestampReply { identifier: u16, sequence_number: u16, originate: u32, receive: u32, transmit: u32 },
+    Unknown { icmp_type: u8, code: u8, rest_of_header: u32, data: Vec<u8> },
+}
+
+impl Message {
+    /**
+     * Error messages quote the IP header and the first 8 bytes of the datagram that caused them.
+     */
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Message::DestinationUnreachable { .. }
+                | Message::SourceQuench
+                | Message::Redirect { .. }
+                | Message::TimeExceeded { .. }
+                | Message::ParameterProblem { .. }
+        )
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### ICMP header structure
+ * Type (8 bits): The type of the message.
+ * Code (8 bits): The subtype of the message.
+ * Checksum (16 bits): Checksum over the whole ICMP message.
+ * Message: The decoded message.
+ * Quoted: The dissected original datagram of error messages, starting with its IPv4 header.
+ */
+pub struct Header {
+    pub icmp_type: u8,
+    pub code: u8,
+    pub checksum: u16,
+    pub message: Message,
+    pub quoted: Vec<Protocol>,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+}
+
+/**
+ ### Parse the ICMP header and message from the data
+
+ The quoted datagram of error messages starts at `Header::size()` and is left to the caller to dissect into `quoted`.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse ICMP header",
+        ));
+    }
+
+    let icmp_type = data[0];
+    let code = data[1];
+    let checksum = read_u16_be(data, 2)?;
+    let identifier = read_u16_be(data, 4)?;
+    let sequence_number = read_u16_be(data, 6)?;
+    let timestamps = || -> io::Result<(u32, u32, u32)> {
+        match (read_u32_be(data, 8), read_u32_be(data, 12), read_u32_be(data, 16)) {
+            (Ok(originate), Ok(receive), Ok(transmit)) => Ok((originate, receive, transmit)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated ICMP timestamp message")),
+        }
+    };
+
+    let message = match icmp_type {
+        0 => Message::EchoReply { identifier, sequence_number, data: data[8..].to_vec() },
+        8 => Message::EchoRequest { identifier, sequence_number, data: data[8..].to_vec() },
+        3 => {
+            let code = UnreachableCode::from_u8(code);
+            let next_hop_mtu = match code {
+                UnreachableCode::FragmentationNeeded => Some(sequence_number),
+                _ => None,
+            };
+            Message::DestinationUnreachable { code, next_hop_mtu }
+        }
+        4 => Message::SourceQuench,
+        5 => Message::Redirect {
+            code: match code {
+                0 => RedirectCode::Network,
+                1 => RedirectCode::Host,
+                2 => RedirectCode::TosAndNetwork,
+                3 => RedirectCode::TosAndHost,
+                _ => RedirectCode::Unsupported(code),
+            },
+            gateway: ipv4::Address::new([data[4], data[5], data[6], data[7]]),
+        },
+        11 => Message::TimeExceeded {
+            code: match code {
+                0 => TimeExceededCode::TtlExceeded,
+                1 => TimeExceededCode::FragmentReassemblyTimeExceeded,
+                _ => TimeExceededCode::Unsupported(code),
+            },
+        },
+        12 => Message::ParameterProblem {
+            code: match code {
+                0 => ParameterProblemCode::PointerIndicatesError,
+                1 => ParameterProblemCode::MissingRequiredOption,
+                2 => ParameterProblemCode::BadLength,
+                _ => ParameterProblemCode::Unsupported(code),
+            },
+            pointer: data[4],
+        },
+        13 | 14 => {
+            let (originate, receive, transmit) = timestamps()?;
+            if icmp_type == 13 {
+                Message::TimestampRequest { identifier, sequence_number, originate, receive, transmit }
+            } else {
+                Message::TimestampReply { identifier, sequence_number, originate, receive, transmit }
+            }
+        }
+        _ => Message::Unknown {
+            icmp_type,
+            code,
+            rest_of_header: read_u32_be(data, 4)?,
+            data: data[8..].to_vec(),
+        },
+    };
+
+    Ok(Header {
+        icmp_type,
+        code,
+        checksum,
+        message,
+        quoted: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_echo_request() {
+        let header = parse(&[0x08, 0x00, 0xF7, 0xFD, 0x00, 0x01, 0x00, 0x02, b'h', b'i']).unwrap();
+
+        assert!(!header.message.is_error());
+        match header.message {
+            Message::EchoRequest { identifier, sequence_number, data } => {
+                assert_eq!(identifier, 1);
+                assert_eq!(sequence_number, 2);
+                assert_eq!(data, b"hi");
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_fragmentation_needed() {
+        let header = parse(&[0x03, 0x04, 0x00, 0x00, 0x00, 0x00, 0x05, 0xDC]).unwrap();
+
+        assert!(header.message.is_error());
+        assert!(matches!(
+            header.message,
+            Message::DestinationUnreachable { code: UnreachableCode::FragmentationNeeded, next_hop_mtu: Some(1500) }
+        ));
+    }
+}
@@ -0,0 +1,457 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::{ipv6::Address, Protocol};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the destination unreachable message.
+ */
+pub enum UnreachableCode {
+    NoRoute,
+    AdministrativelyProhibited,
+    BeyondScopeOfSource,
+    AddressUnreachable,
+    PortUnreachable,
+    SourceAddressFailedPolicy,
+    RejectRoute,
+    SourceRoutingHeaderError,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the time exceeded message.
+ */
+pub enum TimeExceededCode {
+    HopLimitExceeded,
+    FragmentReassemblyTimeExceeded,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Codes of the parameter problem message.
+ */
+pub enum ParameterProblemCode {
+    ErroneousHeaderField,
+    UnrecognizedNextHeader,
+    UnrecognizedOption,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Multicast address record types of MLDv2 reports (RFC 3810), shared with IGMPv3 group records.
+ */
+pub enum RecordType {
+    ModeIsInclude,
+    ModeIsExclude,
+    ChangeToInclude,
+    ChangeToExclude,
+    AllowNewSources,
+    BlockOldSources,
+    Unsupported(u8),
+}
+
+impl RecordType {
+    pub fn from_u8(record_type: u8) -> RecordType {
+        match record_type {
+            1 => RecordType::ModeIsInclude,
+            2 => RecordType::ModeIsExclude,
+            3 => RecordType::ChangeToInclude,
+            4 => RecordType::ChangeToExclude,
+            5 => RecordType::AllowNewSources,
+            6 => RecordType::BlockOldSources,
+            _ => RecordType::Unsupported(record_type),
+        }
+    }
+}
+
+#[derive(Debug)]
+/**
+ * A multicast address record of an MLDv2 report.
+ */
+pub struct MulticastAddressRecord {
+    pub record_type: RecordType,
+    pub multicast_address: Address,
+    pub sources: Vec<Address>,
+    pub auxiliary_data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ * Fields that only MLDv2 queries carry after the multicast address.
+ */
+pub struct QueryV2 {
+    pub suppress_router_processing: bool,
+    pub robustness: u8,
+    pub query_interval_code: u8,
+    pub sources: Vec<Address>,
+}
+
+#[derive(Debug)]
+/**
+ ### Neighbor Discovery option
+ * `SourceLinkLayerAddress` (1) and `TargetLinkLayerAddress` (2): The link-layer address, 6 bytes on Ethernet.
+ * `PrefixInformation` (3): An on-link or autoconfiguration prefix with its lifetimes in seconds.
+ * `RedirectedHeader` (4): The start of the redirected packet.
+ * `Mtu` (5): The link MTU.
+ * `RecursiveDnsServer` (25): DNS servers and their lifetime in seconds (RFC 8106).
+ * `Unknown`: Any other option, with its type and raw data.
+ */
+pub enum NdpOption {
+    SourceLinkLayerAddress(Vec<u8>),
+    TargetLinkLayerAddress(Vec<u8>),
+    PrefixInformation {
+        prefix_length: u8,
+        on_link: bool,
+        autonomous: bool,
+        valid_lifetime: u32,
+        preferred_lifetime: u32,
+        prefix: Address,
+    },
+    RedirectedHeader(Vec<u8>),
+    Mtu(u32),
+    RecursiveDnsServer { lifetime: u32, servers: Vec<Address> },
+    Unknown { option_type: u8, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### ICMPv6 message
+ * `DestinationUnreachable` (1), `PacketTooBig` (2), `TimeExceeded` (3) and `ParameterProblem` (4): Errors, which quote the offending packet.
+ * `EchoRequest` (128) and `EchoReply` (129): Ping, with the identifier, sequence number and data.
+ * `MulticastListenerQuery` (130): MLD query, with the MLDv2 fields when the message is long enough.
+ * `MulticastListenerReport` (131) and `MulticastListenerDone` (132): MLDv1 messages.
+ * `MulticastListenerReportV2` (143): MLDv2 report with its multicast address records.
+ * `RouterSolicitation` (133), `RouterAdvertisement` (134), `NeighborSolicitation` (135), `NeighborAdvertisement` (136) and `Redirect` (137): Neighbor Discovery (RFC 4861).
+ * `Unknown`: Any other message, with its type, code and data.
+ */
+pub enum Message {
+    DestinationUnreachable { code: UnreachableCode },
+    PacketTooBig { mtu: u32 },
+    TimeExceeded { code: TimeExceededCode },
+    ParameterProblem { code: ParameterProblemCode, pointer: u32 },
+    EchoRequest { identifier: u16, sequence_number: u16, data: Vec<u8> },
+    EchoReply { identifier: u16, sequence_number: u16, data: Vec<u8> },
+    MulticastListenerQuery { maximum_response_delay: u16, multicast_address: Address, v2: Option<QueryV2> },
+    MulticastListenerReport { multicast_address: Address },
+    MulticastListenerDone { multicast_address: Address },
+    MulticastListenerReportV2 { records: Vec<MulticastAddressRecord> },
+    RouterSolicitation { options: Vec<NdpOption> },
+    RouterAdvertisement {
+        current_hop_limit: u8,
+        managed: bool,
+        other: bool,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retransmission_timer: u32,
+        options: Vec<NdpOption>,
+    },
+    NeighborSolicitation { target: Address, options: Vec<NdpOption> },
+    NeighborAdvertisement { router: bool, solicited: bool, override_flag: bool, target: Address, options: Vec<NdpOption> },
+    Redirect { target: Address, destination: Address, options: Vec<NdpOption> },
+    Unknown { icmp_type: u8, code: u8, data: Vec<u8> },
+}
+
+impl Message {
+    /**
+     * Error messages quote as much of the offending packet as fits in the minimum IPv6 MTU.
+     */
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Message::DestinationUnreachable { .. }
+                | Message::PacketTooBig { .. }
+                | Message::TimeExceeded { .. }
+                | Message::ParameterProblem { .. }
+        )
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### ICMPv6 header structure
+ * Type (8 bits): The type of the message.
+ * Code (8 bits): The subtype of the message.
+ * Checksum (16 bits): Checksum over the message and the IPv6 pseudo-header.
+ * Message: The decoded message.
+ * Quoted: The dissected original packet of error messages, starting with its IPv6 header.
+ */
+pub struct Header {
+    pub icmp_type: u8,
+    pub code: u8,
+    pub checksum: u16,
+    pub message: Message,
+    pub quoted: Vec<Protocol>,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated ICMPv6 message")
+}
+
+fn read_address(data: &[u8], offset: usize) -> io::Result<Address> {
+    match data.get(offset..offset + 16) {
+        Some(bytes) => {
+            let mut address = [0u8; 16];
+            address.copy_from_slice(bytes);
+            Ok(Address::new(address))
+        }
+        None => Err(truncated()),
+    }
+}
+
+fn read_addresses(data: &[u8], offset: usize, count: usize) -> io::Result<Vec<Address>> {
+    (0..count).map(|index| read_address(data, offset + index * 16)).collect()
+}
+
+fn parse_ndp_options(data: &[u8]) -> io::Result<Vec<NdpOption>> {
+    let mut options = Vec::new();
+    let mut offset = 0;
+    while offset + 2 <= data.len() {
+        let option_type = data[offset];
+        // The length is in units of 8 bytes and includes the type and length fields.
+        let length = data[offset + 1] as usize * 8;
+        if length == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid Neighbor Discovery option length"));
+        }
+        let body = data.get(offset + 2..offset + length).ok_or_else(truncated)?;
+        let option = match option_type {
+            1 => NdpOption::SourceLinkLayerAddress(body.to_vec()),
+            2 => NdpOption::TargetLinkLayerAddress(body.to_vec()),
+            3 => NdpOption::PrefixInformation {
+                prefix_length: *body.first().ok_or_else(truncated)?,
+                on_link: body.get(1).ok_or_else(truncated)? & 0x80 != 0,
+                autonomous: body[1] & 0x40 != 0,
+                valid_lifetime: read_u32_be(body, 2).map_err(|_| truncated())?,
+                preferred_lifetime: read_u32_be(body, 6).map_err(|_| truncated())?,
+                prefix: read_address(body, 14)?,
+            },
+            // Six reserved bytes precede the redirected packet.
+            4 => NdpOption::RedirectedHeader(body.get(6..).unwrap_or_default().to_vec()),
+            5 => NdpOption::Mtu(read_u32_be(body, 2).map_err(|_| truncated())?),
+            25 => NdpOption::RecursiveDnsServer {
+                lifetime: read_u32_be(body, 2).map_err(|_| truncated())?,
+                servers: read_addresses(body, 6, body.len().saturating_sub(6) / 16)?,
+            },
+            _ => NdpOption::Unknown { option_type, data: body.to_vec() },
+        };
+        options.push(option);
+        offset += length;
+    }
+    Ok(options)
+}
+
+fn parse_mldv2_report(data: &[u8]) -> io::Result<Vec<MulticastAddressRecord>> {
+    let count = read_u16_be(data, 6).map_err(|_| truncated())?;
+    let mut offset = 8;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let record_type = RecordType::from_u8(*data.get(offset).ok_or_else(truncated)?);
+        let auxiliary_length = *data.get(offset + 1).ok_or_else(truncated)? as usize * 4;
+        let source_count = read_u16_be(data, offset + 2).map_err(|_| truncated())? as usize;
+        let multicast_address = read_address(data, offset + 4)?;
+        let sources = read_addresses(data, offset + 20, source_count)?;
+        let auxiliary_start = offset + 20 + source_count * 16;
+        let auxiliary_data = data.get(auxiliary_start..auxiliary_start + auxiliary_length).ok_or_else(truncated)?.to_vec();
+        offset = auxiliary_start + auxiliary_length;
+
+        records.push(MulticastAddressRecord {
+            record_type,
+            multicast_address,
+            sources,
+            auxiliary_data,
+        });
+    }
+    Ok(records)
+}
+
+/**
+ ### Parse the ICMPv6 header and message from the data
+
+ The quoted packet of error messages starts at `Header::size()` and is left to the caller to dissect into `quoted`.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse ICMPv6 header",
+        ));
+    }
+
+    let icmp_type = data[0];
+    let code = data[1];
+    let checksum = read_u16_be(data, 2)?;
+    let identifier = read_u16_be(data, 4)?;
+    let sequence_number = read_u16_be(data, 6)?;
+    let rest_of_header = read_u32_be(data, 4)?;
+
+    let message = match icmp_type {
+        1 => Message::DestinationUnreachable {
+            code: match code {
+                0 => UnreachableCode::NoRoute,
+                1 => UnreachableCode::AdministrativelyProhibited,
+                2 => UnreachableCode::BeyondScopeOfSource,
+                3 => UnreachableCode::AddressUnreachable,
+                4 => UnreachableCode::PortUnreachable,
+                5 => UnreachableCode::SourceAddressFailedPolicy,
+                6 => UnreachableCode::RejectRoute,
+                7 => UnreachableCode::SourceRoutingHeaderError,
+                _ => UnreachableCode::Unsupported(code),
+            },
+        },
+        2 => Message::PacketTooBig { mtu: rest_of_header },
+        3 => Message::TimeExceeded {
+            code: match code {
+                0 => TimeExceededCode::HopLimitExceeded,
+                1 => TimeExceededCode::FragmentReassemblyTimeExceeded,
+                _ => TimeExceededCode::Unsupported(code),
+            },
+        },
+        4 => Message::ParameterProblem {
+            code: match code {
+                0 => ParameterProblemCode::ErroneousHeaderField,
+                1 => ParameterProblemCode::UnrecognizedNextHeader,
+                2 => ParameterProblemCode::UnrecognizedOption,
+                _ => ParameterProblemCode::Unsupported(code),
+            },
+            pointer: rest_of_header,
+        },
+        128 => Message::EchoRequest { identifier, sequence_number, data: data[8..].to_vec() },
+        129 => Message::EchoReply { identifier, sequence_number, data: data[8..].to_vec() },
+        130 => {
+            let multicast_address = read_address(data, 8)?;
+            // MLDv2 queries are at least 28 bytes long, MLDv1 queries 24.
+            let v2 = if data.len() >= 28 {
+                let source_count = read_u16_be(data, 26)? as usize;
+                Some(QueryV2 {
+                    suppress_router_processing: data[24] & 0x08 != 0,
+                    robustness: data[24] & 0x07,
+                    query_interval_code: data[25],
+                    sources: read_addresses(data, 28, source_count)?,
+                })
+            } else {
+                None
+            };
+            Message::MulticastListenerQuery { maximum_response_delay: identifier, multicast_address, v2 }
+        }
+        131 => Message::MulticastListenerReport { multicast_address: read_address(data, 8)? },
+        132 => Message::MulticastListenerDone { multicast_address: read_address(data, 8)? },
+        143 => Message::MulticastListenerReportV2 { records: parse_mldv2_report(data)? },
+        133 => Message::RouterSolicitation { options: parse_ndp_options(&data[8..])? },
+        134 => {
+            if data.len() < 16 {
+                return Err(truncated());
+            }
+            Message::RouterAdvertisement {
+                current_hop_limit: data[4],
+                managed: data[5] & 0x80 != 0,
+                other: data[5] & 0x40 != 0,
+                router_lifetime: sequence_number,
+                reachable_time: read_u32_be(data, 8)?,
+                retransmission_timer: read_u32_be(data, 12)?,
+                options: parse_ndp_options(&data[16..])?,
+            }
+        }
+        135 => Message::NeighborSolicitation {
+            target: read_address(data, 8)?,
+            options: parse_ndp_options(&data[24..])?,
+        },
+        136 => Message::NeighborAdvertisement {
+            router: data[4] & 0x80 != 0,
+            solicited: data[4] & 0x40 != 0,
+            override_flag: data[4] & 0x20 != 0,
+            target: read_address(data, 8)?,
+            options: parse_ndp_options(&data[24..])?,
+        },
+        137 => Message::Redirect {
+            target: read_address(data, 8)?,
+            destination: read_address(data, 24)?,
+            options: parse_ndp_options(&data[40..])?,
+        },
+        _ => Message::Unknown { icmp_type, code, data: data[4..].to_vec() },
+    };
+
+    Ok(Header {
+        icmp_type,
+        code,
+        checksum,
+        message,
+        quoted: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_router_advertisement() {
+        let mut data = vec![134, 0, 0x00, 0x00, 64, 0x40, 0x07, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+        // Source link-layer address
+        data.extend_from_slice(&[1, 1, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        // MTU
+        data.extend_from_slice(&[5, 1, 0, 0, 0x00, 0x00, 0x05, 0xDC]);
+        // Prefix information for 2001:db8::/64
+        data.extend_from_slice(&[3, 4, 64, 0xC0, 0x00, 0x27, 0x8D, 0x00, 0x00, 0x09, 0x3A, 0x80, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Message::RouterAdvertisement { current_hop_limit, managed, other, router_lifetime, options, .. } => {
+                assert_eq!(current_hop_limit, 64);
+                assert!(!managed);
+                assert!(other);
+                assert_eq!(router_lifetime, 1800);
+                assert!(matches!(&options[0], NdpOption::SourceLinkLayerAddress(address) if address.len() == 6));
+                assert!(matches!(options[1], NdpOption::Mtu(1500)));
+                match &options[2] {
+                    NdpOption::PrefixInformation { prefix_length, on_link, autonomous, valid_lifetime, prefix, .. } => {
+                        assert_eq!(*prefix_length, 64);
+                        assert!(*on_link && *autonomous);
+                        assert_eq!(*valid_lifetime, 2592000);
+                        assert_eq!(prefix.address[..4], [0x20, 0x01, 0x0D, 0xB8]);
+                    }
+                    other => panic!("Unexpected option: {:?}", other),
+                }
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_mldv2_report() {
+        let mut data = vec![143, 0, 0x00, 0x00, 0, 0, 0x00, 0x01];
+        data.extend_from_slice(&[4, 0, 0x00, 0x00]);
+        data.extend_from_slice(&[0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xFB]);
+
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Message::MulticastListenerReportV2 { records } => {
+                assert_eq!(records.len(), 1);
+                assert_eq!(records[0].record_type, RecordType::ChangeToExclude);
+                assert!(records[0].sources.is_empty());
+                assert_eq!(records[0].multicast_address.address[15], 0xFB);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let header = parse(&[2, 0, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00]).unwrap();
+
+        assert!(header.message.is_error());
+        assert!(matches!(header.message, Message::PacketTooBig { mtu: 1280 }));
+    }
+}
@@ -0,0 +1,340 @@
+use std::{collections::{btree_map::Entry, BTreeMap, HashMap}, io};
+
+use crate::read_bytes::read_u16_be;
+
+use super::{icmpv6::RecordType, ipv4::Address, nflog::Tlv, Protocol};
+
+#[derive(Debug)]
+/**
+ * A group record of an IGMPv3 report.
+ */
+pub struct GroupRecord {
+    pub record_type: RecordType,
+    pub group: Address,
+    pub sources: Vec<Address>,
+    pub auxiliary_data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### IGMP message
+ * `Query` (0x11): Membership query. A general query has group 0.0.0.0. The version is 1 when the maximum response code is 0,
+   2 for 8 byte queries, and 3 for longer ones, which also carry the suppress flag, robustness, query interval code and sources.
+ * `V1Report` (0x12), `V2Report` (0x16): A host joined or still is a member of the group.
+ * `Leave` (0x17): A host left the group (IGMPv2).
+ * `V3Report` (0x22): Group records of a host (RFC 3376).
+ * `Unknown`: Any other message, with its type and data.
+ */
+pub enum Message {
+    Query {
+        version: u8,
+        group: Address,
+        suppress_router_processing: bool,
+        robustness: u8,
+        query_interval_code: u8,
+        sources: Vec<Address>,
+    },
+    V1Report { group: Address },
+    V2Report { group: Address },
+    Leave { group: Address },
+    V3Report { records: Vec<GroupRecord> },
+    Unknown { igmp_type: u8, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### IGMP header structure
+ * Type (8 bits): The type of the message.
+ * Max Response Code (8 bits): The maximum time to respond to a query, in 1/10 seconds. Unused in IGMPv1.
+ * Checksum (16 bits): Checksum over the whole IGMP message.
+ * Message: The decoded message.
+ */
+pub struct Header {
+    pub igmp_type: u8,
+    pub max_response_code: u8,
+    pub checksum: u16,
+    pub message: Message,
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated IGMP message")
+}
+
+fn read_address(data: &[u8], offset: usize) -> io::Result<Address> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(Address::new([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(truncated()),
+    }
+}
+
+fn read_addresses(data: &[u8], offset: usize, count: usize) -> io::Result<Vec<Address>> {
+    (0..count).map(|index| read_address(data, offset + index * 4)).collect()
+}
+
+fn parse_v3_report(data: &[u8]) -> io::Result<Vec<GroupRecord>> {
+    let count = read_u16_be(data, 6).map_err(|_| truncated())?;
+    let mut offset = 8;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let record_type = RecordType::from_u8(*data.get(offset).ok_or_else(truncated)?);
+        let auxiliary_length = *data.get(offset + 1).ok_or_else(truncated)? as usize * 4;
+        let source_count = read_u16_be(data, offset + 2).map_err(|_| truncated())? as usize;
+        let group = read_address(data, offset + 4)?;
+        let sources = read_addresses(data, offset + 8, source_count)?;
+        let auxiliary_start = offset + 8 + source_count * 4;
+        let auxiliary_data = data.get(auxiliary_start..auxiliary_start + auxiliary_length).ok_or_else(truncated)?.to_vec();
+        offset = auxiliary_start + auxiliary_length;
+
+        records.push(GroupRecord {
+            record_type,
+            group,
+            sources,
+            auxiliary_data,
+        });
+    }
+    Ok(records)
+}
+
+/**
+ * Parse the IGMP header and message from the data. The version of queries is told apart by their length (RFC 3376, section 7.1).
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse IGMP header",
+        ));
+    }
+
+    let igmp_type = data[0];
+    let max_response_code = data[1];
+    let checksum = read_u16_be(data, 2)?;
+
+    let message = match igmp_type {
+        0x11 if data.len() >= 12 => Message::Query {
+            version: 3,
+            group: read_address(data, 4)?,
+            suppress_router_processing: data[8] & 0x08 != 0,
+            robustness: data[8] & 0x07,
+            query_interval_code: data[9],
+            sources: read_addresses(data, 12, read_u16_be(data, 10)? as usize)?,
+        },
+        0x11 => Message::Query {
+            version: if max_response_code == 0 { 1 } else { 2 },
+            group: read_address(data, 4)?,
+            suppress_router_processing: false,
+            robustness: 0,
+            query_interval_code: 0,
+            sources: Vec::new(),
+        },
+        0x12 => Message::V1Report { group: read_address(data, 4)? },
+        0x16 => Message::V2Report { group: read_address(data, 4)? },
+        0x17 => Message::Leave { group: read_address(data, 4)? },
+        0x22 => Message::V3Report { records: parse_v3_report(data)? },
+        _ => Message::Unknown { igmp_type, data: data[4..].to_vec() },
+    };
+
+    Ok(Header {
+        igmp_type,
+        max_response_code,
+        checksum,
+        message,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Kind of membership change
+ * `Joined`: A host reported membership of a group it was not known to be a member of.
+ * `Left`: A member sent a leave, or an IGMPv3 report with an empty include list.
+ */
+pub enum EventKind {
+    Joined,
+    Left,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### Membership change seen by the tracker
+ * Time: The capture time in seconds.
+ * Interface: The interface index the report was seen on, from NFLOG, and 0 otherwise.
+ * Group, Host: The multicast group and the reporting host.
+ * Kind: Whether the host joined or left.
+ */
+pub struct Event {
+    pub time: u32,
+    pub interface: u32,
+    pub group: [u8; 4],
+    pub host: [u8; 4],
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Default)]
+/**
+ ### Multicast group membership tracker
+
+ Follows the IGMP reports and leaves of the hosts per interface and group. Memberships are tracked per group only,
+ the source filters of IGMPv3 are not. Hosts that stop reporting without leaving are not aged out.
+ `PCapA::open_with_options` runs it on every packet when `igmp_tracking` is enabled, or feed it with `process` yourself.
+ */
+pub struct Tracker {
+    members: HashMap<(u32, [u8; 4]), BTreeMap<[u8; 4], u32>>,
+    events: Vec<Event>,
+}
+
+impl Tracker {
+    pub fn new() -> Tracker {
+        Tracker::default()
+    }
+
+    /**
+     * Feed an IGMP message sent by a host to the tracker.
+     */
+    pub fn push(&mut self, time: u32, interface: u32, host: &Address, header: &Header) {
+        match &header.message {
+            Message::V1Report { group } | Message::V2Report { group } => self.join(time, interface, group.address, host.address),
+            Message::Leave { group } => self.leave(time, interface, group.address, host.address),
+            Message::V3Report { records } => {
+                for record in records {
+                    let leaving = record.sources.is_empty()
+                        && matches!(record.record_type, RecordType::ModeIsInclude | RecordType::ChangeToInclude);
+                    match record.record_type {
+                        RecordType::BlockOldSources | RecordType::Unsupported(_) => {}
+                        _ if leaving => self.leave(time, interface, record.group.address, host.address),
+                        _ => self.join(time, interface, record.group.address, host.address),
+                    }
+                }
+            }
+            Message::Query { .. } | Message::Unknown { .. } => {}
+        }
+    }
+
+    /**
+     * Run the tracker on the protocols of a packet captured at the given time in seconds.
+     */
+    pub fn process(&mut self, protocols: &[Protocol], time: u32) {
+        let interface = protocols
+            .iter()
+            .find_map(|protocol| match protocol {
+                Protocol::Nflog(nflog) => nflog.tlvs.iter().find_map(|tlv| match tlv {
+                    Tlv::InputInterface(index) | Tlv::OutputInterface(index) => Some(*index),
+                    _ => None,
+                }),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let mut host = None;
+        for protocol in protocols {
+            match protocol {
+                Protocol::IPv4(ip_header) => host = Some(&ip_header.source),
+                Protocol::Igmp(header) => {
+                    if let Some(host) = host {
+                        self.push(time, interface, host, header);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /**
+     * All membership changes, in the order they were seen.
+     */
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /**
+     * The current members of a group on an interface, with the time they joined.
+     */
+    pub fn members(&self, interface: u32, group: [u8; 4]) -> Vec<([u8; 4], u32)> {
+        match self.members.get(&(interface, group)) {
+            Some(members) => members.iter().map(|(host, joined)| (*host, *joined)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn join(&mut self, time: u32, interface: u32, group: [u8; 4], host: [u8; 4]) {
+        let members = self.members.entry((interface, group)).or_default();
+        if let Entry::Vacant(entry) = members.entry(host) {
+            entry.insert(time);
+            self.events.push(Event { time, interface, group, host, kind: EventKind::Joined });
+        }
+    }
+
+    fn leave(&mut self, time: u32, interface: u32, group: [u8; 4], host: [u8; 4]) {
+        let left = self.members.get_mut(&(interface, group)).and_then(|members| members.remove(&host));
+        if left.is_some() {
+            self.events.push(Event { time, interface, group, host, kind: EventKind::Left });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v3_report() {
+        let data = [
+            0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // Two group records
+            0x04, 0x00, 0x00, 0x00, 239, 1, 1, 1, // Change to exclude, no sources
+            0x05, 0x00, 0x00, 0x01, 239, 2, 2, 2, 10, 0, 0, 9, // Allow one new source
+        ];
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Message::V3Report { records } => {
+                assert_eq!(records.len(), 2);
+                assert_eq!(records[0].record_type, RecordType::ChangeToExclude);
+                assert_eq!(records[1].group.address, [239, 2, 2, 2]);
+                assert_eq!(records[1].sources[0].address, [10, 0, 0, 9]);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tells_query_versions_apart() {
+        let v2 = parse(&[0x11, 0x64, 0x00, 0x00, 0, 0, 0, 0]).unwrap();
+        let v3 = parse(&[0x11, 0x64, 0x00, 0x00, 0, 0, 0, 0, 0x02, 125, 0x00, 0x00]).unwrap();
+
+        assert!(matches!(v2.message, Message::Query { version: 2, .. }));
+        assert!(matches!(v3.message, Message::Query { version: 3, robustness: 2, query_interval_code: 125, .. }));
+    }
+
+    #[test]
+    fn tracks_joins_and_leaves() {
+        let mut tracker = Tracker::new();
+        let host = Address::new([10, 0, 0, 5]);
+
+        tracker.push(10, 0, &host, &parse(&[0x16, 0x00, 0x00, 0x00, 239, 1, 1, 1]).unwrap());
+        tracker.push(20, 0, &host, &parse(&[0x16, 0x00, 0x00, 0x00, 239, 1, 1, 1]).unwrap());
+        assert_eq!(tracker.members(0, [239, 1, 1, 1]), vec![([10, 0, 0, 5], 10)]);
+
+        tracker.push(30, 0, &host, &parse(&[0x17, 0x00, 0x00, 0x00, 239, 1, 1, 1]).unwrap());
+        assert!(tracker.members(0, [239, 1, 1, 1]).is_empty());
+        assert_eq!(tracker.events().len(), 2);
+        assert_eq!(tracker.events()[1].kind, EventKind::Left);
+        assert_eq!(tracker.events()[1].time, 30);
+    }
+
+    #[test]
+    fn processes_dissected_packets() {
+        use crate::protocol::{ipv4, nflog};
+
+        let mut data = vec![0x46, 0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 10, 0, 0, 7, 239, 1, 1, 1];
+        data.extend_from_slice(&[0x94, 0x04, 0x00, 0x00]);
+        data.extend_from_slice(&[0x16, 0x00, 0x00, 0x00, 239, 1, 1, 1]);
+        let ip_header = ipv4::parse(&data).unwrap();
+        let igmp_header = parse(&data[ip_header.size()..]).unwrap();
+        let nflog_header = nflog::Header { family: 2, version: 0, resource_id: 0, tlvs: vec![Tlv::InputInterface(3)], payload: None };
+        let protocols = vec![Protocol::Nflog(nflog_header), Protocol::IPv4(ip_header), Protocol::Igmp(igmp_header)];
+
+        let mut tracker = Tracker::new();
+        tracker.process(&protocols, 100);
+
+        assert_eq!(tracker.members(3, [239, 1, 1, 1]), vec![([10, 0, 0, 7], 100)]);
+        assert_eq!(tracker.events(), [Event { time: 100, interface: 3, group: [239, 1, 1, 1], host: [10, 0, 0, 7], kind: EventKind::Joined }]);
+    }
+}
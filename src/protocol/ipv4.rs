@@ -1,14 +1,16 @@
-use std::io;
+use std::{fmt, io, net::{AddrParseError, Ipv4Addr}, str::FromStr};
 
 use aipn::AIPN;
 
+use crate::read_bytes::read_u16_be;
+
 #[derive(Debug)]
 pub enum Version {
     V4=4,
     V6=6,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// IPv4 Address 
 pub struct Address {
     pub address:[u8;4],
@@ -20,9 +22,84 @@ impl Address {
             address
         }
     }
+
+    /**
+     * 10.0.0.0/8, 172.16.0.0/12 and 192.168.0.0/16 (RFC 1918).
+     */
+    pub fn is_private(&self) -> bool {
+        Ipv4Addr::from(*self).is_private()
+    }
+
+    /**
+     * 127.0.0.0/8.
+     */
+    pub fn is_loopback(&self) -> bool {
+        self.address[0] == 127
+    }
+
+    /**
+     * 224.0.0.0/4.
+     */
+    pub fn is_multicast(&self) -> bool {
+        self.address[0] & 0xF0 == 224
+    }
+
+    /**
+     * 169.254.0.0/16 (RFC 3927).
+     */
+    pub fn is_link_local(&self) -> bool {
+        self.address[..2] == [169, 254]
+    }
+
+    /**
+     * 192.0.2.0/24, 198.51.100.0/24 and 203.0.113.0/24 (RFC 5737).
+     */
+    pub fn is_documentation(&self) -> bool {
+        Ipv4Addr::from(*self).is_documentation()
+    }
+
+    /**
+     * 255.255.255.255.
+     */
+    pub fn is_broadcast(&self) -> bool {
+        self.address == [255; 4]
+    }
+
+    /**
+     * 0.0.0.0.
+     */
+    pub fn is_unspecified(&self) -> bool {
+        self.address == [0; 4]
+    }
 }
 
-#[derive(Debug)]
+impl From<Ipv4Addr> for Address {
+    fn from(address: Ipv4Addr) -> Address {
+        Address::new(address.octets())
+    }
+}
+
+impl From<Address> for Ipv4Addr {
+    fn from(address: Address) -> Ipv4Addr {
+        Ipv4Addr::from(address.address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Ipv4Addr::from(*self).fmt(f)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddrParseError;
+
+    fn from_str(text: &str) -> Result<Address, AddrParseError> {
+        text.parse::<Ipv4Addr>().map(Address::from)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 /**
  * Flag enum
  * Reserved (1 bit): Reserved for future use.
@@ -36,13 +113,51 @@ pub enum Flag {
     MoreFragments,
 }
 
+#[derive(Debug)]
+/**
+ ### IPv4 header option
+ * `EndOfOptionList` (0) and `NoOperation` (1): Single byte options used for padding and alignment.
+ * `Security` (130): RFC 1108 basic security option, the classification level and protection authority flags.
+ * `LooseSourceRoute` (131), `StrictSourceRoute` (137) and `RecordRoute` (7): A pointer to the next free slot and the list of addresses.
+ * `StreamId` (136): Obsolete SATNET stream identifier.
+ * `Timestamp` (68): A pointer, the overflow counter, the format flag, and the recorded timestamps with their address if the format includes one.
+ * `RouterAlert` (148): Tells routers to examine the packet more closely, the value is 0 for "router shall examine packet".
+ * `Unknown`: Any other option, with its type and raw data.
+ */
+pub enum HeaderOption {
+    EndOfOptionList,
+    NoOperation,
+    Security { classification: u8, protection_authority: Vec<u8> },
+    LooseSourceRoute { pointer: u8, route: Vec<Address> },
+    StrictSourceRoute { pointer: u8, route: Vec<Address> },
+    RecordRoute { pointer: u8, route: Vec<Address> },
+    StreamId(u16),
+    Timestamp { pointer: u8, overflow: u8, flag: u8, entries: Vec<(Option<Address>, u32)> },
+    RouterAlert(u16),
+    Unknown { option_type: u8, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Header checksum status
+ * `Unverified`: The checksum was not checked.
+ * `Good`: The checksum matches the header.
+ * `Bad`: The checksum does not match, `expected` holds the checksum computed over the header. Often caused by checksum offloading on the capturing host.
+ */
+pub enum Checksum {
+    Unverified,
+    Good,
+    Bad { expected: u16 },
+}
+
 #[derive(Debug)]
 /**
  ### IPv4 header structure
  * Version (4 bits): The version of the IP protocol. For IPv4, this is 4.
- * IHL (4 bits): The Internet Header Length (IHL) is the number of 32-bit words in the header. The minimum value is 5, and the maximum value is 15. At the moment the parser **only supports IHL of 5**.
+ * IHL (4 bits): The Internet Header Length (IHL) is the number of 32-bit words in the header. The minimum value is 5, and the maximum value is 15.
  * Differentiated Services Field (DSF) (8 bits): This field is used to differentiate and prioritize packets.
- * Total Length (16 bits): The total length of the IP packet, including the header and data.
+ * Total Length (16 bits): The total length of the IP packet, including the header and data. A total length of 0, as captured
+   on hosts with TCP segmentation offload, is replaced by the captured length and `total_length_presumed` is set.
  * Identification (16 bits): Used to identify fragments of an IP packet.
  * Flags (3 bits): Used to control or identify fragments of an IP packet.
  * Fragment Offset (13 bits): The offset of the fragment within the original IP packet.
@@ -51,6 +166,9 @@ pub enum Flag {
  * Header Checksum (16 bits): Used to verify the integrity of the IP header.
  * Source Address (32 bits): The IP address of the sender.
  * Destination Address (32 bits): The IP address of the receiver.
+ * Options (variable): Present when the IHL is larger than 5.
+ * Checksum Status: The result of `verify_checksum`, set when dissecting with checksum verification enabled.
+ * Total Length Presumed: The total length field was 0 and the captured length is used instead.
 
  */
 pub struct Header {
@@ -66,30 +184,192 @@ pub struct Header {
     pub checksum:u16,
     pub source:Address,
     pub destination:Address,
+    pub options:Vec<HeaderOption>,
+    pub checksum_status:Checksum,
+    pub total_length_presumed:bool,
 }
 
+impl Header {
+    /**
+     * Size of the header including options, in bytes.
+     */
+    pub fn size(&self) -> usize {
+        self.ihl as usize * 4
+    }
+
+    /**
+     * Length of the payload as announced by the total length field.
+     */
+    pub fn payload_length(&self) -> usize {
+        self.total_length as usize - self.size()
+    }
+
+    /**
+     * Return the payload of the packet. Bytes beyond the total length, like Ethernet padding, are excluded.
+     * The payload is shorter than `payload_length` if the packet was truncated by the capture.
+     */
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let end = (self.total_length as usize).min(data.len());
+        &data[self.size().min(end)..end]
+    }
+
+    /**
+     * Verify the header checksum against the header bytes at the start of the data.
+     */
+    pub fn verify_checksum(&self, data: &[u8]) -> Checksum {
+        let header = match data.get(..self.size()) {
+            Some(header) => header,
+            None => return Checksum::Unverified,
+        };
+        let mut header = header.to_vec();
+        header[10..12].fill(0);
+        let expected = internet_checksum(&header);
+        if expected == self.checksum {
+            Checksum::Good
+        } else {
+            Checksum::Bad { expected }
+        }
+    }
+}
+
+/**
+ * Compute the Internet checksum (RFC 1071), the one's complement of the one's complement sum of 16-bit words.
+ */
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]) as u32)
+        .sum::<u32>();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn read_addresses(data: &[u8]) -> Vec<Address> {
+    data.chunks_exact(4)
+        .map(|chunk| Address::new([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+fn parse_option(option_type: u8, data: &[u8]) -> io::Result<HeaderOption> {
+    let invalid = |name: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid IPv4 {} option", name));
+    let option = match option_type {
+        7 | 131 | 137 => {
+            let pointer = *data.first().ok_or_else(|| invalid("route"))?;
+            let route = read_addresses(&data[1..]);
+            match option_type {
+                7 => HeaderOption::RecordRoute { pointer, route },
+                131 => HeaderOption::LooseSourceRoute { pointer, route },
+                _ => HeaderOption::StrictSourceRoute { pointer, route },
+            }
+        }
+        68 => {
+            if data.len() < 2 {
+                return Err(invalid("timestamp"));
+            }
+            let pointer = data[0];
+            let overflow = data[1] >> 4;
+            let flag = data[1] & 0x0F;
+            let entries = match flag {
+                0 => data[2..]
+                    .chunks_exact(4)
+                    .map(|chunk| (None, u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])))
+                    .collect(),
+                1 | 3 => data[2..]
+                    .chunks_exact(8)
+                    .map(|chunk| {
+                        (
+                            Some(Address::new([chunk[0], chunk[1], chunk[2], chunk[3]])),
+                            u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                        )
+                    })
+                    .collect(),
+                _ => return Err(invalid("timestamp")),
+            };
+            HeaderOption::Timestamp { pointer, overflow, flag, entries }
+        }
+        130 => HeaderOption::Security {
+            classification: *data.first().ok_or_else(|| invalid("security"))?,
+            protection_authority: data[1..].to_vec(),
+        },
+        136 => HeaderOption::StreamId(read_u16_be(data, 0).map_err(|_| invalid("stream ID"))?),
+        148 => HeaderOption::RouterAlert(read_u16_be(data, 0).map_err(|_| invalid("router alert"))?),
+        _ => HeaderOption::Unknown { option_type, data: data.to_vec() },
+    };
+    Ok(option)
+}
+
+/**
+ * Parse the options following the fixed part of the header. Parsing stops at the end of option list.
+ */
+fn parse_options(data: &[u8]) -> io::Result<Vec<HeaderOption>> {
+    let mut options = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let option_type = data[offset];
+        match option_type {
+            0 => {
+                options.push(HeaderOption::EndOfOptionList);
+                break;
+            }
+            1 => {
+                options.push(HeaderOption::NoOperation);
+                offset += 1;
+            }
+            _ => {
+                let length = match data.get(offset + 1) {
+                    Some(length) if *length >= 2 => *length as usize,
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid IPv4 option length")),
+                };
+                let option_data = match data.get(offset + 2..offset + length) {
+                    Some(option_data) => option_data,
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidData, "IPv4 option exceeds the header")),
+                };
+                options.push(parse_option(option_type, option_data)?);
+                offset += length;
+            }
+        }
+    }
+    Ok(options)
+}
+
+/**
+ ### Parse the IPv4 header from the data
+
+ The header is at least 20 bytes long, and up to 60 bytes with options. Use `Header::payload` to find the data it carries.
+ */
 pub fn parse(data:&[u8])-> io::Result<Header> {
+    if data.len() < 20 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof,"Not enough data to parse IPv4 header"));
+    }
     let version_ihl = data[0];
+    if version_ihl >> 4 != 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,"Invalid IP version"));
+    }
     let ihl=version_ihl & 0x0F;
-    if ! ihl == 5 { // TODO: Handle any IHL length.
+    if !(5..=15).contains(&ihl) {
         return Err(io::Error::new(io::ErrorKind::InvalidData,"Invalid IHL"));
     }
+    let header_length = ihl as usize * 4;
+    if data.len() < header_length {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof,"Not enough data to parse IPv4 options"));
+    }
     let dsf = data[1];
-    
-    let total_length = u16::from_be_bytes(match &data[2..4].try_into(){
-        Ok(bytes) => *bytes,
-        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,"Failed to read total length"))
-    });
-
-    let identification = u16::from_be_bytes(match &data[4..6].try_into(){
-        Ok(bytes) => *bytes,
-        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,"Failed to read identification"))
-    });
-    
-    let flags_fragment_offset = u16::from_be_bytes(match &data[6..8].try_into(){
-        Ok(bytes) => *bytes,
-        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,"Failed to read flags and fragment offset"))
-    });
+
+    let mut total_length = read_u16_be(data, 2)?;
+    // With TCP segmentation offload the capture sees packets before the NIC fills in the total length.
+    let total_length_presumed = total_length == 0;
+    if total_length_presumed {
+        total_length = data.len().min(u16::MAX as usize) as u16;
+    }
+    if (total_length as usize) < header_length {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,"Total length is smaller than the header"));
+    }
+
+    let identification = read_u16_be(data, 4)?;
+
+    let flags_fragment_offset = read_u16_be(data, 6)?;
 
     // Extract the flags and fragment offset from the 16-bit field using bit shifting and masking.
     let mut flags=Vec::new();
@@ -106,12 +386,10 @@ pub fn parse(data:&[u8])-> io::Result<Header> {
 
     let ttl = data[8];
     let protocol = AIPN::from(data[9]);
-    let checksum = u16::from_be_bytes(match &data[10..12].try_into(){
-        Ok(bytes) => *bytes,
-        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,"Failed to read checksum"))
-    });
+    let checksum = read_u16_be(data, 10)?;
     let source = Address::new([data[12],data[13],data[14],data[15]]);
     let destination = Address::new([data[16],data[17],data[18],data[19]]);
+    let options = parse_options(&data[20..header_length])?;
     
     
     let header = Header {
@@ -127,7 +405,81 @@ pub fn parse(data:&[u8])-> io::Result<Header> {
         checksum,
         source,
         destination,
+        options,
+        checksum_status:Checksum::Unverified,
+        total_length_presumed,
     };
     Ok(header)
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(ihl: u8, total_length: u16) -> Vec<u8> {
+        let mut data = vec![0x40 | ihl, 0x00];
+        data.extend_from_slice(&total_length.to_be_bytes());
+        data.extend_from_slice(&[0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data
+    }
+
+    #[test]
+    fn parses_options_and_payload() {
+        let mut data = header(7, 32);
+        // Router alert, then record route with room for one address, no padding needed.
+        data.extend_from_slice(&[148, 4, 0, 0, 7, 3, 4, 0]);
+        data.extend_from_slice(&[0xAA; 4]);
+        // Ethernet padding beyond the total length.
+        data.extend_from_slice(&[0x00; 6]);
+
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.size(), 28);
+        assert_eq!(header.payload_length(), 4);
+        assert_eq!(header.payload(&data), &[0xAA; 4]);
+        assert!(matches!(header.options[0], HeaderOption::RouterAlert(0)));
+        assert!(matches!(&header.options[1], HeaderOption::RecordRoute { pointer: 4, route } if route.is_empty()));
+        assert!(matches!(header.options[2], HeaderOption::EndOfOptionList));
+    }
+
+    #[test]
+    fn verifies_checksum() {
+        // Fill in the checksum so that the header sums to zero.
+        let mut data = header(5, 20);
+        let checksum = internet_checksum(&data);
+        data[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+        let header = parse(&data).unwrap();
+        assert_eq!(header.checksum_status, Checksum::Unverified);
+        assert_eq!(header.verify_checksum(&data), Checksum::Good);
+        assert_eq!(internet_checksum(&data), 0);
+
+        data[8] = 0x3F;
+        let header = parse(&data).unwrap();
+        assert_eq!(header.verify_checksum(&data), Checksum::Bad { expected: checksum.wrapping_add(0x0100) });
+    }
+
+    #[test]
+    fn rejects_invalid_ihl() {
+        assert!(parse(&header(4, 20)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        assert!(parse(&header(5, 20)[..12]).is_err());
+        assert!(parse(&header(6, 24)).is_err());
+    }
+
+    #[test]
+    fn presumes_captured_length_for_zero_total_length() {
+        let mut data = header(5, 0);
+        data.extend_from_slice(&[0xAA; 6]);
+
+        let header = parse(&data).unwrap();
+
+        assert!(header.total_length_presumed);
+        assert_eq!(header.total_length, 26);
+        assert_eq!(header.payload(&data), &[0xAA; 6]);
+    }
+}
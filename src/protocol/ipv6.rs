@@ -0,0 +1,411 @@
+use std::{fmt, io, net::{AddrParseError, Ipv6Addr}, str::FromStr};
+
+use aipn::AIPN;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::ipv4::Version;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// IPv6 Address
+pub struct Address {
+    pub address:[u8;16],
+}
+
+impl Address {
+    pub fn new(address:[u8;16])->Address {
+        Address {
+            address
+        }
+    }
+
+    /**
+     * Unique local addresses, fc00::/7 (RFC 4193).
+     */
+    pub fn is_private(&self) -> bool {
+        self.address[0] & 0xFE == 0xFC
+    }
+
+    /**
+     * ::1.
+     */
+    pub fn is_loopback(&self) -> bool {
+        Ipv6Addr::from(*self).is_loopback()
+    }
+
+    /**
+     * ff00::/8.
+     */
+    pub fn is_multicast(&self) -> bool {
+        self.address[0] == 0xFF
+    }
+
+    /**
+     * Unicast link-local addresses, fe80::/10.
+     */
+    pub fn is_link_local(&self) -> bool {
+        self.address[0] == 0xFE && self.address[1] & 0xC0 == 0x80
+    }
+
+    /**
+     * 2001:db8::/32 (RFC 3849) and 3fff::/20 (RFC 9637).
+     */
+    pub fn is_documentation(&self) -> bool {
+        self.address[..4] == [0x20, 0x01, 0x0D, 0xB8] || (self.address[..2] == [0x3F, 0xFF] && self.address[2] & 0xF0 == 0)
+    }
+
+    /**
+     * ::.
+     */
+    pub fn is_unspecified(&self) -> bool {
+        self.address == [0; 16]
+    }
+
+    /**
+     * The embedded IPv4 address of IPv4-mapped addresses, ::ffff:0:0/96.
+     */
+    pub fn to_ipv4_mapped(&self) -> Option<super::ipv4::Address> {
+        Ipv6Addr::from(*self).to_ipv4_mapped().map(super::ipv4::Address::from)
+    }
+}
+
+impl From<Ipv6Addr> for Address {
+    fn from(address: Ipv6Addr) -> Address {
+        Address::new(address.octets())
+    }
+}
+
+impl From<Address> for Ipv6Addr {
+    fn from(address: Address) -> Ipv6Addr {
+        Ipv6Addr::from(address.address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Ipv6Addr::from(*self).fmt(f)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddrParseError;
+
+    fn from_str(text: &str) -> Result<Address, AddrParseError> {
+        text.parse::<Ipv6Addr>().map(Address::from)
+    }
+}
+
+#[derive(Debug)]
+/**
+ ### Option of the hop-by-hop and destination options headers
+ * `Pad1` (0) and `PadN` (1): Padding, `PadN` stores the number of padding bytes.
+ * `RouterAlert` (5): Tells routers to examine the packet more closely, e.g. 0 for MLD.
+ * `JumboPayload` (194): The payload length for packets larger than 65535 bytes.
+ * `Unknown`: Any other option, with its type and raw data.
+ */
+pub enum HeaderOption {
+    Pad1,
+    PadN(usize),
+    RouterAlert(u16),
+    JumboPayload(u32),
+    Unknown { option_type: u8, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### IPv6 extension header
+ * `HopByHop` (0): Options examined by every node on the path.
+ * `Routing` (43): The routing type, the number of segments left, and the addresses for types 0, 2 (mobile IPv6) and 4 (segment routing).
+ * `Fragment` (44): The fragment offset in 8 byte units, the more fragments flag and the identification.
+ * `DestinationOptions` (60): Options examined by the destination.
+ * `AuthenticationHeader` (51): The security parameters index, sequence number and integrity check value.
+ * `Mobility` (135): The mobility header type and its raw message data.
+ */
+pub enum ExtensionHeader {
+    HopByHop { options: Vec<HeaderOption> },
+    Routing { routing_type: u8, segments_left: u8, addresses: Vec<Address>, data: Vec<u8> },
+    Fragment { fragment_offset: u16, more_fragments: bool, identification: u32 },
+    DestinationOptions { options: Vec<HeaderOption> },
+    AuthenticationHeader { spi: u32, sequence_number: u32, icv: Vec<u8> },
+    Mobility { mh_type: u8, checksum: u16, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### IPv6 header structure
+ * Version (4 bits): The version of the IP protocol. For IPv6, this is 6.
+ * Traffic Class (8 bits): The differentiated services field and ECN bits.
+ * Flow Label (20 bits): Identifies packets of the same flow.
+ * Payload Length (16 bits): The length of the payload including extension headers. 0 when a jumbo payload option is used.
+ * Next Header (8 bits): The type of the first extension header, or the upper-layer protocol.
+ * Hop Limit (8 bits): The number of hops the packet can take before being discarded.
+ * Source Address (128 bits): The IP address of the sender.
+ * Destination Address (128 bits): The IP address of the receiver.
+ * Extensions: The extension header chain, in order.
+ * Protocol: The upper-layer protocol found at the end of the extension header chain.
+ */
+pub struct Header {
+    pub version:Version,
+    pub traffic_class:u8,
+    pub flow_label:u32,
+    pub payload_length:u16,
+    pub next_header:AIPN,
+    pub hop_limit:u8,
+    pub source:Address,
+    pub destination:Address,
+    pub extensions:Vec<ExtensionHeader>,
+    pub protocol:AIPN,
+    pub extensions_length:usize,
+}
+
+impl Header {
+    /**
+     * Size of the fixed header and all extension headers, i.e. the offset of the upper-layer payload.
+     */
+    pub fn size(&self) -> usize {
+        40 + self.extensions_length
+    }
+
+    /**
+     * Return the upper-layer payload. Bytes beyond the payload length, like Ethernet padding, are excluded.
+     */
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let jumbo_length = self.extensions.iter().find_map(|extension| match extension {
+            ExtensionHeader::HopByHop { options } => options.iter().find_map(|option| match option {
+                HeaderOption::JumboPayload(length) => Some(*length as usize),
+                _ => None,
+            }),
+            _ => None,
+        });
+        let payload_length = match (self.payload_length, jumbo_length) {
+            (0, Some(length)) => length,
+            (length, _) => length as usize,
+        };
+        let end = (40 + payload_length).min(data.len());
+        &data[self.size().min(end)..end]
+    }
+
+    /**
+     * Return the fragment extension header fields, if the packet is a fragment.
+     */
+    pub fn fragment(&self) -> Option<(u16, bool, u32)> {
+        self.extensions.iter().find_map(|extension| match extension {
+            ExtensionHeader::Fragment { fragment_offset, more_fragments, identification } => {
+                Some((*fragment_offset, *more_fragments, *identification))
+            }
+            _ => None,
+        })
+    }
+}
+
+fn read_address(data: &[u8]) -> Address {
+    let mut address = [0u8; 16];
+    address.copy_from_slice(&data[..16]);
+    Address::new(address)
+}
+
+fn parse_options(data: &[u8]) -> io::Result<Vec<HeaderOption>> {
+    let mut options = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let option_type = data[offset];
+        if option_type == 0 {
+            options.push(HeaderOption::Pad1);
+            offset += 1;
+            continue;
+        }
+        let length = match data.get(offset + 1) {
+            Some(length) => *length as usize,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated IPv6 option")),
+        };
+        let option_data = match data.get(offset + 2..offset + 2 + length) {
+            Some(option_data) => option_data,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "IPv6 option exceeds the header")),
+        };
+        let option = match option_type {
+            1 => HeaderOption::PadN(length + 2),
+            5 => HeaderOption::RouterAlert(read_u16_be(option_data, 0)?),
+            0xC2 => HeaderOption::JumboPayload(read_u32_be(option_data, 0)?),
+            _ => HeaderOption::Unknown { option_type, data: option_data.to_vec() },
+        };
+        options.push(option);
+        offset += 2 + length;
+    }
+    Ok(options)
+}
+
+/**
+ * Parse a single extension header, and return it with the next header value and its size.
+ */
+fn parse_extension(header_type: u8, data: &[u8]) -> io::Result<(ExtensionHeader, u8, usize)> {
+    let truncated = || io::Error::new(io::ErrorKind::InvalidData, "Truncated IPv6 extension header");
+    if data.len() < 8 {
+        return Err(truncated());
+    }
+    let next_header = data[0];
+    let size = match header_type {
+        44 => 8,
+        // The AH length is in 4 byte units minus 2, the others are in 8 byte units not counting the first 8 bytes.
+        51 => (data[1] as usize + 2) * 4,
+        _ => (data[1] as usize + 1) * 8,
+    };
+    let body = data.get(..size).ok_or_else(truncated)?;
+
+    let extension = match header_type {
+        0 => ExtensionHeader::HopByHop { options: parse_options(&body[2..])? },
+        60 => ExtensionHeader::DestinationOptions { options: parse_options(&body[2..])? },
+        43 => {
+            let routing_type = body[2];
+            let segments_left = body[3];
+            let addresses = match routing_type {
+                0 | 2 | 4 => body[8..].chunks_exact(16).map(read_address).collect(),
+                _ => Vec::new(),
+            };
+            ExtensionHeader::Routing { routing_type, segments_left, addresses, data: body[4..].to_vec() }
+        }
+        44 => {
+            let offset_flags = read_u16_be(body, 2)?;
+            ExtensionHeader::Fragment {
+                fragment_offset: offset_flags >> 3,
+                more_fragments: offset_flags & 0x01 == 1,
+                identification: read_u32_be(body, 4)?,
+            }
+        }
+        51 => ExtensionHeader::AuthenticationHeader {
+            spi: read_u32_be(body, 4)?,
+            sequence_number: read_u32_be(body, 8).map_err(|_| truncated())?,
+            icv: body[12..].to_vec(),
+        },
+        _ => ExtensionHeader::Mobility {
+            mh_type: body[2],
+            checksum: read_u16_be(body, 4)?,
+            data: body[6..].to_vec(),
+        },
+    };
+
+    Ok((extension, next_header, size))
+}
+
+/**
+ * Locate the fragment header, and return the offset of the next header field that points to it and the offset of the fragment header.
+ */
+pub fn locate_fragment_header(data: &[u8]) -> Option<(usize, usize)> {
+    let mut next_header_offset = 6;
+    let mut offset = 40;
+    loop {
+        let size = match *data.get(next_header_offset)? {
+            44 => return Some((next_header_offset, offset)),
+            51 => (*data.get(offset + 1)? as usize + 2) * 4,
+            0 | 43 | 60 | 135 => (*data.get(offset + 1)? as usize + 1) * 8,
+            _ => return None,
+        };
+        next_header_offset = offset;
+        offset += size;
+    }
+}
+
+/**
+ ### Parse the IPv6 header and its extension header chain from the data
+
+ The chain is walked until an upper-layer protocol is found. Walking stops early at an ESP header, whose content is encrypted,
+ and after the fragment header of a non-first fragment, whose payload does not start with the next header.
+ */
+pub fn parse(data:&[u8])-> io::Result<Header> {
+    if data.len() < 40 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof,"Not enough data to parse IPv6 header"));
+    }
+    if data[0] >> 4 != 6 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,"Invalid IP version"));
+    }
+
+    let version_class_flow = read_u32_be(data, 0)?;
+    let traffic_class = ((version_class_flow >> 20) & 0xFF) as u8;
+    let flow_label = version_class_flow & 0x000F_FFFF;
+    let payload_length = read_u16_be(data, 4)?;
+    let next_header = data[6];
+    let hop_limit = data[7];
+    let source = read_address(&data[8..24]);
+    let destination = read_address(&data[24..40]);
+
+    let mut extensions = Vec::new();
+    let mut protocol = next_header;
+    let mut offset = 40;
+    while matches!(protocol, 0 | 43 | 44 | 51 | 60 | 135) {
+        let (extension, next, size) = parse_extension(protocol, &data[offset..])?;
+        let non_first_fragment = matches!(extension, ExtensionHeader::Fragment { fragment_offset, .. } if fragment_offset != 0);
+        extensions.push(extension);
+        protocol = next;
+        offset += size;
+        if non_first_fragment {
+            break;
+        }
+    }
+
+    Ok(Header {
+        version:Version::V6,
+        traffic_class,
+        flow_label,
+        payload_length,
+        next_header:AIPN::from(next_header),
+        hop_limit,
+        source,
+        destination,
+        extensions,
+        protocol:AIPN::from(protocol),
+        extensions_length:offset - 40,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(next_header: u8, payload_length: u16) -> Vec<u8> {
+        let mut data = vec![0x60, 0x00, 0x00, 0x01];
+        data.extend_from_slice(&payload_length.to_be_bytes());
+        data.extend_from_slice(&[next_header, 64]);
+        data.extend_from_slice(&[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x16]);
+        data
+    }
+
+    #[test]
+    fn walks_extension_chain() {
+        let mut data = header(0, 28);
+        // Hop-by-hop with router alert, then a first fragment, then 12 bytes of UDP.
+        data.extend_from_slice(&[44, 0, 5, 2, 0, 0, 1, 0]);
+        data.extend_from_slice(&[17, 0, 0x00, 0x01, 0xDE, 0xAD, 0xBE, 0xEF]);
+        data.extend_from_slice(&[0x11; 12]);
+
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.flow_label, 1);
+        assert_eq!(header.hop_limit, 64);
+        assert!(matches!(header.next_header, AIPN::HOPOPT));
+        assert!(matches!(header.protocol, AIPN::UDP));
+        assert_eq!(header.extensions.len(), 2);
+        assert!(matches!(&header.extensions[0], ExtensionHeader::HopByHop { options } if matches!(options[0], HeaderOption::RouterAlert(0))));
+        assert_eq!(header.fragment(), Some((0, true, 0xDEADBEEF)));
+        assert_eq!(header.size(), 56);
+        assert_eq!(header.payload(&data).len(), 12);
+    }
+
+    #[test]
+    fn stops_after_non_first_fragment() {
+        let mut data = header(44, 16);
+        data.extend_from_slice(&[6, 0, 0x00, 0xB8, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0x22; 8]);
+
+        let header = parse(&data).unwrap();
+
+        assert!(matches!(header.protocol, AIPN::TCP));
+        assert_eq!(header.fragment(), Some((23, false, 1)));
+    }
+
+    #[test]
+    fn rejects_truncated_extension() {
+        let mut data = header(60, 8);
+        data.extend_from_slice(&[17, 1, 1, 4]);
+        assert!(parse(&data).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::{collections::HashMap, io};
+
+use super::{can, Protocol};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Flow status of a flow control frame.
+ */
+pub enum FlowStatus {
+    ContinueToSend,
+    Wait,
+    Overflow,
+    Unsupported(u8),
+}
+
+#[derive(Debug, PartialEq)]
+/**
+ ### ISO-TP (ISO 15765-2) frame
+ The protocol control information in the upper nibble of the first data byte selects the frame type:
+ * `Single` (0): A complete message. The length is in the lower nibble, or in the second byte for CAN FD.
+ * `First` (1): The start of a segmented message with its total length (12 bits, or 32 bits when those are zero).
+ * `Consecutive` (2): A continuation of a segmented message with a 4 bit sequence number.
+ * `FlowControl` (3): Sent by the receiver to pace the sender.
+ */
+pub enum Frame {
+    Single { data: Vec<u8> },
+    First { length: u32, data: Vec<u8> },
+    Consecutive { sequence_number: u8, data: Vec<u8> },
+    FlowControl { status: FlowStatus, block_size: u8, separation_time: u8 },
+}
+
+#[derive(Debug)]
+/**
+ * A complete ISO-TP message, either from a single frame or reassembled from a first frame and its consecutive frames.
+ */
+pub struct Message {
+    pub can_id: u32,
+    pub frames: usize,
+    pub data: Vec<u8>,
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated ISO-TP frame")
+}
+
+/**
+ ### Parse the ISO-TP protocol control information from the data of a CAN frame
+ */
+pub fn parse_frame(data: &[u8]) -> io::Result<Frame> {
+    let pci = *data.first().ok_or_else(truncated)?;
+    let frame = match pci >> 4 {
+        0 => {
+            let (length, offset) = match pci & 0x0F {
+                0 => (*data.get(1).ok_or_else(truncated)? as usize, 2),
+                length => (length as usize, 1),
+            };
+            let data = data.get(offset..offset + length).ok_or_else(truncated)?.to_vec();
+            Frame::Single { data }
+        }
+        1 => {
+            let length = u16::from_be_bytes([pci & 0x0F, *data.get(1).ok_or_else(truncated)?]) as u32;
+            if length == 0 {
+                let bytes = data.get(2..6).ok_or_else(truncated)?;
+                let length = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                Frame::First { length, data: data[6..].to_vec() }
+            } else {
+                Frame::First { length, data: data[2..].to_vec() }
+            }
+        }
+        2 => Frame::Consecutive {
+            sequence_number: pci & 0x0F,
+            data: data[1..].to_vec(),
+        },
+        3 => Frame::FlowControl {
+            status: match pci & 0x0F {
+                0 => FlowStatus::ContinueToSend,
+                1 => FlowStatus::Wait,
+                2 => FlowStatus::Overflow,
+                other => FlowStatus::Unsupported(other),
+            },
+            block_size: *data.get(1).ok_or_else(truncated)?,
+            separation_time: *data.get(2).ok_or_else(truncated)?,
+        },
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid ISO-TP frame type")),
+    };
+    Ok(frame)
+}
+
+/**
+ * Largest message that is reassembled. The 32 bit length of the escaped first frame (ISO 15765-2:2016) allows 4 GiB,
+ * far beyond any real transfer, so such a length is taken for garbage instead of buffering frames until it is reached.
+ */
+const MAX_MESSAGE_LENGTH: usize = 1 << 20;
+
+#[derive(Debug)]
+struct Pending {
+    length: usize,
+    next_sequence_number: u8,
+    frames: usize,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+/**
+ ### ISO-TP reassembler
+
+ Keeps track of segmented messages per CAN ID across packets. A message is dropped when a consecutive frame arrives out of sequence,
+ or replaced when a new first frame arrives on the same CAN ID. First frames announcing more than `MAX_MESSAGE_LENGTH` bytes are ignored.
+ */
+pub struct Reassembler {
+    pending: HashMap<u32, Pending>,
+}
+
+impl Reassembler {
+    pub fn new() -> Reassembler {
+        Reassembler::default()
+    }
+
+    /**
+     * Feed a CAN frame to the reassembler, and return the message it completes if any.
+     */
+    pub fn push(&mut self, frame: &can::Header) -> Option<Message> {
+        let isotp_frame = match parse_frame(&frame.data) {
+            Ok(isotp_frame) => isotp_frame,
+            Err(e) => {
+                log::debug!("CAN frame {:#x} is not ISO-TP: {}", frame.id, e);
+                return None;
+            }
+        };
+
+        match isotp_frame {
+            Frame::Single { data } => Some(Message {
+                can_id: frame.id,
+                frames: 1,
+                data,
+            }),
+            Frame::First { length, .. } if length as usize > MAX_MESSAGE_LENGTH => {
+                log::warn!("Ignoring ISO-TP first frame on CAN ID {:#x} announcing {} bytes", frame.id, length);
+                self.pending.remove(&frame.id);
+                None
+            }
+            Frame::First { length, data } => {
+                let pending = Pending {
+                    length: length as usize,
+                    next_sequence_number: 1,
+                    frames: 1,
+                    data,
+                };
+                self.pending.insert(frame.id, pending);
+                self.complete(frame.id)
+            }
+            Frame::Consecutive { sequence_number, data } => {
+                let pending = self.pending.get_mut(&frame.id)?;
+                if pending.next_sequence_number != sequence_number {
+                    log::warn!(
+                        "ISO-TP sequence error on CAN ID {:#x}: expected {}, got {}",
+                        frame.id, pending.next_sequence_number, sequence_number
+                    );
+                    self.pending.remove(&frame.id);
+                    return None;
+                }
+                pending.next_sequence_number = (sequence_number + 1) & 0x0F;
+                pending.frames += 1;
+                pending.data.extend_from_slice(&data);
+                self.complete(frame.id)
+            }
+            Frame::FlowControl { .. } => None,
+        }
+    }
+
+    /**
+     * Run the reassembler on the protocols of a packet, and append the completed message to them.
+     */
+    pub fn process(&mut self, protocols: &mut Vec<Protocol>) {
+        let message = match protocols.last() {
+            Some(Protocol::Can(frame)) => self.push(frame),
+            _ => None,
+        };
+        if let Some(message) = message {
+            protocols.push(Protocol::IsoTp(message));
+        }
+    }
+
+    fn complete(&mut self, can_id: u32) -> Option<Message> {
+        let pending = self.pending.get(&can_id)?;
+        if pending.data.len() < pending.length {
+            return None;
+        }
+        let mut pending = self.pending.remove(&can_id)?;
+        // The last frame is padded to the CAN frame size.
+        pending.data.truncate(pending.length);
+        Some(Message {
+            can_id,
+            frames: pending.frames,
+            data: pending.data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(data: &[u8]) -> can::Header {
+        can::Header {
+            id: 0x7E8,
+            flags: Vec::new(),
+            format: can::FrameFormat::Classic,
+            length: data.len() as u8,
+            len8_dlc: None,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn reassembles_segmented_message() {
+        let mut reassembler = Reassembler::new();
+
+        assert!(reassembler.push(&frame(&[0x10, 0x0A, 1, 2, 3, 4, 5, 6])).is_none());
+        let message = reassembler.push(&frame(&[0x21, 7, 8, 9, 10, 0xAA, 0xAA, 0xAA])).unwrap();
+
+        assert_eq!(message.can_id, 0x7E8);
+        assert_eq!(message.frames, 2);
+        assert_eq!(message.data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn drops_out_of_sequence_message() {
+        let mut reassembler = Reassembler::new();
+
+        reassembler.push(&frame(&[0x10, 0x0A, 1, 2, 3, 4, 5, 6]));
+        assert!(reassembler.push(&frame(&[0x22, 7, 8, 9, 10])).is_none());
+        assert!(reassembler.push(&frame(&[0x21, 7, 8, 9, 10])).is_none());
+    }
+
+    #[test]
+    fn ignores_oversized_first_frame() {
+        let mut reassembler = Reassembler::new();
+
+        // Escaped first frame announcing 16 MiB.
+        assert!(reassembler.push(&frame(&[0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 1, 2])).is_none());
+        assert!(reassembler.pending.is_empty());
+        assert!(reassembler.push(&frame(&[0x21, 3, 4, 5, 6, 7, 8, 9])).is_none());
+    }
+
+    #[test]
+    fn parses_single_frame() {
+        assert_eq!(parse_frame(&[0x02, 0x01, 0x0C, 0x55]).unwrap(), Frame::Single { data: vec![0x01, 0x0C] });
+    }
+}
@@ -0,0 +1,71 @@
+use std::io;
+
+use crate::read_bytes::read_u16_le;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### L2CAP channel
+ * `Signaling`: BR/EDR signaling channel (CID 0x0001).
+ * `Connectionless`: Connectionless reception channel (CID 0x0002).
+ * `Att`: Attribute protocol, used by GATT (CID 0x0004).
+ * `LeSignaling`: LE signaling channel (CID 0x0005).
+ * `SecurityManager`: LE Security Manager protocol (CID 0x0006).
+ * `Dynamic`: Any other channel, dynamically allocated channels start at 0x0040.
+ */
+pub enum Channel {
+    Signaling,
+    Connectionless,
+    Att,
+    LeSignaling,
+    SecurityManager,
+    Dynamic(u16),
+}
+
+impl Channel {
+    pub fn from_u16(channel_id: u16) -> Channel {
+        match channel_id {
+            0x0001 => Channel::Signaling,
+            0x0002 => Channel::Connectionless,
+            0x0004 => Channel::Att,
+            0x0005 => Channel::LeSignaling,
+            0x0006 => Channel::SecurityManager,
+            _ => Channel::Dynamic(channel_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### L2CAP basic header structure
+ * Length (16 bits): The length of the information payload.
+ * Channel ID (16 bits): The destination channel.
+ */
+pub struct Header {
+    pub length: u16,
+    pub channel: Channel,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        4
+    }
+}
+
+/**
+ ### Parse the L2CAP basic header from the data
+
+ Both fields are little-endian.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse L2CAP header",
+        ));
+    }
+
+    let length = read_u16_le(data, 0)?;
+    let channel = Channel::from_u16(read_u16_le(data, 2)?);
+
+    Ok(Header { length, channel })
+}
@@ -0,0 +1,129 @@
+use std::io;
+
+use crate::read_bytes::read_u16_be;
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### SNAP header structure
+ * OUI (24 bits): The organization that defines the protocol ID. `00-00-00` means the protocol ID is an EtherType.
+ * Protocol ID (16 bits): The protocol of the payload.
+ */
+pub struct Snap {
+    pub oui: [u8; 3],
+    pub protocol_id: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### LLC control field
+ * `Information`: Numbered information transfer, 16 bits.
+ * `Supervisory`: Acknowledgement and flow control, 16 bits.
+ * `Unnumbered`: Connectionless transfer and link management, 8 bits. `0x03` is an unnumbered information (UI) frame.
+ */
+pub enum Control {
+    Information(u16),
+    Supervisory(u16),
+    Unnumbered(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### IEEE 802.2 LLC header structure
+ * DSAP (8 bits): Destination service access point.
+ * SSAP (8 bits): Source service access point, the lowest bit is the command/response bit.
+ * Control (8 or 16 bits): 8 bits for unnumbered frames, 16 bits for information and supervisory frames.
+ * SNAP (40 bits, optional): Present when both SAPs are `0xAA` and the frame is an unnumbered information frame.
+ */
+pub struct Header {
+    pub dsap: u8,
+    pub ssap: u8,
+    pub control: Control,
+    pub snap: Option<Snap>,
+}
+
+impl Header {
+    pub fn size(&self) -> usize {
+        let control = match self.control {
+            Control::Unnumbered(_) => 1,
+            _ => 2,
+        };
+        let snap = if self.snap.is_some() { 5 } else { 0 };
+        2 + control + snap
+    }
+}
+
+/**
+ ### Parse the LLC header, and the SNAP header if present, from the data
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse LLC header",
+        ));
+    }
+
+    let dsap = data[0];
+    let ssap = data[1];
+    // The lowest bits of the first control byte tell the frame format, and thereby the size of the field.
+    let control = match data[2] & 0x03 {
+        0x03 => Control::Unnumbered(data[2]),
+        format => {
+            let control = read_u16_be(data, 2).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Truncated LLC control field"))?;
+            if format == 0x01 {
+                Control::Supervisory(control)
+            } else {
+                Control::Information(control)
+            }
+        }
+    };
+
+    let snap = if dsap == 0xAA && ssap & 0xFE == 0xAA && control == Control::Unnumbered(0x03) {
+        match data.get(3..8) {
+            Some(bytes) => Some(Snap {
+                oui: [bytes[0], bytes[1], bytes[2]],
+                protocol_id: u16::from_be_bytes([bytes[3], bytes[4]]),
+            }),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Not enough data to parse SNAP header",
+                ))
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(Header {
+        dsap,
+        ssap,
+        control,
+        snap,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stp_llc() {
+        let header = parse(&[0x42, 0x42, 0x03, 0x00, 0x00]).unwrap();
+
+        assert_eq!(header.dsap, 0x42);
+        assert_eq!(header.control, Control::Unnumbered(0x03));
+        assert!(header.snap.is_none());
+        assert_eq!(header.size(), 3);
+    }
+
+    #[test]
+    fn parses_snap() {
+        let header = parse(&[0xAA, 0xAA, 0x03, 0x00, 0x00, 0x0C, 0x20, 0x00]).unwrap();
+        let snap = header.snap.unwrap();
+
+        assert_eq!(snap.oui, [0x00, 0x00, 0x0C]);
+        assert_eq!(snap.protocol_id, 0x2000);
+        assert_eq!(header.size(), 8);
+    }
+}
@@ -1,14 +1,75 @@
 use std::fmt::Debug;
 
+pub mod address;
+pub mod ah;
+pub mod arp;
+pub mod att;
+pub mod bgp;
+pub mod can;
+pub mod cdp;
+pub mod esp;
 pub mod ethernet;
+pub mod fragment;
+pub mod geneve;
+pub mod gre;
+pub mod hci;
+pub mod icmp;
+pub mod icmpv6;
+pub mod igmp;
 pub mod ipv4;
+pub mod ipv6;
+pub mod isotp;
+pub mod l2cap;
+pub mod llc;
+pub mod mpls;
+pub mod nflog;
+pub mod options;
+pub mod ospf;
+pub mod oui;
+pub mod ppp;
+pub mod pppoe;
+pub mod rip;
+pub mod stp;
 pub mod tcp;
+pub mod udp;
+pub mod usb;
+pub mod vxlan;
 pub mod parse;
 
 
 #[derive(Debug)]
 pub enum Protocol {
     Ethernet(ethernet::Header),
-    IPv4(ipv4::Header)
+    IPv4(ipv4::Header),
+    IPv6(ipv6::Header),
+    Arp(arp::Header),
+    Icmp(icmp::Header),
+    Icmpv6(icmpv6::Header),
+    Igmp(igmp::Header),
+    Gre(gre::Header),
+    Ah(ah::Header),
+    Esp(esp::Header),
+    Tcp(tcp::Header),
+    Udp(udp::Header),
+    Ospf(ospf::Header),
+    Bgp(bgp::Header),
+    Rip(rip::Header),
+    Vxlan(vxlan::Header),
+    Geneve(geneve::Header),
+    Mpls(mpls::Header),
+    Ppp(ppp::Header),
+    Pppoe(pppoe::Header),
+    Llc(llc::Header),
+    Stp(stp::Header),
+    Cdp(cdp::Header),
+    Usb(usb::Header),
+    Hci(hci::Header),
+    L2cap(l2cap::Header),
+    Att(att::Header),
+    Can(can::Header),
+    IsoTp(isotp::Message),
+    Nflog(nflog::Header),
+    Fragment(fragment::Fragment),
+    Reassembled(fragment::Datagram),
 }
 
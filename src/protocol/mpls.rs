@@ -0,0 +1,140 @@
+use std::io;
+
+use crate::read_bytes::read_u32_be;
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### MPLS label stack entry
+ * Label (20 bits): The label value used for forwarding.
+ * Traffic Class (3 bits): Used for QoS and ECN, formerly known as EXP.
+ * Bottom of Stack (1 bit): Set on the last entry of the label stack.
+ * Time to Live (TTL) (8 bits): The number of hops the packet can take before being discarded.
+ */
+pub struct Label {
+    pub label: u32,
+    pub traffic_class: u8,
+    pub bottom_of_stack: bool,
+    pub ttl: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### Payload carried below the label stack
+
+ MPLS does not announce what it carries, so the payload is guessed from the first nibble after the bottom of the stack:
+ * `4`: IPv4
+ * `6`: IPv6
+ * `0`: A pseudowire control word followed by an Ethernet frame.
+ * Anything else: An Ethernet frame without a control word.
+ */
+pub enum Payload {
+    IPv4,
+    IPv6,
+    Ethernet { control_word: Option<u32> },
+    Empty,
+}
+
+#[derive(Debug)]
+/**
+ ### MPLS header structure
+ * Labels: The complete label stack, outermost label first.
+ * Payload: The guessed type of the data following the label stack.
+ */
+pub struct Header {
+    pub labels: Vec<Label>,
+    pub payload: Payload,
+}
+
+impl Header {
+    /**
+     * Size of the label stack, including the pseudowire control word if present.
+     */
+    pub fn size(&self) -> usize {
+        let control_word = match self.payload {
+            Payload::Ethernet { control_word: Some(_) } => 4,
+            _ => 0,
+        };
+        self.labels.len() * 4 + control_word
+    }
+}
+
+/**
+ ### Parse the MPLS label stack from the data
+
+ Every label stack entry is 4 bytes long, entries are read until one has the bottom of stack bit set.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    let mut labels = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let entry = match read_u32_be(data, offset) {
+            Ok(entry) => entry,
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated MPLS label stack")),
+        };
+        offset += 4;
+
+        let label = Label {
+            label: entry >> 12,
+            traffic_class: ((entry >> 9) & 0x07) as u8,
+            bottom_of_stack: (entry >> 8) & 0x01 == 1,
+            ttl: (entry & 0xFF) as u8,
+        };
+        labels.push(label);
+
+        if label.bottom_of_stack {
+            break;
+        }
+    }
+
+    let payload = match data.get(offset) {
+        None => Payload::Empty,
+        Some(byte) => match byte >> 4 {
+            4 => Payload::IPv4,
+            6 => Payload::IPv6,
+            0 => match read_u32_be(data, offset) {
+                Ok(control_word) => Payload::Ethernet { control_word: Some(control_word) },
+                Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated pseudowire control word")),
+            },
+            _ => Payload::Ethernet { control_word: None },
+        },
+    };
+
+    Ok(Header { labels, payload })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_label_stack() {
+        // Two labels (16 and 17), the second one is bottom of stack, followed by an IPv4 header.
+        let data = [0x00, 0x01, 0x0A, 0x40, 0x00, 0x01, 0x13, 0x3F, 0x45, 0x00];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.labels.len(), 2);
+        assert_eq!(header.labels[0].label, 16);
+        assert_eq!(header.labels[0].traffic_class, 5);
+        assert!(!header.labels[0].bottom_of_stack);
+        assert_eq!(header.labels[0].ttl, 64);
+        assert_eq!(header.labels[1].label, 17);
+        assert!(header.labels[1].bottom_of_stack);
+        assert!(matches!(header.payload, Payload::IPv4));
+        assert_eq!(header.size(), 8);
+    }
+
+    #[test]
+    fn detects_pseudowire_control_word() {
+        let data = [0x00, 0x01, 0x01, 0xFF, 0x00, 0x00, 0x00, 0x01, 0xFF, 0xFF];
+        let header = parse(&data).unwrap();
+
+        assert!(matches!(header.payload, Payload::Ethernet { control_word: Some(1) }));
+        assert_eq!(header.size(), 8);
+    }
+
+    #[test]
+    fn rejects_truncated_stack() {
+        assert!(parse(&[0x00, 0x01, 0x00, 0x40]).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{io, ops::Range};
+
+use crate::{
+    pcap::byte_order::ByteOrder,
+    read_bytes::{read_u16_be, read_u16_with_byte_order, read_u32_be},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Netfilter hook the packet was logged from.
+ */
+pub enum Hook {
+    PreRouting,
+    Input,
+    Forward,
+    Output,
+    PostRouting,
+    Unsupported(u8),
+}
+
+#[derive(Debug, PartialEq)]
+/**
+ ### Decoded NFLOG TLV
+ The values are in network byte order, unlike the TLV headers.
+ * `PacketHeader`: The EtherType of the logged packet and the hook it was logged from.
+ * `Mark`: The netfilter mark.
+ * `Timestamp`: The time the packet was logged.
+ * `InputInterface` to `PhysicalOutputInterface`: Interface indexes.
+ * `HardwareAddress`: The link-layer source address.
+ * `Payload`: The logged packet, found at `Header::payload`.
+ * `Prefix`: The `--nflog-prefix` of the rule.
+ * `Uid`, `Gid`: The owner of the socket that sent the packet.
+ * `Sequence`, `GlobalSequence`: Per instance and global sequence numbers.
+ * `HardwareType`, `HardwareHeader`, `HardwareHeaderLength`: The link-layer header of the packet.
+ */
+pub enum Tlv {
+    PacketHeader { hardware_protocol: u16, hook: Hook },
+    Mark(u32),
+    Timestamp { seconds: u64, microseconds: u64 },
+    InputInterface(u32),
+    OutputInterface(u32),
+    PhysicalInputInterface(u32),
+    PhysicalOutputInterface(u32),
+    HardwareAddress(Vec<u8>),
+    Payload,
+    Prefix(String),
+    Uid(u32),
+    Sequence(u32),
+    GlobalSequence(u32),
+    Gid(u32),
+    HardwareType(u16),
+    HardwareHeader(Vec<u8>),
+    HardwareHeaderLength(u16),
+    Unknown { tlv_type: u16, value: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### NFLOG header structure
+ * Family (8 bits): The address family of the payload, 2 for IPv4 and 10 for IPv6.
+ * Version (8 bits): Always 0.
+ * Resource ID (16 bits, network byte order): The NFLOG group.
+ * TLVs (variable): Each TLV has a length (16 bits) and type (16 bits) in host byte order, the value, and padding to a multiple of 4 bytes.
+ * Payload: The position of the logged packet in the data, when present.
+ */
+pub struct Header {
+    pub family: u8,
+    pub version: u8,
+    pub resource_id: u16,
+    pub tlvs: Vec<Tlv>,
+    pub payload: Option<Range<usize>>,
+}
+
+impl Header {
+    pub fn prefix(&self) -> Option<&str> {
+        self.tlvs.iter().find_map(|tlv| match tlv {
+            Tlv::Prefix(prefix) => Some(prefix.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn hook(&self) -> Option<Hook> {
+        self.tlvs.iter().find_map(|tlv| match tlv {
+            Tlv::PacketHeader { hook, .. } => Some(*hook),
+            _ => None,
+        })
+    }
+
+    pub fn mark(&self) -> Option<u32> {
+        self.tlvs.iter().find_map(|tlv| match tlv {
+            Tlv::Mark(mark) => Some(*mark),
+            _ => None,
+        })
+    }
+
+    pub fn uid(&self) -> Option<u32> {
+        self.tlvs.iter().find_map(|tlv| match tlv {
+            Tlv::Uid(uid) => Some(*uid),
+            _ => None,
+        })
+    }
+}
+
+fn parse_tlv(tlv_type: u16, value: &[u8]) -> io::Result<Tlv> {
+    let read_u64 = |offset: usize| -> io::Result<u64> {
+        Ok(((read_u32_be(value, offset)? as u64) << 32) | read_u32_be(value, offset + 4)? as u64)
+    };
+    let tlv = match tlv_type {
+        1 => Tlv::PacketHeader {
+            hardware_protocol: read_u16_be(value, 0)?,
+            hook: match value.get(2) {
+                Some(0) => Hook::PreRouting,
+                Some(1) => Hook::Input,
+                Some(2) => Hook::Forward,
+                Some(3) => Hook::Output,
+                Some(4) => Hook::PostRouting,
+                Some(other) => Hook::Unsupported(*other),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated NFLOG packet header")),
+            },
+        },
+        2 => Tlv::Mark(read_u32_be(value, 0)?),
+        3 => Tlv::Timestamp {
+            seconds: read_u64(0)?,
+            microseconds: read_u64(8)?,
+        },
+        4 => Tlv::InputInterface(read_u32_be(value, 0)?),
+        5 => Tlv::OutputInterface(read_u32_be(value, 0)?),
+        6 => Tlv::PhysicalInputInterface(read_u32_be(value, 0)?),
+        7 => Tlv::PhysicalOutputInterface(read_u32_be(value, 0)?),
+        8 => {
+            // Address length (16 bits), padding (16 bits) and an 8 byte address field.
+            let length = read_u16_be(value, 0)? as usize;
+            Tlv::HardwareAddress(value.get(4..4 + length.min(8)).unwrap_or_default().to_vec())
+        }
+        9 => Tlv::Payload,
+        10 => {
+            let prefix = value.split(|byte| *byte == 0).next().unwrap_or_default();
+            Tlv::Prefix(String::from_utf8_lossy(prefix).into_owned())
+        }
+        11 => Tlv::Uid(read_u32_be(value, 0)?),
+        12 => Tlv::Sequence(read_u32_be(value, 0)?),
+        13 => Tlv::GlobalSequence(read_u32_be(value, 0)?),
+        14 => Tlv::Gid(read_u32_be(value, 0)?),
+        15 => Tlv::HardwareType(read_u16_be(value, 0)?),
+        16 => Tlv::HardwareHeader(value.to_vec()),
+        17 => Tlv::HardwareHeaderLength(read_u16_be(value, 0)?),
+        _ => Tlv::Unknown {
+            tlv_type,
+            value: value.to_vec(),
+        },
+    };
+    Ok(tlv)
+}
+
+/**
+ ### Parse the NFLOG header and its TLVs from the data
+
+ The TLV lengths and types are in the byte order of the capturing host, which is the byte order of the pcap file.
+ */
+pub fn parse(data: &[u8], byte_order: &ByteOrder) -> io::Result<Header> {
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse NFLOG header",
+        ));
+    }
+
+    let family = data[0];
+    let version = data[1];
+    let resource_id = read_u16_be(data, 2)?;
+
+    let mut tlvs = Vec::new();
+    let mut payload = None;
+    let mut offset = 4;
+    while offset + 4 <= data.len() {
+        let mut reader = &data[offset..];
+        let tlv_length = read_u16_with_byte_order(&mut reader, byte_order)? as usize;
+        // The upper bits of the type are netlink attribute flags.
+        let tlv_type = read_u16_with_byte_order(&mut reader, byte_order)? & 0x3FFF;
+        if tlv_length < 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid NFLOG TLV length"));
+        }
+        let value = match data.get(offset + 4..offset + tlv_length) {
+            Some(value) => value,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Truncated NFLOG TLV")),
+        };
+
+        if tlv_type == 9 {
+            payload = Some(offset + 4..offset + tlv_length);
+        }
+        tlvs.push(parse_tlv(tlv_type, value)?);
+
+        // TLVs are padded to a multiple of 4 bytes.
+        offset += (tlv_length + 3) & !3;
+    }
+
+    Ok(Header {
+        family,
+        version,
+        resource_id,
+        tlvs,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tlvs_and_payload() {
+        let data = [
+            0x02, 0x00, 0x00, 0x05, // IPv4, version 0, group 5
+            0x08, 0x00, 0x01, 0x00, 0x08, 0x00, 0x01, 0x00, // Packet header: IPv4, INPUT
+            0x09, 0x00, 0x0A, 0x00, b'D', b'R', b'O', b'P', 0x00, 0x00, 0x00, 0x00, // Prefix "DROP"
+            0x08, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x03, 0xE8, // UID 1000
+            0x08, 0x00, 0x09, 0x00, 0x45, 0x00, 0x00, 0x14, // Payload
+        ];
+        let header = parse(&data, &ByteOrder::LittleEndian).unwrap();
+
+        assert_eq!(header.family, 2);
+        assert_eq!(header.resource_id, 5);
+        assert_eq!(header.prefix(), Some("DROP"));
+        assert_eq!(header.hook(), Some(Hook::Input));
+        assert_eq!(header.uid(), Some(1000));
+        assert_eq!(header.payload, Some(36..40));
+    }
+}
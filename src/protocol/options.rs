@@ -0,0 +1,52 @@
+use super::{esp, ethernet::FcsMode, fragment, udp};
+
+#[derive(Debug, Clone)]
+/**
+ ### Options controlling how packets are dissected
+ * `fcs`: How to treat the frame check sequence at the end of Ethernet frames. Detected by default.
+ * `isotp_reassembly`: Interpret CAN frames as ISO-TP and reassemble segmented messages. Disabled by default, since any CAN frame can look like ISO-TP.
+ * `verify_ipv4_checksum`: Verify the header checksum of every IPv4 layer and store the result in its `checksum_status`. Disabled by default.
+ * `ip_reassembly`: Reassemble fragmented IPv4 and IPv6 datagrams with the given settings, and dissect them as if they had arrived whole. Disabled by default.
+ * `vxlan_ports`: UDP destination ports decoded as VXLAN. 4789 by default.
+ * `geneve_ports`: UDP destination ports decoded as Geneve. 6081 by default.
+ * `max_nesting_depth`: How many tunnels, like IP in IP, GRE, VXLAN and Geneve, and ICMP quoted datagrams are decapsulated
+   within each other. Anything nested deeper is left undissected with a warning. 8 by default.
+ * `esp_security_associations`: Keys to decrypt ESP payloads with, see `esp::load_security_associations`. None by default.
+ * `udp_dissectors`: Dissectors attached to UDP ports, tried on the destination port and then the source port,
+   before the built-in ones. None by default.
+ * `igmp_tracking`: Track multicast group membership from the IGMP messages, see `PCapA::igmp_tracker`. Disabled by default.
+   Memberships are kept per interface only for NFLOG captures, which record the interface, and under interface 0 otherwise.
+ * `bgp_reassembly`: Reassemble the TCP streams of BGP connections, so messages spanning segments are decoded. Disabled by default,
+   in which case only the complete messages at the start of each segment are decoded.
+ */
+pub struct Options {
+    pub fcs: FcsMode,
+    pub isotp_reassembly: bool,
+    pub verify_ipv4_checksum: bool,
+    pub ip_reassembly: Option<fragment::Settings>,
+    pub vxlan_ports: Vec<u16>,
+    pub geneve_ports: Vec<u16>,
+    pub max_nesting_depth: usize,
+    pub esp_security_associations: Vec<esp::SecurityAssociation>,
+    pub udp_dissectors: Vec<(u16, udp::Dissector)>,
+    pub igmp_tracking: bool,
+    pub bgp_reassembly: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            fcs: FcsMode::default(),
+            isotp_reassembly: false,
+            verify_ipv4_checksum: false,
+            ip_reassembly: None,
+            vxlan_ports: vec![4789],
+            geneve_ports: vec![6081],
+            max_nesting_depth: 8,
+            esp_security_associations: Vec::new(),
+            udp_dissectors: Vec::new(),
+            igmp_tracking: false,
+            bgp_reassembly: false,
+        }
+    }
+}
@@ -0,0 +1,690 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::{address::{IpAddress, IpPrefix}, ipv4::Address, ipv6};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * OSPF packet types, the same for OSPFv2 and OSPFv3.
+ */
+pub enum PacketType {
+    Hello,
+    DatabaseDescription,
+    LinkStateRequest,
+    LinkStateUpdate,
+    LinkStateAcknowledgment,
+    Unsupported(u8),
+}
+
+#[derive(Debug)]
+/**
+ ### LSA header structure
+ * LS Age (16 bits): Seconds since the LSA was originated.
+ * Options (8 bits): OSPFv2 only, the OSPFv3 options are part of the LSA body.
+ * LS Type (8 bits in OSPFv2, 16 bits in OSPFv3): The type of the LSA. In OSPFv3, the upper bits hold the flooding scope.
+ * Link State ID (32 bits), Advertising Router (32 bits): Identify the LSA together with the type.
+ * LS Sequence Number (32 bits): Tells newer instances apart.
+ * LS Checksum (16 bits): Fletcher checksum of the LSA without the age.
+ * Length (16 bits): The length of the LSA including the header.
+ */
+pub struct LsaHeader {
+    pub age: u16,
+    pub options: u8,
+    pub ls_type: u16,
+    pub link_state_id: Address,
+    pub advertising_router: Address,
+    pub sequence_number: u32,
+    pub checksum: u16,
+    pub length: u16,
+}
+
+#[derive(Debug)]
+/**
+ ### Link of an OSPFv2 router LSA
+ * Type: 1 point-to-point, 2 transit network, 3 stub network, 4 virtual link.
+ * ID, Data: Their meaning depends on the type, e.g. the network and mask for stub networks.
+ * Metric: The cost of the link. Per TOS metrics are skipped.
+ */
+pub struct RouterLink {
+    pub link_id: Address,
+    pub link_data: Address,
+    pub link_type: u8,
+    pub metric: u16,
+}
+
+#[derive(Debug)]
+/**
+ ### Interface of an OSPFv3 router LSA
+ * Type: 1 point-to-point, 2 transit network, 4 virtual link.
+ * Metric: The cost of the interface.
+ * Interface ID, Neighbor Interface ID, Neighbor Router ID: The interface and the neighbor, or the designated router
+   of a transit network, it connects to.
+ */
+pub struct RouterInterface {
+    pub link_type: u8,
+    pub metric: u16,
+    pub interface_id: u32,
+    pub neighbor_interface_id: u32,
+    pub neighbor_router_id: Address,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Address prefix of an OSPFv3 LSA
+ * Prefix: The IPv6 prefix. The LSA holds only the network bits, padded to 32 bit words.
+ * Options (8 bits): The DN, P (propagate), LA (local address) and NU (no unicast) bits.
+ */
+pub struct Prefix {
+    pub prefix: IpPrefix,
+    pub options: u8,
+}
+
+#[derive(Debug)]
+/**
+ ### LSA body
+ * `Router` (1): The router's flags (virtual link endpoint, ASBR, ABR) and links.
+ * `Network` (2): The network mask and the routers attached to the network.
+ * `Summary` (3 for networks, 4 for ASBRs): The network mask and metric.
+ * `External` (5 for AS external, 7 for NSSA): The network mask, external metric type, metric, forwarding address and route tag.
+ * `RouterV3` (0x2001): The router's flags, options and interfaces.
+ * `NetworkV3` (0x2002): The options and the routers attached to the network.
+ * `InterAreaPrefix` (0x2003): The metric to a prefix in another area.
+ * `InterAreaRouter` (0x2004): The options of and metric to an ASBR in another area.
+ * `ExternalV3` (0x4005 for AS external, 0x2007 for NSSA): The external metric type, metric and prefix,
+   and the optional forwarding address, route tag and referenced LSA.
+ * `Link` (0x0008): The router's priority, options, link-local address and the prefixes of the link.
+ * `IntraAreaPrefix` (0x2009): The router or network LSA the prefixes belong to, and the prefixes with their metrics.
+ * `Other`: Opaque LSAs, unknown OSPFv3 LSAs and anything else, as raw data.
+
+ OSPFv3 LSAs are recognized by the function code, the lower 13 bits of the type, so the flooding scope and U bit don't matter.
+ */
+pub enum Lsa {
+    Router { flags: u8, links: Vec<RouterLink> },
+    Network { mask: Address, attached_routers: Vec<Address> },
+    Summary { mask: Address, metric: u32 },
+    External { mask: Address, type_2_metric: bool, metric: u32, forwarding_address: Address, route_tag: u32 },
+    RouterV3 { flags: u8, options: u32, interfaces: Vec<RouterInterface> },
+    NetworkV3 { options: u32, attached_routers: Vec<Address> },
+    InterAreaPrefix { metric: u32, prefix: Prefix },
+    InterAreaRouter { options: u32, metric: u32, destination_router_id: Address },
+    ExternalV3 {
+        type_2_metric: bool,
+        metric: u32,
+        prefix: Prefix,
+        forwarding_address: Option<ipv6::Address>,
+        route_tag: Option<u32>,
+        referenced_ls_type: u16,
+        referenced_link_state_id: Option<Address>,
+    },
+    Link { priority: u8, options: u32, link_local_address: ipv6::Address, prefixes: Vec<Prefix> },
+    IntraAreaPrefix {
+        referenced_ls_type: u16,
+        referenced_link_state_id: Address,
+        referenced_advertising_router: Address,
+        prefixes: Vec<(Prefix, u16)>,
+    },
+    Other(Vec<u8>),
+}
+
+#[derive(Debug)]
+/**
+ * A link state request entry, identifying an LSA.
+ */
+pub struct LsaRequest {
+    pub ls_type: u32,
+    pub link_state_id: Address,
+    pub advertising_router: Address,
+}
+
+#[derive(Debug)]
+/**
+ ### OSPF packet body
+ * `Hello`: The network mask (OSPFv2) or interface ID (OSPFv3), timers, priority, designated routers and the neighbors seen.
+ * `DatabaseDescription`: The interface MTU, the init, more and master flags, the sequence number and LSA headers.
+ * `LinkStateRequest`: The requested LSAs.
+ * `LinkStateUpdate`: Complete LSAs, each with its header and body.
+ * `LinkStateAcknowledgment`: The headers of the acknowledged LSAs.
+ * `Unknown`: Any other packet type, as raw data.
+ */
+pub enum Packet {
+    Hello {
+        network_mask: Option<Address>,
+        interface_id: Option<u32>,
+        hello_interval: u16,
+        options: u32,
+        priority: u8,
+        dead_interval: u32,
+        designated_router: Address,
+        backup_designated_router: Address,
+        neighbors: Vec<Address>,
+    },
+    DatabaseDescription {
+        interface_mtu: u16,
+        options: u32,
+        init: bool,
+        more: bool,
+        master: bool,
+        sequence_number: u32,
+        lsa_headers: Vec<LsaHeader>,
+    },
+    LinkStateRequest { requests: Vec<LsaRequest> },
+    LinkStateUpdate { lsas: Vec<(LsaHeader, Lsa)> },
+    LinkStateAcknowledgment { lsa_headers: Vec<LsaHeader> },
+    Unknown(Vec<u8>),
+}
+
+#[derive(Debug)]
+/**
+ ### OSPF header structure
+ * Version (8 bits): 2 for OSPFv2 (RFC 2328), 3 for OSPFv3 (RFC 5340).
+ * Type (8 bits): The packet type.
+ * Packet Length (16 bits): The length of the packet including the header.
+ * Router ID (32 bits): The originating router.
+ * Area ID (32 bits): The area the packet belongs to.
+ * Checksum (16 bits)
+ * Authentication Type (16 bits) and Authentication (64 bits): OSPFv2 only.
+ * Instance ID (8 bits): OSPFv3 only, to run several instances on a link.
+ * Packet: The decoded packet body.
+ */
+pub struct Header {
+    pub version: u8,
+    pub packet_type: PacketType,
+    pub packet_length: u16,
+    pub router_id: Address,
+    pub area_id: Address,
+    pub checksum: u16,
+    pub authentication_type: Option<u16>,
+    pub authentication: Option<[u8; 8]>,
+    pub instance_id: Option<u8>,
+    pub packet: Packet,
+}
+
+impl Header {
+    pub fn size(&self) -> usize {
+        match self.version {
+            2 => 24,
+            _ => 16,
+        }
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Truncated OSPF packet")
+}
+
+fn read_address(data: &[u8], offset: usize) -> io::Result<Address> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(Address::new([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(truncated()),
+    }
+}
+
+fn read_addresses(data: &[u8]) -> Vec<Address> {
+    data.chunks_exact(4).map(|chunk| Address::new([chunk[0], chunk[1], chunk[2], chunk[3]])).collect()
+}
+
+fn parse_lsa_header(version: u8, data: &[u8]) -> io::Result<LsaHeader> {
+    if data.len() < 20 {
+        return Err(truncated());
+    }
+    let (options, ls_type) = match version {
+        2 => (data[2], data[3] as u16),
+        _ => (0, read_u16_be(data, 2)?),
+    };
+    Ok(LsaHeader {
+        age: read_u16_be(data, 0)?,
+        options,
+        ls_type,
+        link_state_id: read_address(data, 4)?,
+        advertising_router: read_address(data, 8)?,
+        sequence_number: read_u32_be(data, 12)?,
+        checksum: read_u16_be(data, 16)?,
+        length: read_u16_be(data, 18)?,
+    })
+}
+
+fn parse_lsa_headers(version: u8, data: &[u8]) -> io::Result<Vec<LsaHeader>> {
+    data.chunks(20).map(|chunk| parse_lsa_header(version, chunk)).collect()
+}
+
+fn read_ipv6_address(data: &[u8], offset: usize) -> io::Result<ipv6::Address> {
+    let bytes = data.get(offset..offset + 16).ok_or_else(truncated)?;
+    let mut address = [0u8; 16];
+    address.copy_from_slice(bytes);
+    Ok(ipv6::Address::new(address))
+}
+
+/**
+ * Read an OSPFv3 prefix at the offset, and return it with the offset following it.
+ * The two bytes between the prefix options and the address are left to the caller.
+ */
+fn read_prefix(data: &[u8], offset: usize) -> io::Result<(Prefix, usize)> {
+    let length = *data.get(offset).ok_or_else(truncated)?;
+    let options = *data.get(offset + 1).ok_or_else(truncated)?;
+    if length > 128 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid OSPFv3 prefix length"));
+    }
+    let end = offset + 4 + (length as usize + 31) / 32 * 4;
+    let mut address = [0u8; 16];
+    let bytes = data.get(offset + 4..end).ok_or_else(truncated)?;
+    address[..bytes.len()].copy_from_slice(bytes);
+    let prefix = IpPrefix::new(IpAddress::V6(ipv6::Address::new(address)), length).ok_or_else(truncated)?;
+    Ok((Prefix { prefix, options }, end))
+}
+
+fn parse_v3_lsa(function_code: u16, body: &[u8]) -> io::Result<Lsa> {
+    let read_u32 = |offset: usize| read_u32_be(body, offset).map_err(|_| truncated());
+    let read_u16 = |offset: usize| read_u16_be(body, offset).map_err(|_| truncated());
+    let lsa = match function_code {
+        1 => {
+            let interfaces = body
+                .get(4..)
+                .ok_or_else(truncated)?
+                .chunks(16)
+                .map(|entry| {
+                    Ok(RouterInterface {
+                        link_type: entry[0],
+                        metric: read_u16_be(entry, 2).map_err(|_| truncated())?,
+                        interface_id: read_u32_be(entry, 4).map_err(|_| truncated())?,
+                        neighbor_interface_id: read_u32_be(entry, 8).map_err(|_| truncated())?,
+                        neighbor_router_id: read_address(entry, 12)?,
+                    })
+                })
+                .collect::<io::Result<Vec<RouterInterface>>>()?;
+            let options = read_u32(0)?;
+            Lsa::RouterV3 { flags: (options >> 24) as u8, options: options & 0x00FF_FFFF, interfaces }
+        }
+        2 => Lsa::NetworkV3 {
+            options: read_u32(0)? & 0x00FF_FFFF,
+            attached_routers: read_addresses(&body[4..]),
+        },
+        3 => Lsa::InterAreaPrefix {
+            metric: read_u32(0)? & 0x00FF_FFFF,
+            prefix: read_prefix(body, 4)?.0,
+        },
+        4 => Lsa::InterAreaRouter {
+            options: read_u32(0)? & 0x00FF_FFFF,
+            metric: read_u32(4)? & 0x00FF_FFFF,
+            destination_router_id: read_address(body, 8)?,
+        },
+        5 | 7 => {
+            let flags = read_u32(0)?;
+            let (prefix, mut offset) = read_prefix(body, 4)?;
+            let referenced_ls_type = read_u16(6)?;
+            let forwarding_address = match flags & 0x0200_0000 {
+                0 => None,
+                _ => {
+                    offset += 16;
+                    Some(read_ipv6_address(body, offset - 16)?)
+                }
+            };
+            let route_tag = match flags & 0x0100_0000 {
+                0 => None,
+                _ => {
+                    offset += 4;
+                    Some(read_u32(offset - 4)?)
+                }
+            };
+            Lsa::ExternalV3 {
+                type_2_metric: flags & 0x0400_0000 != 0,
+                metric: flags & 0x00FF_FFFF,
+                prefix,
+                forwarding_address,
+                route_tag,
+                referenced_ls_type,
+                referenced_link_state_id: match referenced_ls_type {
+                    0 => None,
+                    _ => Some(read_address(body, offset)?),
+                },
+            }
+        }
+        8 => {
+            let count = read_u32(20)?;
+            let mut prefixes = Vec::new();
+            let mut offset = 24;
+            for _ in 0..count {
+                let (prefix, next) = read_prefix(body, offset)?;
+                prefixes.push(prefix);
+                offset = next;
+            }
+            let options = read_u32(0)?;
+            Lsa::Link {
+                priority: (options >> 24) as u8,
+                options: options & 0x00FF_FFFF,
+                link_local_address: read_ipv6_address(body, 4)?,
+                prefixes,
+            }
+        }
+        9 => {
+            let count = read_u16(0)?;
+            let mut prefixes = Vec::new();
+            let mut offset = 12;
+            for _ in 0..count {
+                let (prefix, next) = read_prefix(body, offset)?;
+                prefixes.push((prefix, read_u16(offset + 2)?));
+                offset = next;
+            }
+            Lsa::IntraAreaPrefix {
+                referenced_ls_type: read_u16(2)?,
+                referenced_link_state_id: read_address(body, 4)?,
+                referenced_advertising_router: read_address(body, 8)?,
+                prefixes,
+            }
+        }
+        _ => Lsa::Other(body.to_vec()),
+    };
+    Ok(lsa)
+}
+
+fn parse_lsa(version: u8, ls_type: u16, body: &[u8]) -> io::Result<Lsa> {
+    let read_u32 = |offset: usize| read_u32_be(body, offset).map_err(|_| truncated());
+    let lsa = match (version, ls_type) {
+        (2, 1) => {
+            let count = read_u16_be(body, 2).map_err(|_| truncated())?;
+            let mut links = Vec::new();
+            let mut offset = 4;
+            for _ in 0..count {
+                let tos_count = *body.get(offset + 9).ok_or_else(truncated)? as usize;
+                links.push(RouterLink {
+                    link_id: read_address(body, offset)?,
+                    link_data: read_address(body, offset + 4)?,
+                    link_type: body[offset + 8],
+                    metric: read_u16_be(body, offset + 10).map_err(|_| truncated())?,
+                });
+                offset += 12 + tos_count * 4;
+            }
+            Lsa::Router { flags: *body.first().ok_or_else(truncated)?, links }
+        }
+        (2, 2) => Lsa::Network {
+            mask: read_address(body, 0)?,
+            attached_routers: read_addresses(&body[4..]),
+        },
+        (2, 3) | (2, 4) => Lsa::Summary {
+            mask: read_address(body, 0)?,
+            metric: read_u32(4)? & 0x00FF_FFFF,
+        },
+        (2, 5) | (2, 7) => {
+            let metric = read_u32(4)?;
+            Lsa::External {
+                mask: read_address(body, 0)?,
+                type_2_metric: metric & 0x8000_0000 != 0,
+                metric: metric & 0x00FF_FFFF,
+                forwarding_address: read_address(body, 8)?,
+                route_tag: read_u32(12)?,
+            }
+        }
+        (3, _) => parse_v3_lsa(ls_type & 0x1FFF, body)?,
+        _ => Lsa::Other(body.to_vec()),
+    };
+    Ok(lsa)
+}
+
+fn parse_packet(version: u8, packet_type: PacketType, body: &[u8]) -> io::Result<Packet> {
+    let read_u32 = |offset: usize| read_u32_be(body, offset).map_err(|_| truncated());
+    let read_u16 = |offset: usize| read_u16_be(body, offset).map_err(|_| truncated());
+    let packet = match (packet_type, version) {
+        (PacketType::Hello, 2) => Packet::Hello {
+            network_mask: Some(read_address(body, 0)?),
+            interface_id: None,
+            hello_interval: read_u16(4)?,
+            options: body[6] as u32,
+            priority: body[7],
+            dead_interval: read_u32(8)?,
+            designated_router: read_address(body, 12)?,
+            backup_designated_router: read_address(body, 16)?,
+            neighbors: read_addresses(&body[20..]),
+        },
+        (PacketType::Hello, _) => Packet::Hello {
+            network_mask: None,
+            interface_id: Some(read_u32(0)?),
+            hello_interval: read_u16(8)?,
+            options: read_u32(4)? & 0x00FF_FFFF,
+            priority: body[4],
+            dead_interval: read_u16(10)? as u32,
+            designated_router: read_address(body, 12)?,
+            backup_designated_router: read_address(body, 16)?,
+            neighbors: read_addresses(&body[20..]),
+        },
+        (PacketType::DatabaseDescription, _) => {
+            let (interface_mtu, options, flags) = match version {
+                2 => (read_u16(0)?, *body.get(2).ok_or_else(truncated)? as u32, *body.get(3).ok_or_else(truncated)?),
+                _ => (read_u16(4)?, read_u32(0)? & 0x00FF_FFFF, *body.get(7).ok_or_else(truncated)?),
+            };
+            let headers_start = match version {
+                2 => 8,
+                _ => 12,
+            };
+            Packet::DatabaseDescription {
+                interface_mtu,
+                options,
+                init: flags & 0x04 != 0,
+                more: flags & 0x02 != 0,
+                master: flags & 0x01 != 0,
+                sequence_number: read_u32(headers_start - 4)?,
+                lsa_headers: parse_lsa_headers(version, &body[headers_start..])?,
+            }
+        }
+        (PacketType::LinkStateRequest, _) => {
+            let requests = body
+                .chunks(12)
+                .map(|entry| {
+                    Ok(LsaRequest {
+                        ls_type: read_u32_be(entry, 0).map_err(|_| truncated())? & if version == 2 { 0xFFFF_FFFF } else { 0xFFFF },
+                        link_state_id: read_address(entry, 4)?,
+                        advertising_router: read_address(entry, 8)?,
+                    })
+                })
+                .collect::<io::Result<Vec<LsaRequest>>>()?;
+            Packet::LinkStateRequest { requests }
+        }
+        (PacketType::LinkStateUpdate, _) => {
+            let count = read_u32(0)?;
+            let mut lsas = Vec::new();
+            let mut offset = 4;
+            for _ in 0..count {
+                let header = parse_lsa_header(version, body.get(offset..).unwrap_or_default())?;
+                let length = header.length as usize;
+                if length < 20 {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid LSA length"));
+                }
+                let lsa_body = body.get(offset + 20..offset + length).ok_or_else(truncated)?;
+                let lsa = parse_lsa(version, header.ls_type, lsa_body)?;
+                lsas.push((header, lsa));
+                offset += length;
+            }
+            Packet::LinkStateUpdate { lsas }
+        }
+        (PacketType::LinkStateAcknowledgment, _) => Packet::LinkStateAcknowledgment {
+            lsa_headers: parse_lsa_headers(version, body)?,
+        },
+        (PacketType::Unsupported(_), _) => Packet::Unknown(body.to_vec()),
+    };
+    Ok(packet)
+}
+
+/**
+ ### Parse the OSPFv2 or OSPFv3 header and packet from the data
+
+ The packet length bounds the packet, so trailing data like the OSPFv2 cryptographic authentication digest is ignored.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse OSPF header",
+        ));
+    }
+
+    let version = data[0];
+    let header_length = match version {
+        2 => 24,
+        3 => 16,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported OSPF version")),
+    };
+    let packet_type = match data[1] {
+        1 => PacketType::Hello,
+        2 => PacketType::DatabaseDescription,
+        3 => PacketType::LinkStateRequest,
+        4 => PacketType::LinkStateUpdate,
+        5 => PacketType::LinkStateAcknowledgment,
+        other => PacketType::Unsupported(other),
+    };
+    let packet_length = read_u16_be(data, 2)?;
+    let body = match data.get(header_length..packet_length as usize) {
+        Some(body) => body,
+        None => return Err(truncated()),
+    };
+    if packet_type == PacketType::Hello && body.len() < 20 {
+        return Err(truncated());
+    }
+
+    let (authentication_type, authentication, instance_id) = match version {
+        2 => {
+            let mut authentication = [0u8; 8];
+            authentication.copy_from_slice(&data[16..24]);
+            (Some(read_u16_be(data, 14)?), Some(authentication), None)
+        }
+        _ => (None, None, Some(data[14])),
+    };
+
+    Ok(Header {
+        version,
+        packet_type,
+        packet_length,
+        router_id: read_address(data, 4)?,
+        area_id: read_address(data, 8)?,
+        checksum: read_u16_be(data, 12)?,
+        authentication_type,
+        authentication,
+        instance_id,
+        packet: parse_packet(version, packet_type, body)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v2_hello() {
+        let mut data = vec![2, 1, 0x00, 0x30, 1, 1, 1, 1, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(&[255, 255, 255, 0, 0x00, 0x0A, 0x02, 1, 0x00, 0x00, 0x00, 0x28]);
+        data.extend_from_slice(&[10, 0, 0, 1, 0, 0, 0, 0, 2, 2, 2, 2]);
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.router_id.address, [1, 1, 1, 1]);
+        assert_eq!(header.authentication_type, Some(0));
+        match header.packet {
+            Packet::Hello { network_mask, hello_interval, dead_interval, designated_router, neighbors, .. } => {
+                assert_eq!(network_mask.unwrap().address, [255, 255, 255, 0]);
+                assert_eq!(hello_interval, 10);
+                assert_eq!(dead_interval, 40);
+                assert_eq!(designated_router.address, [10, 0, 0, 1]);
+                assert_eq!(neighbors.len(), 1);
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_v2_link_state_update() {
+        let mut data = vec![2, 4, 0x00, 0x40, 1, 1, 1, 1, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+        // Router LSA with one stub network link.
+        data.extend_from_slice(&[0x00, 0x01, 0x02, 0x01, 1, 1, 1, 1, 1, 1, 1, 1, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x24]);
+        data.extend_from_slice(&[0x02, 0x00, 0x00, 0x01, 10, 1, 0, 0, 255, 255, 0, 0, 3, 0, 0x00, 0x0A]);
+        let header = parse(&data).unwrap();
+
+        match header.packet {
+            Packet::LinkStateUpdate { lsas } => {
+                assert_eq!(lsas.len(), 1);
+                assert_eq!(lsas[0].0.ls_type, 1);
+                assert_eq!(lsas[0].0.sequence_number, 0x80000001);
+                match &lsas[0].1 {
+                    Lsa::Router { flags, links } => {
+                        assert_eq!(*flags, 0x02);
+                        assert_eq!(links[0].link_type, 3);
+                        assert_eq!(links[0].metric, 10);
+                    }
+                    other => panic!("Unexpected LSA: {:?}", other),
+                }
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_v3_link_state_update() {
+        fn lsa(ls_type: u16, body: &[u8]) -> Vec<u8> {
+            let mut data = vec![0x00, 0x01];
+            data.extend_from_slice(&ls_type.to_be_bytes());
+            data.extend_from_slice(&[0, 0, 0, 0, 1, 1, 1, 1, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00]);
+            data.extend_from_slice(&(20 + body.len() as u16).to_be_bytes());
+            data.extend_from_slice(body);
+            data
+        }
+        let link_local = [0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let mut lsas = vec![0x00, 0x00, 0x00, 0x04];
+        // Router LSA with a point-to-point interface.
+        lsas.extend(lsa(0x2001, &[0x01, 0x00, 0x00, 0x13, 1, 0, 0x00, 0x0A, 0, 0, 0, 5, 0, 0, 0, 7, 2, 2, 2, 2]));
+        // Link LSA with 2001:db8:1::/64.
+        let mut link = vec![1, 0x00, 0x00, 0x13];
+        link.extend_from_slice(&link_local);
+        link.extend_from_slice(&[0, 0, 0, 1, 64, 0, 0, 0, 0x20, 0x01, 0x0D, 0xB8, 0x00, 0x01, 0x00, 0x00]);
+        lsas.extend(lsa(0x0008, &link));
+        // Intra-area prefix LSA of the router LSA, with 2001:db8:1::/64 at metric 10.
+        lsas.extend(lsa(0x2009, &[0, 1, 0x20, 0x01, 0, 0, 0, 0, 1, 1, 1, 1, 64, 0, 0, 10, 0x20, 0x01, 0x0D, 0xB8, 0x00, 0x01, 0x00, 0x00]));
+        // AS external LSA for 2001:db8:2::/48 with type 2 metric 20, forwarding address and route tag.
+        let mut external = vec![0x07, 0x00, 0x00, 0x14, 48, 0, 0, 0, 0x20, 0x01, 0x0D, 0xB8, 0x00, 0x02, 0x00, 0x00];
+        external.extend_from_slice(&[0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        external.extend_from_slice(&[0, 0, 0, 42]);
+        lsas.extend(lsa(0x4005, &external));
+
+        let mut data = vec![3, 4];
+        data.extend_from_slice(&(16 + lsas.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[1, 1, 1, 1, 0, 0, 0, 0, 0x00, 0x00, 0, 0]);
+        data.extend(lsas);
+        let header = parse(&data).unwrap();
+
+        let Packet::LinkStateUpdate { lsas } = header.packet else {
+            panic!("Unexpected packet: {:?}", header.packet);
+        };
+        assert_eq!(lsas.len(), 4);
+        match &lsas[0].1 {
+            Lsa::RouterV3 { flags, options, interfaces } => {
+                assert_eq!((*flags, *options), (0x01, 0x13));
+                assert_eq!(interfaces[0].metric, 10);
+                assert_eq!(interfaces[0].neighbor_interface_id, 7);
+                assert_eq!(interfaces[0].neighbor_router_id.address, [2, 2, 2, 2]);
+            }
+            other => panic!("Unexpected LSA: {:?}", other),
+        }
+        match &lsas[1].1 {
+            Lsa::Link { priority, link_local_address, prefixes, .. } => {
+                assert_eq!(*priority, 1);
+                assert_eq!(link_local_address.address, link_local);
+                assert_eq!(prefixes[0].prefix, "2001:db8:1::/64".parse().unwrap());
+            }
+            other => panic!("Unexpected LSA: {:?}", other),
+        }
+        match &lsas[2].1 {
+            Lsa::IntraAreaPrefix { referenced_ls_type, prefixes, .. } => {
+                assert_eq!(*referenced_ls_type, 0x2001);
+                assert_eq!(prefixes, &[(Prefix { prefix: "2001:db8:1::/64".parse().unwrap(), options: 0 }, 10)]);
+            }
+            other => panic!("Unexpected LSA: {:?}", other),
+        }
+        match &lsas[3].1 {
+            Lsa::ExternalV3 { type_2_metric, metric, prefix, forwarding_address, route_tag, referenced_link_state_id, .. } => {
+                assert!(*type_2_metric);
+                assert_eq!(*metric, 20);
+                assert_eq!(prefix.prefix, "2001:db8:2::/48".parse().unwrap());
+                assert_eq!(forwarding_address.unwrap().address[15], 1);
+                assert_eq!(*route_tag, Some(42));
+                assert_eq!(*referenced_link_state_id, None);
+            }
+            other => panic!("Unexpected LSA: {:?}", other),
+        }
+    }
+}
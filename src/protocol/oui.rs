@@ -0,0 +1,183 @@
+use std::{collections::HashMap, fs, io, sync::OnceLock};
+
+use super::ethernet::MacAddress;
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### Vendor of a MAC address prefix
+ * Short Name: The abbreviated name used in resolved addresses, e.g. `Intel`.
+ * Name: The full name of the organization, if known.
+ */
+pub struct Vendor {
+    pub short_name: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+/**
+ ### OUI registry
+
+ Maps MAC address prefixes to vendors. Besides 24 bit MA-L assignments, it holds the 28 bit MA-M and 36 bit MA-S
+ assignments carved out of the IEEE Registration Authority blocks, and lookups prefer the longest matching prefix.
+ */
+pub struct Registry {
+    vendors: HashMap<(u8, u64), Vendor>,
+    lengths: Vec<u8>,
+}
+
+fn mac_to_u64(bytes: &[u8; 6]) -> u64 {
+    bytes.iter().fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn masked(value: u64, length: u8) -> u64 {
+    value & !u64::MAX.checked_shr(16 + length as u32).unwrap_or(0)
+}
+
+impl Registry {
+    /**
+     * The registry embedded in the crate, from `src/protocol/oui/manuf`. `src/protocol/oui/generate.py` builds that file
+     * from the IEEE MA-L, MA-M and MA-S registries.
+     */
+    pub fn embedded() -> &'static Registry {
+        static EMBEDDED: OnceLock<Registry> = OnceLock::new();
+        EMBEDDED.get_or_init(|| Registry::parse(include_str!("oui/manuf")).unwrap_or_default())
+    }
+
+    /**
+     ### Parse a registry in the format of Wireshark's manuf file
+
+     Each line holds a prefix, the short name and the full name, separated by whitespace. A prefix is three bytes,
+     or six bytes followed by the number of significant bits, e.g. `70:B3:D5:00:00:00/36`. Bytes may be separated
+     by colons, dashes or dots. Empty lines and comments starting with `#` are ignored.
+     */
+    pub fn parse(text: &str) -> io::Result<Registry> {
+        let mut registry = Registry::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid manuf line: {}", line));
+            let (prefix, rest) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let rest = rest.trim_start();
+            let (short_name, name) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            // Older manuf files keep the full name in a trailing comment.
+            let name = Some(name.trim().trim_start_matches('#').trim()).filter(|name| !name.is_empty()).map(str::to_string);
+
+            let (address, length) = match prefix.split_once('/') {
+                Some((address, length)) => (address, Some(length.parse::<u8>().map_err(|_| invalid())?)),
+                None => (prefix, None),
+            };
+            let bytes = address
+                .split([':', '-', '.'])
+                .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| invalid()))
+                .collect::<io::Result<Vec<u8>>>()?;
+            if bytes.is_empty() || bytes.len() > 6 {
+                return Err(invalid());
+            }
+            let length = length.unwrap_or(bytes.len() as u8 * 8);
+            if length == 0 || length > 48 {
+                return Err(invalid());
+            }
+            let mut padded = [0u8; 6];
+            padded[..bytes.len()].copy_from_slice(&bytes);
+
+            registry.insert(length, mac_to_u64(&padded), Vendor { short_name: short_name.to_string(), name });
+        }
+        Ok(registry)
+    }
+
+    /**
+     * Load a registry from a Wireshark manuf file, to use a more recent registry than the embedded one.
+     */
+    pub fn load(path: &str) -> io::Result<Registry> {
+        Registry::parse(&fs::read_to_string(path)?)
+    }
+
+    fn insert(&mut self, length: u8, prefix: u64, vendor: Vendor) {
+        self.vendors.insert((length, masked(prefix, length)), vendor);
+        if !self.lengths.contains(&length) {
+            self.lengths.push(length);
+            self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+
+    /**
+     * The vendor of the longest prefix matching the address.
+     */
+    pub fn lookup(&self, mac: &MacAddress) -> Option<&Vendor> {
+        let value = mac_to_u64(&mac.bytes);
+        self.lengths.iter().find_map(|length| self.vendors.get(&(*length, masked(value, *length))))
+    }
+
+    /**
+     * Format the address with the short vendor name in place of the OUI, e.g. `Intel_3a:4f:1c`.
+     * Addresses of unknown vendors are formatted as usual.
+     */
+    pub fn resolve(&self, mac: &MacAddress) -> String {
+        match self.lookup(mac) {
+            Some(vendor) => {
+                let [_, _, _, d, e, f] = mac.bytes;
+                format!("{}_{:02x}:{:02x}:{:02x}", vendor.short_name, d, e, f)
+            }
+            None => mac.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_embedded_vendors() {
+        let mac = MacAddress::new([0x00, 0x02, 0xB3, 0x3A, 0x4F, 0x1C]);
+
+        assert_eq!(mac.vendor().map(|vendor| vendor.short_name.as_str()), Some("Intel"));
+        assert_eq!(mac.resolved(), "Intel_3a:4f:1c");
+        assert_eq!(MacAddress::new([0x02, 0, 0, 0, 0, 1]).resolved(), "02:00:00:00:00:01");
+    }
+
+    #[test]
+    fn resolves_embedded_ma_m_and_ma_s_assignments() {
+        let ma_s = MacAddress::new([0x00, 0x1B, 0xC5, 0x00, 0x01, 0x2C]);
+        assert_eq!(ma_s.vendor().and_then(|vendor| vendor.name.as_deref()), Some("Converging Systems Inc."));
+        assert_eq!(ma_s.resolved(), "Convergi_00:01:2c");
+        let ma_m = MacAddress::new([0x00, 0x55, 0xDA, 0x01, 0x02, 0x03]);
+        assert_eq!(ma_m.resolved(), "ShinkoTe_01:02:03");
+
+        // Addresses in the rest of a block of the Registration Authority fall back to the block.
+        assert_eq!(MacAddress::new([0x00, 0x1B, 0xC5, 0x00, 0x10, 0x00]).resolved(), "IEEERegi_00:10:00");
+    }
+
+    #[test]
+    fn prefers_longest_prefix() {
+        let registry = Registry::parse(concat!(
+            "# Test registry\n",
+            "70:B3:D5\tIEEERegi\t# IEEE Registration Authority\n",
+            "70-B3-D5-00-10-00/36\tSensorCo\tSensor Company Ltd\n",
+            "70:B3:D5:10:00:00/28\tBoardCo\n",
+        ))
+        .unwrap();
+
+        assert_eq!(registry.len(), 3);
+        let ma_s = MacAddress::new([0x70, 0xB3, 0xD5, 0x00, 0x10, 0x0A]);
+        assert_eq!(registry.lookup(&ma_s).unwrap().name.as_deref(), Some("Sensor Company Ltd"));
+        let ma_m = MacAddress::new([0x70, 0xB3, 0xD5, 0x1F, 0xFF, 0x00]);
+        assert_eq!(registry.resolve(&ma_m), "BoardCo_1f:ff:00");
+        assert_eq!(ma_m.resolved_with(&registry), "BoardCo_1f:ff:00");
+        assert_eq!(ma_s.vendor_with(&registry).map(|vendor| vendor.short_name.as_str()), Some("SensorCo"));
+        let ma_l = MacAddress::new([0x70, 0xB3, 0xD5, 0x00, 0x20, 0x00]);
+        assert_eq!(registry.lookup(&ma_l).unwrap().name.as_deref(), Some("IEEE Registration Authority"));
+
+        assert!(Registry::parse("70:B3:D5/52\tBroken\n").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+#!/usr/bin/env python3
+"""Generate the embedded OUI registry from the IEEE registries.
+
+Usage: generate.py oui.csv mam.csv oui36.csv iab.csv > manuf
+
+The CSV files are published by the IEEE Registration Authority, e.g. https://standards-oui.ieee.org/oui/oui.csv,
+https://standards-oui.ieee.org/oui28/mam.csv, https://standards-oui.ieee.org/oui36/oui36.csv and
+https://standards-oui.ieee.org/iab/iab.csv. The prefix length follows from the width of the assignment:
+6 hex digits for MA-L, 7 for MA-M and 9 for MA-S and IAB.
+"""
+
+import csv
+import re
+import sys
+
+HEADER = """\
+# Embedded OUI registry, in the format of Wireshark's manuf file.
+#
+# Each line holds a MAC address prefix, a short vendor name and optionally the full name, separated by tabs.
+# Prefixes are 24 bits (MA-L) unless followed by a mask, e.g. /28 for MA-M and /36 for MA-S assignments.
+# The 24 bit blocks the IEEE Registration Authority subdivides are listed as IEEERegi, and the longer MA-M
+# and MA-S prefixes within them take precedence.
+#
+# Generated by generate.py from the IEEE registries, run it again to update the registry.
+"""
+
+# Words that don't tell vendors apart, left out of the short names.
+GENERAL_TERMS = {
+    "a", "ab", "ag", "and", "as", "bv", "co", "company", "corp", "corporation", "gmbh", "inc", "incorporated",
+    "kg", "limited", "llc", "ltd", "of", "oy", "plc", "pte", "pty", "sa", "sas", "spa", "srl", "systems", "the",
+}
+
+
+def shorten(name):
+    """Abbreviate an organization name to at most 8 characters, e.g. `Intel Corporation` to `Intel`."""
+    words = [word for word in re.split(r"[^0-9A-Za-z-]+", name) if word]
+    significant = [word for word in words if word.lower() not in GENERAL_TERMS] or words
+    return "".join(significant)[:8]
+
+
+def prefix(assignment):
+    digits = assignment.upper()
+    padded = digits.ljust(12, "0")
+    octets = ":".join(padded[index:index + 2] for index in range(0, 12, 2))
+    if len(digits) == 6:
+        return octets[:8]
+    return "{}/{}".format(octets, len(digits) * 4)
+
+
+def main(paths):
+    rows = {}
+    for path in paths:
+        with open(path, newline="", encoding="utf-8") as file:
+            for row in csv.DictReader(file):
+                assignment = row["Assignment"].strip()
+                name = " ".join(row["Organization Name"].split())
+                if not re.fullmatch(r"[0-9A-Fa-f]{6,9}", assignment) or not name:
+                    continue
+                short_name = "IEEERegi" if name == "IEEE Registration Authority" else shorten(name)
+                rows[(assignment.upper().ljust(12, "0"), len(assignment))] = (prefix(assignment), short_name, name)
+
+    sys.stdout.write(HEADER)
+    for key in sorted(rows):
+        sys.stdout.write("\t".join(rows[key]) + "\n")
+
+
+if __name__ == "__main__":
+    if len(sys.argv) < 2:
+        sys.exit(__doc__)
+    main(sys.argv[1:])
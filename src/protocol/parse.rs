@@ -1,12 +1,51 @@
+use aipn::AIPN;
 use linktype::LinkType;
 use crate::pcap::global_header::GlobalHeader;
-use super::{ethernet::{self, EtherType}, ipv4, Protocol};
+use super::{address::IpAddress, ah, arp, att, bgp, can, cdp, esp, ethernet::{self, EtherType, FcsMode}, fragment, geneve, gre, hci, icmp, icmpv6, igmp, ipv4, ipv6, l2cap, llc, mpls, nflog, options::Options, ospf, ppp, pppoe, rip, stp, tcp, udp, usb, vxlan, Protocol};
 
 
+#[derive(Default)]
+/**
+ ### State carried along while dissecting a packet
+ * BGP Reassembler: The stream reassembler that BGP segments are fed to, when `bgp_reassembly` is enabled.
+ * Addresses: The source and destination of the innermost IP header so far, which identify the connection of a TCP segment.
+ * Depth: How many tunnels and quoted datagrams the current layer is nested in.
+ * Quoted: Whether the current layer belongs to the datagram quoted by an ICMP error, whose transport header may be cut short.
+ */
+pub(crate) struct Dissection<'a> {
+    bgp_reassembler: Option<&'a mut bgp::Reassembler>,
+    addresses: Option<(IpAddress, IpAddress)>,
+    depth: usize,
+    quoted: bool,
+}
 
-fn parse_ethernet(data:&[u8]) -> Vec<Protocol> {
+impl Dissection<'_> {
+    /**
+     * Dissect the packet carried by a tunnel one level deeper, unless `max_nesting_depth` levels are open already.
+     */
+    fn decapsulate(&mut self, options:&Options, dissect:impl FnOnce(&mut Self)) {
+        if self.depth >= options.max_nesting_depth {
+            log::warn!("Not decapsulating packets nested deeper than {} levels", options.max_nesting_depth);
+            return;
+        }
+        self.depth += 1;
+        dissect(self);
+        self.depth -= 1;
+    }
+
+    /**
+     * Dissect the datagram quoted by an ICMP error one level deeper.
+     */
+    fn quote(&mut self, options:&Options, dissect:impl FnOnce(&mut Self)) {
+        let quoted = std::mem::replace(&mut self.quoted, true);
+        self.decapsulate(options, dissect);
+        self.quoted = quoted;
+    }
+}
+
+fn parse_ethernet(data:&[u8], fcs_mode:FcsMode, options:&Options, dissection:&mut Dissection) -> Vec<Protocol> {
     // Sequentially parse the Ethernet header and the next protocol, and return the current list of protocols upon error.
-    let ethernet_header = match ethernet::parse(data) {
+    let ethernet_header = match ethernet::parse(data, fcs_mode) {
         Ok(header) => header,
         Err(e) => {
             log::error!("Failed to parse Ethernet header: {}", e);
@@ -14,42 +53,876 @@ fn parse_ethernet(data:&[u8]) -> Vec<Protocol> {
         }
     };
 
+    let ether_type = ethernet_header.ether_type;
+    let payload_data = ethernet_header.payload(data);
     let mut protocols = vec![Protocol::Ethernet(ethernet_header)];
 
     // Parse the next protocol based on the EtherType.
-    match ethernet_header.ether_type {
-        EtherType::IPv4 => {
-            let ip_header=match ipv4::parse(&data[ethernet::Header::size()..]) {
-                Ok(header) => header,
-                Err(e) => {
-                    log::error!("Failed to parse IPv4 header: {}", e);
-                    return protocols;
-                }
-            };
-            protocols.push(Protocol::IPv4(ip_header));
+    parse_ether_type(ether_type, payload_data, &mut protocols, options, dissection);
+
+    protocols
+
+
+}
+
+/**
+ * Parse the protocol identified by an EtherType and append it, and everything it carries, to the list of protocols.
+ */
+fn parse_ether_type(ether_type:EtherType, data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    match ether_type {
+        EtherType::IPv4 => parse_ipv4(data, protocols, options, dissection),
+        EtherType::MplsUnicast | EtherType::MplsMulticast => parse_mpls(data, protocols, options, dissection),
+        EtherType::PppoeDiscovery | EtherType::PppoeSession => parse_pppoe(data, protocols, options, dissection),
+        EtherType::Length(length) => {
+            // The length excludes any padding added to reach the minimum frame size.
+            let length = (length as usize).min(data.len());
+            parse_llc(&data[..length], protocols, options, dissection);
+        }
+        EtherType::IPv6 => parse_ipv6(data, protocols, options, dissection),
+        EtherType::Arp | EtherType::Rarp => {
+            match arp::parse(data) {
+                Ok(header) => protocols.push(Protocol::Arp(header)),
+                Err(e) => log::error!("Failed to parse ARP header: {}", e),
+            }
+        }
+        _ => {
+            log::warn!("Unsupported EtherType: {:?}", ether_type);
+        }
+    }
+}
+
+fn parse_ipv4(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let mut ip_header=match ipv4::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse IPv4 header: {}", e);
+            return;
+        }
+    };
+    if options.verify_ipv4_checksum {
+        ip_header.checksum_status = ip_header.verify_checksum(data);
+    }
+    let payload_data = ip_header.payload(data);
+    dissection.addresses = Some((IpAddress::from(ip_header.source), IpAddress::from(ip_header.destination)));
+    if payload_data.len() < ip_header.payload_length() {
+        log::debug!("IPv4 payload truncated to {} of {} bytes", payload_data.len(), ip_header.payload_length());
+    }
+
+    // Only the first fragment starts with the header of the next protocol.
+    let mut upper_protocols = vec![];
+    if ip_header.fragment_offset == 0 {
+        parse_ip_protocol(&ip_header.protocol, payload_data, &mut upper_protocols, options, dissection);
+    }
+    // Fragments are only collected for the reassembler, when there is one.
+    let fragment = if options.ip_reassembly.is_some() { ipv4_fragment(data, &ip_header) } else { None };
+    protocols.push(Protocol::IPv4(ip_header));
+    protocols.extend(fragment.map(Protocol::Fragment));
+    protocols.extend(upper_protocols);
+}
+
+/**
+ * Collect the fragment carried by an IPv4 packet for reassembly, unless the packet is whole or was truncated by the capture.
+ */
+fn ipv4_fragment(data:&[u8], ip_header:&ipv4::Header) -> Option<fragment::Fragment> {
+    let more_fragments = ip_header.flags.contains(&ipv4::Flag::MoreFragments);
+    let payload_data = ip_header.payload(data);
+    if (ip_header.fragment_offset == 0 && !more_fragments) || payload_data.len() < ip_header.payload_length() {
+        return None;
+    }
+    Some(fragment::Fragment {
+        key: fragment::Key {
+            source: ip_header.source.address.to_vec(),
+            destination: ip_header.destination.address.to_vec(),
+            protocol: data[9],
+            identification: ip_header.identification as u32,
+        },
+        offset: ip_header.fragment_offset as usize * 8,
+        more_fragments,
+        header: data[..ip_header.size()].to_vec(),
+        data: payload_data.to_vec(),
+    })
+}
+
+/**
+ * Parse the protocol carried by IPv4 or IPv6 and append it, and everything it carries, to the list of protocols.
+ */
+fn parse_ip_protocol(protocol:&AIPN, data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    match protocol {
+        AIPN::ICMP => parse_icmp(data, protocols, options, dissection),
+        AIPN::Ipv6Icmp => parse_icmpv6(data, protocols, options, dissection),
+        AIPN::IGMP => match igmp::parse(data) {
+            Ok(header) => protocols.push(Protocol::Igmp(header)),
+            Err(e) => log::error!("Failed to parse IGMP message: {}", e),
+        },
+        AIPN::IPv4 | AIPN::IPIP => dissection.decapsulate(options, |dissection| parse_ipv4(data, protocols, options, dissection)),
+        AIPN::IPv6 => dissection.decapsulate(options, |dissection| parse_ipv6(data, protocols, options, dissection)),
+        AIPN::GRE => parse_gre(data, protocols, options, dissection),
+        AIPN::AH => parse_ah(data, protocols, options, dissection),
+        AIPN::ESP => parse_esp(data, protocols, options, dissection),
+        AIPN::TCP => parse_tcp(data, protocols, dissection),
+        AIPN::UDP => parse_udp(data, protocols, options, dissection),
+        AIPN::OSPFIGP => match ospf::parse(data) {
+            Ok(header) => protocols.push(Protocol::Ospf(header)),
+            Err(e) => log::error!("Failed to parse OSPF packet: {}", e),
+        },
+        _ => {
+            log::warn!("Unsupported IP protocol: {:?}", protocol);
+        }
+    }
+}
+
+fn parse_gre(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let gre_header=match gre::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse GRE header: {}", e);
+            return;
+        }
+    };
+    let protocol_type = gre_header.protocol_type;
+    let payload_data = &data[gre_header.size().min(data.len())..];
+    protocols.push(Protocol::Gre(gre_header));
+
+    // Decapsulate the tunneled packet, so that the inner layers follow the outer ones.
+    dissection.decapsulate(options, |dissection| match protocol_type {
+        gre::ProtocolType::IPv4 => parse_ipv4(payload_data, protocols, options, dissection),
+        gre::ProtocolType::IPv6 => parse_ipv6(payload_data, protocols, options, dissection),
+        gre::ProtocolType::TransparentEthernetBridging
+        | gre::ProtocolType::ErspanTypeII
+        | gre::ProtocolType::ErspanTypeIII => protocols.extend(parse_ethernet(payload_data, FcsMode::Absent, options, dissection)),
+        gre::ProtocolType::Ppp => parse_ppp(payload_data, protocols, options, dissection),
+        gre::ProtocolType::Mpls => parse_mpls(payload_data, protocols, options, dissection),
+        gre::ProtocolType::Unsupported(protocol_type) => {
+            log::warn!("Unsupported GRE protocol type: {:#06x}", protocol_type);
+        }
+    });
+}
+
+fn parse_ah(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let ah_header=match ah::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse authentication header: {}", e);
+            return;
+        }
+    };
+    let next_header = AIPN::from(data[0]);
+    let payload_data = &data[ah_header.size()..];
+    protocols.push(Protocol::Ah(ah_header));
+
+    // The authentication header only signs the packet, so the protected protocol follows in the clear.
+    parse_ip_protocol(&next_header, payload_data, protocols, options, dissection);
+}
+
+fn parse_esp(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let esp_header=match esp::parse(data, &options.esp_security_associations) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse ESP header: {}", e);
+            return;
+        }
+    };
+
+    // Only a decrypted payload can be dissected, in tunnel mode it is a whole IP packet.
+    let mut inner_protocols = vec![];
+    if let Some(decrypted) = &esp_header.decrypted {
+        parse_ip_protocol(&decrypted.next_header, &decrypted.payload, &mut inner_protocols, options, dissection);
+    }
+    protocols.push(Protocol::Esp(esp_header));
+    protocols.extend(inner_protocols);
+}
+
+fn parse_tcp(data:&[u8], protocols:&mut Vec<Protocol>, dissection:&mut Dissection) {
+    // ICMP errors quote only the first 8 bytes of the transport header.
+    if dissection.quoted && data.len() < 20 {
+        match tcp::parse_truncated(data) {
+            Ok(header) => protocols.push(Protocol::Tcp(header)),
+            Err(e) => log::error!("Failed to parse TCP header: {}", e),
+        }
+        return;
+    }
+    let tcp_header=match tcp::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse TCP header: {}", e);
+            return;
+        }
+    };
+    let bgp = tcp_header.source_port == 179 || tcp_header.destination_port == 179;
+    let payload_data = tcp_header.payload(data);
+
+    // With reassembly, the segment continues the stream of its connection, and completes the messages that follow the TCP header.
+    // Quoted segments were already seen when they were sent.
+    if let (true, false, Some(reassembler), Some((source, destination))) = (bgp, dissection.quoted, dissection.bgp_reassembler.as_deref_mut(), dissection.addresses) {
+        let messages = reassembler.push_segment(source, destination, &tcp_header, payload_data);
+        protocols.push(Protocol::Tcp(tcp_header));
+        protocols.extend(messages.into_iter().map(Protocol::Bgp));
+        return;
+    }
+    protocols.push(Protocol::Tcp(tcp_header));
+
+    if !bgp || payload_data.is_empty() {
+        return;
+    }
+    let mut offset = 0;
+    while offset < payload_data.len() {
+        match bgp::parse(&payload_data[offset..]) {
+            Ok(header) => {
+                offset += header.length as usize;
+                protocols.push(Protocol::Bgp(header));
+            }
+            Err(e) => {
+                log::warn!("Failed to parse BGP message, it may span segments: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn parse_udp(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let udp_header=match udp::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse UDP header: {}", e);
+            return;
+        }
+    };
+    if udp_header.length_status != udp::Length::Valid {
+        log::debug!("UDP length {} does not match the IP payload: {:?}", udp_header.length, udp_header.length_status);
+    }
+    let source_port = udp_header.source_port;
+    let destination_port = udp_header.destination_port;
+    let payload_data = udp_header.payload(data);
+    protocols.push(Protocol::Udp(udp_header));
+
+    let dissector = [destination_port, source_port]
+        .iter()
+        .find_map(|port| options.udp_dissectors.iter().find(|(dissector_port, _)| dissector_port == port));
+    if let Some((_, dissector)) = dissector {
+        dissector(payload_data, protocols, options);
+    } else if options.vxlan_ports.contains(&destination_port) {
+        parse_vxlan(payload_data, protocols, options, dissection);
+    } else if options.geneve_ports.contains(&destination_port) {
+        parse_geneve(payload_data, protocols, options, dissection);
+    } else if source_port == 520 || destination_port == 520 {
+        match rip::parse(payload_data) {
+            Ok(header) => protocols.push(Protocol::Rip(header)),
+            Err(e) => log::error!("Failed to parse RIP message: {}", e),
+        }
+    }
+}
+
+fn parse_vxlan(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let vxlan_header=match vxlan::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse VXLAN header: {}", e);
+            return;
+        }
+    };
+    protocols.push(Protocol::Vxlan(vxlan_header));
+    dissection.decapsulate(options, |dissection| protocols.extend(parse_ethernet(&data[vxlan::Header::size()..], FcsMode::Absent, options, dissection)));
+}
+
+fn parse_geneve(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let geneve_header=match geneve::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse Geneve header: {}", e);
+            return;
+        }
+    };
+    let protocol_type = geneve_header.protocol_type;
+    let payload_data = &data[geneve_header.size()..];
+    protocols.push(Protocol::Geneve(geneve_header));
+
+    // Transparent Ethernet bridging carries a whole frame, anything else is an EtherType.
+    dissection.decapsulate(options, |dissection| match protocol_type {
+        0x6558 => protocols.extend(parse_ethernet(payload_data, FcsMode::Absent, options, dissection)),
+        ether_type => parse_ether_type(EtherType::from_u16(ether_type), payload_data, protocols, options, dissection),
+    });
+}
+
+fn parse_icmp(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let mut icmp_header=match icmp::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse ICMP header: {}", e);
+            return;
+        }
+    };
+    // Error messages quote the offending datagram, which is dissected on its own.
+    if icmp_header.message.is_error() {
+        dissection.quote(options, |dissection| parse_ipv4(&data[icmp::Header::size()..], &mut icmp_header.quoted, options, dissection));
+    }
+    protocols.push(Protocol::Icmp(icmp_header));
+}
+
+fn parse_icmpv6(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let mut icmp_header=match icmpv6::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse ICMPv6 header: {}", e);
+            return;
+        }
+    };
+    // Error messages quote the offending packet, which is dissected on its own.
+    if icmp_header.message.is_error() {
+        dissection.quote(options, |dissection| parse_ipv6(&data[icmpv6::Header::size()..], &mut icmp_header.quoted, options, dissection));
+    }
+    protocols.push(Protocol::Icmpv6(icmp_header));
+}
+
+fn parse_ipv6(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let ip_header=match ipv6::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse IPv6 header: {}", e);
+            return;
+        }
+    };
+    let payload_data = ip_header.payload(data);
+    dissection.addresses = Some((IpAddress::from(ip_header.source), IpAddress::from(ip_header.destination)));
+
+    // The payload of a non-first fragment does not start with the upper-layer header.
+    let mut upper_protocols = vec![];
+    if !matches!(ip_header.fragment(), Some((fragment_offset, _, _)) if fragment_offset != 0) {
+        parse_ip_protocol(&ip_header.protocol, payload_data, &mut upper_protocols, options, dissection);
+    }
+    let fragment = if options.ip_reassembly.is_some() { ipv6_fragment(data, &ip_header) } else { None };
+    protocols.push(Protocol::IPv6(ip_header));
+    protocols.extend(fragment.map(Protocol::Fragment));
+    protocols.extend(upper_protocols);
+}
+
+/**
+ * Collect the fragment carried by an IPv6 packet for reassembly, unless the packet is whole or was truncated by the capture.
+ * Atomic fragments, with offset 0 and no more fragments, are whole packets (RFC 6946).
+ */
+fn ipv6_fragment(data:&[u8], ip_header:&ipv6::Header) -> Option<fragment::Fragment> {
+    let (fragment_offset, more_fragments, identification) = ip_header.fragment()?;
+    if fragment_offset == 0 && !more_fragments {
+        return None;
+    }
+    let (next_header_offset, fragment_header_offset) = ipv6::locate_fragment_header(data)?;
+    let end = 40 + ip_header.payload_length as usize;
+    let fragment_data = data.get(fragment_header_offset + 8..end)?;
+
+    // Remove the fragment header from the chain, so that the header is followed by the fragmented protocol.
+    let protocol = data[fragment_header_offset];
+    let mut header = data[..fragment_header_offset].to_vec();
+    header[next_header_offset] = protocol;
+
+    Some(fragment::Fragment {
+        key: fragment::Key {
+            source: ip_header.source.address.to_vec(),
+            destination: ip_header.destination.address.to_vec(),
+            protocol,
+            identification,
+        },
+        offset: fragment_offset as usize * 8,
+        more_fragments,
+        header,
+        data: fragment_data.to_vec(),
+    })
+}
+
+/**
+ * Dissect a whole IPv4 or IPv6 datagram, like one put back together from its fragments.
+ */
+pub(crate) fn parse_datagram(data:&[u8], options:&Options) -> Vec<Protocol> {
+    let mut protocols = vec![];
+    let dissection = &mut Dissection::default();
+    match data.first().map(|byte| byte >> 4) {
+        Some(4) => parse_ipv4(data, &mut protocols, options, dissection),
+        Some(6) => parse_ipv6(data, &mut protocols, options, dissection),
+        _ => log::warn!("Datagram is neither IPv4 nor IPv6"),
+    }
+    protocols
+}
+
+fn parse_mpls(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let mpls_header=match mpls::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse MPLS header: {}", e);
+            return;
         }
-        EtherType::IPv6 => {
-            log::warn!("Unsupported EtherType: {:?}", ethernet_header.ether_type);
+    };
+    let payload = mpls_header.payload;
+    let payload_data = &data[mpls_header.size()..];
+    protocols.push(Protocol::Mpls(mpls_header));
+
+    // Continue into whatever the label stack was guessed to carry.
+    match payload {
+        mpls::Payload::IPv4 => parse_ether_type(EtherType::IPv4, payload_data, protocols, options, dissection),
+        mpls::Payload::IPv6 => parse_ether_type(EtherType::IPv6, payload_data, protocols, options, dissection),
+        // Pseudowires carry the Ethernet frame without its FCS.
+        mpls::Payload::Ethernet { .. } => {
+            dissection.decapsulate(options, |dissection| protocols.extend(parse_ethernet(payload_data, FcsMode::Absent, options, dissection)));
+        }
+        mpls::Payload::Empty => {}
+    }
+}
+
+fn parse_llc(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let llc_header=match llc::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse LLC header: {}", e);
+            return;
         }
+    };
+    let payload_data = &data[llc_header.size()..];
+    protocols.push(Protocol::Llc(llc_header));
+
+    // SNAP identifies the payload by OUI and protocol ID, plain LLC by the destination SAP.
+    match (llc_header.snap, llc_header.dsap) {
+        (Some(snap), _) if snap.oui == [0x00, 0x00, 0x00] => {
+            parse_ether_type(EtherType::from_u16(snap.protocol_id), payload_data, protocols, options, dissection);
+        }
+        (Some(snap), _) if snap.oui == [0x00, 0x00, 0x0C] && snap.protocol_id == 0x2000 => {
+            match cdp::parse(payload_data) {
+                Ok(header) => protocols.push(Protocol::Cdp(header)),
+                Err(e) => log::error!("Failed to parse CDP packet: {}", e),
+            }
+        }
+        (None, 0x42) => {
+            match stp::parse(payload_data) {
+                Ok(header) => protocols.push(Protocol::Stp(header)),
+                Err(e) => log::error!("Failed to parse BPDU: {}", e),
+            }
+        }
+        (None, 0x06) => parse_ipv4(payload_data, protocols, options, dissection),
         _ => {
-            log::warn!("Unsupported EtherType: {:?}", ethernet_header.ether_type);
+            log::warn!("Unsupported LLC payload: {:?}", llc_header);
+        }
+    }
+}
+
+fn parse_pppoe(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let pppoe_header=match pppoe::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse PPPoE header: {}", e);
+            return;
+        }
+    };
+    let code = pppoe_header.code;
+    let payload_data = &data[pppoe::Header::size()..pppoe::Header::size() + pppoe_header.length as usize];
+    protocols.push(Protocol::Pppoe(pppoe_header));
+
+    // Only session data carries a PPP frame, discovery packets end with their tags.
+    if code == pppoe::Code::SessionData {
+        parse_ppp(payload_data, protocols, options, dissection);
+    }
+}
+
+fn parse_ppp(data:&[u8], protocols:&mut Vec<Protocol>, options:&Options, dissection:&mut Dissection) {
+    let ppp_header=match ppp::parse(data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse PPP header: {}", e);
+            return;
+        }
+    };
+    let protocol = ppp_header.protocol;
+    let payload_data = &data[ppp_header.size()..];
+    protocols.push(Protocol::Ppp(ppp_header));
+
+    match protocol {
+        ppp::ProtocolType::IPv4 => parse_ether_type(EtherType::IPv4, payload_data, protocols, options, dissection),
+        ppp::ProtocolType::IPv6 => parse_ether_type(EtherType::IPv6, payload_data, protocols, options, dissection),
+        ppp::ProtocolType::MplsUnicast => parse_ether_type(EtherType::MplsUnicast, payload_data, protocols, options, dissection),
+        ppp::ProtocolType::Unsupported(_) => {
+            log::warn!("Unsupported PPP protocol: {:?}", protocol);
+        }
+        _ => {}
+    }
+}
+
+fn parse_bluetooth_hci(data:&[u8], with_pseudo_header:bool) -> Vec<Protocol> {
+    let hci_header = match hci::parse(data, with_pseudo_header) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse HCI packet: {}", e);
+            return vec![];
+        }
+    };
+    let payload_data = &data[hci_header.size()..];
+    // Only the first fragment of an ACL packet starts with an L2CAP header.
+    let l2cap_start = matches!(&hci_header.packet, hci::Packet::AclData(acl) if acl.is_start());
+
+    let mut protocols = vec![Protocol::Hci(hci_header)];
+    if !l2cap_start {
+        return protocols;
+    }
+
+    let l2cap_header = match l2cap::parse(payload_data) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse L2CAP header: {}", e);
+            return protocols;
+        }
+    };
+    protocols.push(Protocol::L2cap(l2cap_header));
+
+    if l2cap_header.channel == l2cap::Channel::Att {
+        match att::parse(&payload_data[l2cap::Header::size()..]) {
+            Ok(header) => protocols.push(Protocol::Att(header)),
+            Err(e) => log::error!("Failed to parse ATT PDU: {}", e),
         }
     }
 
     protocols
+}
+
+fn parse_nflog(data:&[u8], global_header:&GlobalHeader, options:&Options, dissection:&mut Dissection) -> Vec<Protocol> {
+    let nflog_header = match nflog::parse(data, &global_header.byte_order) {
+        Ok(header) => header,
+        Err(e) => {
+            log::error!("Failed to parse NFLOG header: {}", e);
+            return vec![];
+        }
+    };
+    let family = nflog_header.family;
+    let payload = nflog_header.payload.clone();
+    let mut protocols = vec![Protocol::Nflog(nflog_header)];
 
+    // The address family tells which IP version the logged packet is.
+    if let Some(payload) = payload {
+        match family {
+            2 => parse_ether_type(EtherType::IPv4, &data[payload], &mut protocols, options, dissection),
+            10 => parse_ether_type(EtherType::IPv6, &data[payload], &mut protocols, options, dissection),
+            _ => log::warn!("Unsupported NFLOG address family: {}", family),
+        }
+    }
 
+    protocols
 }
 
-pub fn parse(data:&[u8],global_header:&GlobalHeader)-> Vec<Protocol> {
+pub fn parse(data:&[u8],global_header:&GlobalHeader,options:&Options)-> Vec<Protocol> {
+    parse_with(data, global_header, options, None)
+}
 
+/**
+ * Dissect a packet like `parse`, feeding BGP segments to the stream reassembler of the capture.
+ */
+pub(crate) fn parse_with(data:&[u8], global_header:&GlobalHeader, options:&Options, bgp_reassembler:Option<&mut bgp::Reassembler>) -> Vec<Protocol> {
+    let dissection = &mut Dissection { bgp_reassembler, ..Dissection::default() };
     match global_header.network {
         LinkType::Ethernet => {
-            parse_ethernet(data)
+            parse_ethernet(data, options.fcs, options, dissection)
+        },
+        LinkType::Ppp => {
+            let mut protocols = vec![];
+            parse_ppp(data, &mut protocols, options, dissection);
+            protocols
+        },
+        LinkType::BluetoothHciH4 => {
+            parse_bluetooth_hci(data, false)
+        },
+        LinkType::BluetoothHciH4WithPhdr => {
+            parse_bluetooth_hci(data, true)
+        },
+        LinkType::Nflog => {
+            parse_nflog(data, global_header, options, dissection)
+        },
+        LinkType::CanSocketcan => {
+            match can::parse(data) {
+                Ok(header) => vec![Protocol::Can(header)],
+                Err(e) => {
+                    log::error!("Failed to parse CAN frame: {}", e);
+                    vec![]
+                }
+            }
+        },
+        LinkType::UsbLinux | LinkType::UsbLinuxMmapped => {
+            let mmapped = matches!(global_header.network, LinkType::UsbLinuxMmapped);
+            match usb::parse(data, &global_header.byte_order, mmapped) {
+                Ok(header) => vec![Protocol::Usb(header)],
+                Err(e) => {
+                    log::error!("Failed to parse usbmon header: {}", e);
+                    vec![]
+                }
+            }
         },
         _ => {
             log::error!("Network type not supported");
             unimplemented!("Network type not supported");
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dissects_quoted_datagram_of_icmp_errors() {
+        let mut data = vec![0x45, 0x00, 0x00, 0x38, 0x00, 0x01, 0x00, 0x00, 0x40, 0x01, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2];
+        // Port unreachable, quoting a UDP datagram from 10.0.0.2 to 10.0.0.1.
+        data.extend_from_slice(&[0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0x45, 0x00, 0x00, 0x30, 0x00, 0x02, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 10, 0, 0, 2, 10, 0, 0, 1]);
+        data.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0x00, 0x1C, 0x00, 0x00]);
+
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(protocols[0], Protocol::IPv4(_)));
+        match &protocols[1] {
+            Protocol::Icmp(icmp) => {
+                assert!(matches!(&icmp.quoted[0], Protocol::IPv4(quoted) if quoted.source.address == [10, 0, 0, 2]));
+                assert!(matches!(&icmp.quoted[1], Protocol::Udp(quoted) if quoted.source_port == 12345 && quoted.destination_port == 53));
+            }
+            other => panic!("Unexpected protocol: {:?}", other),
+        }
+
+        // Time exceeded, quoting the first 8 bytes of a TCP segment.
+        data[20] = 0x0B;
+        data[21] = 0x00;
+        data[37] = 0x06;
+        data[48..56].copy_from_slice(&[0xC3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xE8]);
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        match &protocols[1] {
+            Protocol::Icmp(icmp) => match &icmp.quoted[1] {
+                Protocol::Tcp(quoted) => {
+                    assert_eq!((quoted.source_port, quoted.destination_port, quoted.sequence_number), (50000, 80, 1000));
+                    assert!(quoted.truncated);
+                }
+                other => panic!("Unexpected quoted protocol: {:?}", other),
+            },
+            other => panic!("Unexpected protocol: {:?}", other),
+        }
+
+        // Outside of a quote, a TCP header is never cut short.
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::TCP, &data[48..56], &mut protocols, &Options::default(), &mut Dissection::default());
+        assert!(protocols.is_empty());
+    }
+
+    #[test]
+    fn dispatches_icmpv6_from_ipv6() {
+        let mut data = vec![0x60, 0x00, 0x00, 0x00, 0x00, 0x18, 58, 255];
+        data.extend_from_slice(&[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0xFF, 0, 0, 2]);
+        // Neighbor solicitation for fe80::2 without options.
+        data.extend_from_slice(&[135, 0, 0x00, 0x00, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+        let mut protocols = vec![];
+        parse_ipv6(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(protocols[0], Protocol::IPv6(_)));
+        match &protocols[1] {
+            Protocol::Icmpv6(icmp) => {
+                assert!(matches!(&icmp.message, icmpv6::Message::NeighborSolicitation { target, options } if target.address[15] == 2 && options.is_empty()));
+            }
+            other => panic!("Unexpected protocol: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reassembles_ipv6_fragments() {
+        let fragment = |offset_flags: u16, payload: &[u8]| {
+            let mut data = vec![0x60, 0x00, 0x00, 0x00];
+            data.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+            data.extend_from_slice(&[44, 64]);
+            data.extend_from_slice(&[0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+            data.extend_from_slice(&[0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+            data.extend_from_slice(&[58, 0]);
+            data.extend_from_slice(&offset_flags.to_be_bytes());
+            data.extend_from_slice(&[0x00, 0x00, 0x00, 0x07]);
+            data.extend_from_slice(payload);
+            data
+        };
+        // Without reassembly, the fragment isn't collected.
+        let mut first = vec![];
+        parse_ipv6(&fragment(0x0001, &[128, 0, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 1, 2, 3, 4, 5, 6, 7, 8]), &mut first, &Options::default(), &mut Dissection::default());
+        assert!(!first.iter().any(|protocol| matches!(protocol, Protocol::Fragment(_))));
+
+        let options = Options { ip_reassembly: Some(fragment::Settings::default()), ..Options::default() };
+        let mut reassembler = fragment::Reassembler::new(fragment::Settings::default());
+
+        // Echo request with 12 bytes of data, split after the first 16 bytes.
+        let mut first = vec![];
+        parse_ipv6(&fragment(0x0001, &[128, 0, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 1, 2, 3, 4, 5, 6, 7, 8]), &mut first, &options, &mut Dissection::default());
+        reassembler.process(&mut first, 0, &options);
+        assert!(matches!(first[1], Protocol::Fragment(_)));
+        assert!(!first.iter().any(|protocol| matches!(protocol, Protocol::Reassembled(_))));
+
+        let mut last = vec![];
+        parse_ipv6(&fragment(0x0010, &[9, 10, 11, 12]), &mut last, &options, &mut Dissection::default());
+        reassembler.process(&mut last, 0, &options);
+
+        assert!(matches!(&last[2], Protocol::Reassembled(datagram) if datagram.fragments == 2 && datagram.data.len() == 60));
+        assert!(matches!(&last[3], Protocol::IPv6(header) if header.extensions.is_empty() && header.payload_length == 20));
+        assert!(matches!(&last[4], Protocol::Icmpv6(icmp) if matches!(&icmp.message, icmpv6::Message::EchoRequest { data, .. } if data.len() == 12)));
+    }
+
+    #[test]
+    fn decapsulates_gre_and_ip_in_ip() {
+        let mut inner = vec![0x45, 0x00, 0x00, 0x1C, 0x00, 0x01, 0x00, 0x00, 0x40, 0x01, 0x00, 0x00, 192, 168, 0, 1, 192, 168, 0, 2];
+        inner.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01]);
+
+        let mut data = vec![0x45, 0x00, 0x00, 0x38, 0x00, 0x02, 0x00, 0x00, 0x40, 0x2F, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2];
+        data.extend_from_slice(&[0x20, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x2A]);
+        data.extend_from_slice(&inner);
+
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert_eq!(protocols.len(), 4);
+        assert!(matches!(&protocols[1], Protocol::Gre(gre) if gre.key == Some(42)));
+        assert!(matches!(&protocols[2], Protocol::IPv4(header) if header.source.address == [192, 168, 0, 1]));
+        assert!(matches!(&protocols[3], Protocol::Icmp(_)));
+
+        // The same datagram tunneled directly in IPv4.
+        let mut data = vec![0x45, 0x00, 0x00, 0x30, 0x00, 0x03, 0x00, 0x00, 0x40, 0x04, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2];
+        data.extend_from_slice(&inner);
+
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(&protocols[..], [Protocol::IPv4(_), Protocol::IPv4(_), Protocol::Icmp(_)]));
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        // Twenty IPv4 headers, each tunneling the next one.
+        let mut data = vec![];
+        for _ in 0..20 {
+            let total_length = (20 + data.len()) as u16;
+            let mut outer = vec![0x45, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x40, 0x04, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2];
+            outer[2..4].copy_from_slice(&total_length.to_be_bytes());
+            outer.extend_from_slice(&data);
+            data = outer;
+        }
+
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+        assert_eq!(protocols.len(), 9);
+
+        let options = Options { max_nesting_depth: 2, ..Options::default() };
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &options, &mut Dissection::default());
+        assert_eq!(protocols.len(), 3);
+    }
+
+    #[test]
+    fn decapsulates_vxlan_on_configured_ports() {
+        let mut inner = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06];
+        inner.extend_from_slice(&[0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01]);
+        inner.extend_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 10, 244, 0, 1, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 10, 244, 0, 2]);
+
+        let mut udp = vec![0xC0, 0x00, 0x12, 0xB5, 0x00, 0x00, 0x00, 0x00];
+        udp.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2A, 0x00]);
+        udp.extend_from_slice(&inner);
+
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::UDP, &udp, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(
+            &protocols[..],
+            [Protocol::Udp(_), Protocol::Vxlan(vxlan), Protocol::Ethernet(_), Protocol::Arp(_)] if vxlan.vni == 42
+        ));
+
+        let options = Options { vxlan_ports: vec![], ..Options::default() };
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::UDP, &udp, &mut protocols, &options, &mut Dissection::default());
+
+        assert!(matches!(&protocols[..], [Protocol::Udp(_)]));
+    }
+
+    #[test]
+    fn dispatches_routing_protocols() {
+        let ospf = [3, 5, 0x00, 0x10, 1, 1, 1, 1, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x00];
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::OSPFIGP, &ospf, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(&protocols[..], [Protocol::Ospf(ospf)] if ospf.version == 3 && ospf.instance_id == Some(0)));
+
+        let mut udp = vec![0x02, 0x08, 0x02, 0x08, 0x00, 0x20, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00];
+        udp.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x10]);
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::UDP, &udp, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(&protocols[..], [Protocol::Udp(_), Protocol::Rip(rip)] if rip.entries.len() == 1));
+    }
+
+    #[test]
+    fn dispatches_tcp_and_bgp() {
+        let keepalive = [[0xFF; 16].as_slice(), &[0x00, 0x13, 0x04]].concat();
+        let mut data = vec![0x45, 0x00, 0x00, 0x4E, 0x00, 0x04, 0x00, 0x00, 0x40, 0x06, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2];
+        data.extend_from_slice(&[0xC3, 0x50, 0x00, 0xB3, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x01, 0x50, 0x18, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&keepalive);
+        data.extend_from_slice(&keepalive);
+
+        let mut protocols = vec![];
+        parse_ipv4(&data, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(
+            &protocols[..],
+            [Protocol::IPv4(_), Protocol::Tcp(tcp), Protocol::Bgp(_), Protocol::Bgp(_)] if tcp.destination_port == 179
+        ));
+
+        // With reassembly, the second keepalive is split across two segments and completed by the second one.
+        let mut reassembler = bgp::Reassembler::new();
+        let mut segment = data[..69].to_vec();
+        segment[3] = 69;
+        let mut first = vec![];
+        parse_ipv4(&segment, &mut first, &Options::default(), &mut Dissection { bgp_reassembler: Some(&mut reassembler), ..Dissection::default() });
+        let mut second = data[..40].to_vec();
+        second[3] = 49;
+        second[26..28].copy_from_slice(&[0x04, 0x05]);
+        second.extend_from_slice(&keepalive[10..]);
+        let mut last = vec![];
+        parse_ipv4(&second, &mut last, &Options::default(), &mut Dissection { bgp_reassembler: Some(&mut reassembler), ..Dissection::default() });
+
+        assert!(matches!(&first[..], [Protocol::IPv4(_), Protocol::Tcp(_), Protocol::Bgp(_)]));
+        assert!(matches!(&last[..], [Protocol::IPv4(_), Protocol::Tcp(_), Protocol::Bgp(_)]));
+    }
+
+    #[test]
+    fn dispatches_udp_dissectors_by_port() {
+        fn dissect_as_rip(data:&[u8], protocols:&mut Vec<Protocol>, _options:&Options) {
+            if let Ok(header) = rip::parse(data) {
+                protocols.push(Protocol::Rip(header));
+            }
+        }
+
+        let mut udp = vec![0x9C, 0x40, 0x27, 0x10, 0x00, 0x0C, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00];
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::UDP, &udp, &mut protocols, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(&protocols[..], [Protocol::Udp(udp)] if udp.length_status == udp::Length::Valid));
+
+        let options = Options { udp_dissectors: vec![(40000, dissect_as_rip)], ..Options::default() };
+        udp.extend_from_slice(&[0xFF; 4]);
+        let mut protocols = vec![];
+        parse_ip_protocol(&AIPN::UDP, &udp, &mut protocols, &options, &mut Dissection::default());
+
+        assert!(matches!(
+            &protocols[..],
+            [Protocol::Udp(udp), Protocol::Rip(rip)] if udp.payload_length == 4 && rip.entries.is_empty()
+        ));
+    }
+
+    #[test]
+    fn dissects_ipv4_with_zero_total_length() {
+        let mut frame = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00];
+        frame.extend_from_slice(&[0x45, 0x00, 0x00, 0x00, 0x00, 0x05, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0x00, 0x0C, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04]);
+
+        let protocols = parse_ethernet(&frame, FcsMode::Absent, &Options::default(), &mut Dissection::default());
+
+        assert!(matches!(
+            &protocols[..],
+            [Protocol::Ethernet(ethernet), Protocol::IPv4(ip), Protocol::Udp(udp)]
+                if ethernet.trailer.is_empty() && ip.total_length_presumed && udp.length_status == udp::Length::Valid
+        ));
+    }
+}
@@ -0,0 +1,387 @@
+use std::io;
+
+use crate::read_bytes::read_u16_be;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### PPP protocol field
+ * `IPv4`, `IPv6` and `MplsUnicast`: Network layer payloads.
+ * `Lcp`: Link Control Protocol, negotiates the link itself.
+ * `Ipcp`: IP Control Protocol, negotiates addresses and DNS servers.
+ * `Pap`: Password Authentication Protocol.
+ * `Chap`: Challenge Handshake Authentication Protocol.
+ * `Unsupported`: Stores the actual value for unsupported or unrecognized protocols.
+ */
+pub enum ProtocolType {
+    IPv4,
+    IPv6,
+    MplsUnicast,
+    Lcp,
+    Ipcp,
+    Pap,
+    Chap,
+    Unsupported(u16),
+}
+
+impl ProtocolType {
+    pub fn from_u16(protocol: u16) -> ProtocolType {
+        match protocol {
+            0x0021 => ProtocolType::IPv4,
+            0x0057 => ProtocolType::IPv6,
+            0x0281 => ProtocolType::MplsUnicast,
+            0xC021 => ProtocolType::Lcp,
+            0x8021 => ProtocolType::Ipcp,
+            0xC023 => ProtocolType::Pap,
+            0xC223 => ProtocolType::Chap,
+            _ => ProtocolType::Unsupported(protocol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * LCP and IPCP packet codes as defined in RFC 1661.
+ */
+pub enum Code {
+    ConfigureRequest,
+    ConfigureAck,
+    ConfigureNak,
+    ConfigureReject,
+    TerminateRequest,
+    TerminateAck,
+    CodeReject,
+    ProtocolReject,
+    EchoRequest,
+    EchoReply,
+    DiscardRequest,
+    Unsupported(u8),
+}
+
+impl Code {
+    pub fn from_u8(code: u8) -> Code {
+        match code {
+            1 => Code::ConfigureRequest,
+            2 => Code::ConfigureAck,
+            3 => Code::ConfigureNak,
+            4 => Code::ConfigureReject,
+            5 => Code::TerminateRequest,
+            6 => Code::TerminateAck,
+            7 => Code::CodeReject,
+            8 => Code::ProtocolReject,
+            9 => Code::EchoRequest,
+            10 => Code::EchoReply,
+            11 => Code::DiscardRequest,
+            _ => Code::Unsupported(code),
+        }
+    }
+}
+
+#[derive(Debug)]
+/**
+ * A configuration option of a Configure-Request/Ack/Nak/Reject packet. The meaning of the option type depends on whether it is LCP (e.g. 1 = MRU, 3 = Authentication Protocol, 5 = Magic Number) or IPCP (e.g. 3 = IP Address, 129 = Primary DNS).
+ */
+pub struct ConfigurationOption {
+    pub option_type: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### LCP/IPCP packet structure
+ * Code (8 bits): The type of the packet.
+ * Identifier (8 bits): Used to match requests and replies.
+ * Length (16 bits): The length of the packet including the code, identifier and length fields.
+ * Options: The configuration options, only for the Configure-* codes.
+ * Data: The remaining data for all other codes, e.g. the magic number of an Echo-Request.
+ */
+pub struct ControlPacket {
+    pub code: Code,
+    pub identifier: u8,
+    pub length: u16,
+    pub options: Vec<ConfigurationOption>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ * PAP packets as defined in RFC 1334.
+ */
+pub enum Pap {
+    AuthenticateRequest { identifier: u8, peer_id: String, password: String },
+    AuthenticateAck { identifier: u8, message: String },
+    AuthenticateNak { identifier: u8, message: String },
+    Unsupported { code: u8, identifier: u8 },
+}
+
+#[derive(Debug)]
+/**
+ * CHAP packets as defined in RFC 1994.
+ */
+pub enum Chap {
+    Challenge { identifier: u8, value: Vec<u8>, name: String },
+    Response { identifier: u8, value: Vec<u8>, name: String },
+    Success { identifier: u8, message: String },
+    Failure { identifier: u8, message: String },
+    Unsupported { code: u8, identifier: u8 },
+}
+
+#[derive(Debug)]
+/**
+ * Decoded PPP control message.
+ */
+pub enum Message {
+    Lcp(ControlPacket),
+    Ipcp(ControlPacket),
+    Pap(Pap),
+    Chap(Chap),
+}
+
+#[derive(Debug)]
+/**
+ ### PPP header structure
+ * Address and Control (16 bits, optional): `0xFF 0x03` when HDLC-like framing is used.
+ * Protocol (8 or 16 bits): The protocol of the payload. A single byte when protocol field compression is negotiated.
+ * Message: The decoded control message when the protocol is LCP, IPCP, PAP or CHAP, unless it is malformed.
+ */
+pub struct Header {
+    pub hdlc_framing: bool,
+    pub protocol_compressed: bool,
+    pub protocol: ProtocolType,
+    pub message: Option<Message>,
+}
+
+impl Header {
+    /**
+     * Size of the PPP header, excluding the control message.
+     */
+    pub fn size(&self) -> usize {
+        let address_control = if self.hdlc_framing { 2 } else { 0 };
+        let protocol = if self.protocol_compressed { 1 } else { 2 };
+        address_control + protocol
+    }
+}
+
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Truncated {}", what))
+}
+
+/**
+ * Read a field prefixed by a one byte length, and return it along with the offset right after it.
+ */
+fn read_length_prefixed(data: &[u8], offset: usize) -> io::Result<(&[u8], usize)> {
+    let length = *data.get(offset).ok_or_else(|| truncated("length prefixed field"))? as usize;
+    match data.get(offset + 1..offset + 1 + length) {
+        Some(value) => Ok((value, offset + 1 + length)),
+        None => Err(truncated("length prefixed field")),
+    }
+}
+
+/**
+ * Split a control packet into code, identifier, and the data bounded by its length field.
+ */
+fn parse_control_header(data: &[u8]) -> io::Result<(u8, u8, u16, &[u8])> {
+    if data.len() < 4 {
+        return Err(truncated("PPP control packet"));
+    }
+    let length = read_u16_be(data, 2)?;
+    if length < 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid PPP control packet length"));
+    }
+    match data.get(4..length as usize) {
+        Some(body) => Ok((data[0], data[1], length, body)),
+        None => Err(truncated("PPP control packet")),
+    }
+}
+
+fn parse_control_packet(data: &[u8]) -> io::Result<ControlPacket> {
+    let (code, identifier, length, body) = parse_control_header(data)?;
+    let code = Code::from_u8(code);
+
+    let mut options = Vec::new();
+    let mut remaining = Vec::new();
+    match code {
+        Code::ConfigureRequest | Code::ConfigureAck | Code::ConfigureNak | Code::ConfigureReject => {
+            let mut offset = 0;
+            while offset < body.len() {
+                if offset + 2 > body.len() {
+                    return Err(truncated("PPP configuration option"));
+                }
+                let option_type = body[offset];
+                let option_length = body[offset + 1] as usize;
+                if option_length < 2 {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid PPP configuration option length"));
+                }
+                let option_data = match body.get(offset + 2..offset + option_length) {
+                    Some(option_data) => option_data.to_vec(),
+                    None => return Err(truncated("PPP configuration option")),
+                };
+                options.push(ConfigurationOption { option_type, data: option_data });
+                offset += option_length;
+            }
+        }
+        _ => remaining = body.to_vec(),
+    }
+
+    Ok(ControlPacket {
+        code,
+        identifier,
+        length,
+        options,
+        data: remaining,
+    })
+}
+
+fn parse_pap(data: &[u8]) -> io::Result<Pap> {
+    let (code, identifier, _, body) = parse_control_header(data)?;
+    let pap = match code {
+        1 => {
+            let (peer_id, offset) = read_length_prefixed(body, 0)?;
+            let (password, _) = read_length_prefixed(body, offset)?;
+            Pap::AuthenticateRequest {
+                identifier,
+                peer_id: String::from_utf8_lossy(peer_id).into_owned(),
+                password: String::from_utf8_lossy(password).into_owned(),
+            }
+        }
+        2 | 3 => {
+            let (message, _) = read_length_prefixed(body, 0)?;
+            let message = String::from_utf8_lossy(message).into_owned();
+            if code == 2 {
+                Pap::AuthenticateAck { identifier, message }
+            } else {
+                Pap::AuthenticateNak { identifier, message }
+            }
+        }
+        _ => Pap::Unsupported { code, identifier },
+    };
+    Ok(pap)
+}
+
+fn parse_chap(data: &[u8]) -> io::Result<Chap> {
+    let (code, identifier, _, body) = parse_control_header(data)?;
+    let chap = match code {
+        1 | 2 => {
+            let (value, offset) = read_length_prefixed(body, 0)?;
+            let value = value.to_vec();
+            let name = String::from_utf8_lossy(&body[offset..]).into_owned();
+            if code == 1 {
+                Chap::Challenge { identifier, value, name }
+            } else {
+                Chap::Response { identifier, value, name }
+            }
+        }
+        3 => Chap::Success { identifier, message: String::from_utf8_lossy(body).into_owned() },
+        4 => Chap::Failure { identifier, message: String::from_utf8_lossy(body).into_owned() },
+        _ => Chap::Unsupported { code, identifier },
+    };
+    Ok(chap)
+}
+
+/**
+ ### Parse the PPP header from the data
+
+ As per RFC 1661 and RFC 1662 the frame optionally starts with the `0xFF 0x03` address and control bytes, followed by the protocol field.
+ The protocol field is compressed to a single byte when its first byte is odd. LCP, IPCP, PAP and CHAP messages are decoded, any other payload is left to the caller.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    let hdlc_framing = data.len() >= 2 && data[0] == 0xFF && data[1] == 0x03;
+    let offset = if hdlc_framing { 2 } else { 0 };
+
+    let first = *data.get(offset).ok_or_else(|| truncated("PPP header"))?;
+    let (protocol, protocol_compressed) = if first & 0x01 == 1 {
+        (first as u16, true)
+    } else {
+        (read_u16_be(data, offset).map_err(|_| truncated("PPP header"))?, false)
+    };
+
+    let mut header = Header {
+        hdlc_framing,
+        protocol_compressed,
+        protocol: ProtocolType::from_u16(protocol),
+        message: None,
+    };
+
+    let payload = &data[header.size()..];
+    let message = match header.protocol {
+        ProtocolType::Lcp => parse_control_packet(payload).map(Message::Lcp),
+        ProtocolType::Ipcp => parse_control_packet(payload).map(Message::Ipcp),
+        ProtocolType::Pap => parse_pap(payload).map(Message::Pap),
+        ProtocolType::Chap => parse_chap(payload).map(Message::Chap),
+        _ => return Ok(header),
+    };
+    // A malformed control message doesn't make the PPP header invalid, it is just left undecoded.
+    header.message = match message {
+        Ok(message) => Some(message),
+        Err(e) => {
+            log::warn!("Failed to parse PPP {:?} message: {}", header.protocol, e);
+            None
+        }
+    };
+
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lcp_configure_request() {
+        // HDLC framing, LCP Configure-Request with MRU 1492 and a magic number.
+        let data = [
+            0xFF, 0x03, 0xC0, 0x21, 0x01, 0x01, 0x00, 0x0E, 0x01, 0x04, 0x05, 0xD4, 0x05, 0x06,
+            0x12, 0x34, 0x56, 0x78,
+        ];
+        let header = parse(&data).unwrap();
+
+        assert!(header.hdlc_framing);
+        assert_eq!(header.protocol, ProtocolType::Lcp);
+        assert_eq!(header.size(), 4);
+        match header.message {
+            Some(Message::Lcp(packet)) => {
+                assert_eq!(packet.code, Code::ConfigureRequest);
+                assert_eq!(packet.options.len(), 2);
+                assert_eq!(packet.options[0].option_type, 1);
+                assert_eq!(packet.options[0].data, vec![0x05, 0xD4]);
+                assert_eq!(packet.options[1].option_type, 5);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_header_of_malformed_control_message() {
+        // LCP Configure-Request whose length exceeds the data.
+        let data = [0xFF, 0x03, 0xC0, 0x21, 0x01, 0x01, 0x00, 0x0E, 0x01, 0x04];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.protocol, ProtocolType::Lcp);
+        assert!(header.message.is_none());
+    }
+
+    #[test]
+    fn parses_pap_request() {
+        let data = [
+            0xC0, 0x23, 0x01, 0x07, 0x00, 0x0B, 0x03, b'b', b'o', b'b', 0x02, b'p', b'w',
+        ];
+        let header = parse(&data).unwrap();
+
+        match header.message {
+            Some(Message::Pap(Pap::AuthenticateRequest { identifier, peer_id, password })) => {
+                assert_eq!(identifier, 7);
+                assert_eq!(peer_id, "bob");
+                assert_eq!(password, "pw");
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_compressed_protocol_field() {
+        let header = parse(&[0x21, 0x45, 0x00]).unwrap();
+
+        assert!(header.protocol_compressed);
+        assert_eq!(header.protocol, ProtocolType::IPv4);
+        assert_eq!(header.size(), 1);
+    }
+}
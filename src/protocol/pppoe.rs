@@ -0,0 +1,198 @@
+use std::io;
+
+use crate::read_bytes::read_u16_be;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### PPPoE code
+ * `SessionData`: Carries a PPP frame during the session stage.
+ * `Padi`: PPPoE Active Discovery Initiation, broadcast by the host.
+ * `Pado`: PPPoE Active Discovery Offer, sent by an access concentrator.
+ * `Padr`: PPPoE Active Discovery Request, the host picks an access concentrator.
+ * `Pads`: PPPoE Active Discovery Session-confirmation, assigns the session ID.
+ * `Padt`: PPPoE Active Discovery Terminate, ends the session.
+ */
+pub enum Code {
+    SessionData,
+    Padi,
+    Pado,
+    Padr,
+    Pads,
+    Padt,
+    Unsupported(u8),
+}
+
+impl Code {
+    pub fn from_u8(code: u8) -> Code {
+        match code {
+            0x00 => Code::SessionData,
+            0x09 => Code::Padi,
+            0x07 => Code::Pado,
+            0x19 => Code::Padr,
+            0x65 => Code::Pads,
+            0xA7 => Code::Padt,
+            _ => Code::Unsupported(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Discovery tag types as defined in RFC 2516 and RFC 4638.
+ */
+pub enum TagType {
+    EndOfList,
+    ServiceName,
+    AcName,
+    HostUniq,
+    AcCookie,
+    VendorSpecific,
+    RelaySessionId,
+    PppMaxPayload,
+    ServiceNameError,
+    AcSystemError,
+    GenericError,
+    Unsupported(u16),
+}
+
+impl TagType {
+    pub fn from_u16(tag_type: u16) -> TagType {
+        match tag_type {
+            0x0000 => TagType::EndOfList,
+            0x0101 => TagType::ServiceName,
+            0x0102 => TagType::AcName,
+            0x0103 => TagType::HostUniq,
+            0x0104 => TagType::AcCookie,
+            0x0105 => TagType::VendorSpecific,
+            0x0110 => TagType::RelaySessionId,
+            0x0120 => TagType::PppMaxPayload,
+            0x0201 => TagType::ServiceNameError,
+            0x0202 => TagType::AcSystemError,
+            0x0203 => TagType::GenericError,
+            _ => TagType::Unsupported(tag_type),
+        }
+    }
+}
+
+#[derive(Debug)]
+/**
+ * A discovery tag, the value is kept as raw bytes since most tags are either UTF-8 strings or opaque blobs.
+ */
+pub struct Tag {
+    pub tag_type: TagType,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug)]
+/**
+ ### PPPoE header structure
+ * Version (4 bits): Always 1.
+ * Type (4 bits): Always 1.
+ * Code (8 bits): The discovery packet type, or session data.
+ * Session ID (16 bits): The session the packet belongs to, 0 until the session is established.
+ * Length (16 bits): The length of the payload, excluding the PPPoE header.
+ * Tags (variable): The discovery tags. Empty for session data.
+ */
+pub struct Header {
+    pub version: u8,
+    pub pppoe_type: u8,
+    pub code: Code,
+    pub session_id: u16,
+    pub length: u16,
+    pub tags: Vec<Tag>,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        6
+    }
+}
+
+/**
+ ### Parse the PPPoE header from the data
+
+ Discovery packets have their tag list decoded. For session data the PPP frame follows the header and is left to the caller.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse PPPoE header",
+        ));
+    }
+
+    let version = data[0] >> 4;
+    let pppoe_type = data[0] & 0x0F;
+    let code = Code::from_u8(data[1]);
+    let session_id = read_u16_be(data, 2)?;
+    let length = read_u16_be(data, 4)?;
+
+    let payload = match data.get(Header::size()..Header::size() + length as usize) {
+        Some(payload) => payload,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "PPPoE length exceeds the captured data",
+            ))
+        }
+    };
+
+    let mut tags = Vec::new();
+    if code != Code::SessionData {
+        let mut offset = 0;
+        while offset + 4 <= payload.len() {
+            let tag_type = TagType::from_u16(read_u16_be(payload, offset)?);
+            let tag_length = read_u16_be(payload, offset + 2)? as usize;
+            let value = match payload.get(offset + 4..offset + 4 + tag_length) {
+                Some(value) => value.to_vec(),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Truncated PPPoE discovery tag",
+                    ))
+                }
+            };
+            offset += 4 + tag_length;
+
+            tags.push(Tag { tag_type, value });
+            if tag_type == TagType::EndOfList {
+                break;
+            }
+        }
+    }
+
+    Ok(Header {
+        version,
+        pppoe_type,
+        code,
+        session_id,
+        length,
+        tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_discovery_tags() {
+        // PADI with an empty Service-Name tag and a Host-Uniq tag.
+        let data = [
+            0x11, 0x09, 0x00, 0x00, 0x00, 0x0C, 0x01, 0x01, 0x00, 0x00, 0x01, 0x03, 0x00, 0x04,
+            0xDE, 0xAD, 0xBE, 0xEF,
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.code, Code::Padi);
+        assert_eq!(header.tags.len(), 2);
+        assert_eq!(header.tags[0].tag_type, TagType::ServiceName);
+        assert_eq!(header.tags[1].tag_type, TagType::HostUniq);
+        assert_eq!(header.tags[1].value, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn rejects_length_beyond_data() {
+        assert!(parse(&[0x11, 0x00, 0x00, 0x01, 0x00, 0x10, 0xC0, 0x21]).is_err());
+    }
+}
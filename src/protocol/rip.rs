@@ -0,0 +1,131 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::ipv4::Address;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Request,
+    Response,
+    Unsupported(u8),
+}
+
+#[derive(Debug)]
+/**
+ ### RIP entry
+ * `Route`: Address family (2 for IP), route tag, address, subnet mask, next hop and metric. The route tag, mask and next hop
+   are only used by RIPv2 and zero in RIPv1. A metric of 16 means unreachable.
+ * `Authentication` (address family 0xFFFF): The authentication type and data of RIPv2 (RFC 2453, RFC 4822).
+ */
+pub enum Entry {
+    Route {
+        address_family: u16,
+        route_tag: u16,
+        address: Address,
+        subnet_mask: Address,
+        next_hop: Address,
+        metric: u32,
+    },
+    Authentication { authentication_type: u16, data: Vec<u8> },
+}
+
+#[derive(Debug)]
+/**
+ ### RIP header structure
+ * Command (8 bits): 1 for requests, 2 for responses.
+ * Version (8 bits): 1 or 2.
+ * Entries: Up to 25 entries of 20 bytes each.
+ */
+pub struct Header {
+    pub command: Command,
+    pub version: u8,
+    pub entries: Vec<Entry>,
+}
+
+fn read_address(data: &[u8], offset: usize) -> Address {
+    Address::new([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/**
+ * Parse a RIPv1 or RIPv2 message from the data.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse RIP header",
+        ));
+    }
+    if (data.len() - 4) % 20 != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "RIP entries must be 20 bytes long"));
+    }
+
+    let command = match data[0] {
+        1 => Command::Request,
+        2 => Command::Response,
+        other => Command::Unsupported(other),
+    };
+    let version = data[1];
+    if !matches!(version, 1 | 2) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported RIP version"));
+    }
+
+    let mut entries = Vec::new();
+    for entry in data[4..].chunks_exact(20) {
+        let address_family = read_u16_be(entry, 0)?;
+        if version == 2 && address_family == 0xFFFF {
+            entries.push(Entry::Authentication {
+                authentication_type: read_u16_be(entry, 2)?,
+                data: entry[4..].to_vec(),
+            });
+        } else {
+            entries.push(Entry::Route {
+                address_family,
+                route_tag: read_u16_be(entry, 2)?,
+                address: read_address(entry, 4),
+                subnet_mask: read_address(entry, 8),
+                next_hop: read_address(entry, 12),
+                metric: read_u32_be(entry, 16)?,
+            });
+        }
+    }
+
+    Ok(Header {
+        command,
+        version,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v2_response() {
+        let data = [
+            0x02, 0x02, 0x00, 0x00,
+            0xFF, 0xFF, 0x00, 0x02, b's', b'e', b'c', b'r', b'e', b't', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // Simple password
+            0x00, 0x02, 0x00, 0x07, 10, 1, 0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x02,
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.command, Command::Response);
+        assert!(matches!(&header.entries[0], Entry::Authentication { authentication_type: 2, data } if data.starts_with(b"secret")));
+        match &header.entries[1] {
+            Entry::Route { route_tag, address, subnet_mask, metric, .. } => {
+                assert_eq!(*route_tag, 7);
+                assert_eq!(address.address, [10, 1, 0, 0]);
+                assert_eq!(subnet_mask.address, [255, 255, 0, 0]);
+                assert_eq!(*metric, 2);
+            }
+            other => panic!("Unexpected entry: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_partial_entries() {
+        assert!(parse(&[0x01, 0x01, 0x00, 0x00, 0x00, 0x02]).is_err());
+    }
+}
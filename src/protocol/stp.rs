@@ -0,0 +1,219 @@
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+use super::ethernet::MacAddress;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### BPDU type
+ * `Configuration`: Classic 802.1D configuration BPDU.
+ * `TopologyChangeNotification`: Sent towards the root bridge when the topology changes.
+ * `RapidSpanningTree`: RSTP and MSTP BPDU, distinguished by the protocol version.
+ */
+pub enum BpduType {
+    Configuration,
+    TopologyChangeNotification,
+    RapidSpanningTree,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * BPDU flags. The port role of RSTP BPDUs is stored separately in `Configuration::port_role`.
+ */
+pub enum Flag {
+    TopologyChange,
+    Proposal,
+    Learning,
+    Forwarding,
+    Agreement,
+    TopologyChangeAcknowledgment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * The role of the sending port, only meaningful in RSTP and MSTP BPDUs.
+ */
+pub enum PortRole {
+    Unknown,
+    AlternateOrBackup,
+    Root,
+    Designated,
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### Bridge identifier
+ * Priority (16 bits): The bridge priority, including the 12 bit system ID extension (usually the VLAN).
+ * MAC address (48 bits): The address of the bridge.
+ */
+pub struct BridgeId {
+    pub priority: u16,
+    pub mac: MacAddress,
+}
+
+#[derive(Debug)]
+/**
+ ### Configuration BPDU body
+ All times are expressed in units of 1/256 seconds.
+ * Flags (8 bits): Topology change and RSTP state flags.
+ * Root Identifier (64 bits): The bridge the sender believes is the root.
+ * Root Path Cost (32 bits): The cost from the sender to the root.
+ * Bridge Identifier (64 bits): The sending bridge.
+ * Port Identifier (16 bits): The sending port.
+ * Message Age, Max Age, Hello Time, Forward Delay (16 bits each): Timers advertised by the root.
+ */
+pub struct Configuration {
+    pub flags: Vec<Flag>,
+    pub port_role: PortRole,
+    pub root_id: BridgeId,
+    pub root_path_cost: u32,
+    pub bridge_id: BridgeId,
+    pub port_id: u16,
+    pub message_age: u16,
+    pub max_age: u16,
+    pub hello_time: u16,
+    pub forward_delay: u16,
+}
+
+#[derive(Debug)]
+/**
+ ### Spanning Tree BPDU structure
+ * Protocol Identifier (16 bits): Always 0.
+ * Protocol Version (8 bits): 0 for STP, 2 for RSTP and 3 for MSTP.
+ * BPDU Type (8 bits): The type of the BPDU.
+ * Configuration: The body of configuration and RST BPDUs, topology change notifications have none.
+ */
+pub struct Header {
+    pub protocol_id: u16,
+    pub version: u8,
+    pub bpdu_type: BpduType,
+    pub configuration: Option<Configuration>,
+}
+
+fn parse_bridge_id(data: &[u8], offset: usize) -> io::Result<BridgeId> {
+    let priority = read_u16_be(data, offset)?;
+    let mac = MacAddress::new([
+        data[offset + 2],
+        data[offset + 3],
+        data[offset + 4],
+        data[offset + 5],
+        data[offset + 6],
+        data[offset + 7],
+    ]);
+    Ok(BridgeId { priority, mac })
+}
+
+/**
+ ### Parse a Spanning Tree BPDU from the data
+
+ MSTP specific fields following the RSTP part are ignored.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse BPDU",
+        ));
+    }
+
+    let protocol_id = read_u16_be(data, 0)?;
+    let version = data[2];
+    let bpdu_type = match data[3] {
+        0x00 => BpduType::Configuration,
+        0x80 => BpduType::TopologyChangeNotification,
+        0x02 => BpduType::RapidSpanningTree,
+        other => BpduType::Unsupported(other),
+    };
+
+    let configuration = match bpdu_type {
+        BpduType::Configuration | BpduType::RapidSpanningTree => {
+            if data.len() < 35 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Not enough data to parse configuration BPDU",
+                ));
+            }
+
+            let raw_flags = data[4];
+            let mut flags = Vec::new();
+            if raw_flags & 0x01 != 0 {
+                flags.push(Flag::TopologyChange);
+            }
+            if raw_flags & 0x02 != 0 {
+                flags.push(Flag::Proposal);
+            }
+            if raw_flags & 0x10 != 0 {
+                flags.push(Flag::Learning);
+            }
+            if raw_flags & 0x20 != 0 {
+                flags.push(Flag::Forwarding);
+            }
+            if raw_flags & 0x40 != 0 {
+                flags.push(Flag::Agreement);
+            }
+            if raw_flags & 0x80 != 0 {
+                flags.push(Flag::TopologyChangeAcknowledgment);
+            }
+            let port_role = match (raw_flags >> 2) & 0x03 {
+                1 => PortRole::AlternateOrBackup,
+                2 => PortRole::Root,
+                3 => PortRole::Designated,
+                _ => PortRole::Unknown,
+            };
+
+            Some(Configuration {
+                flags,
+                port_role,
+                root_id: parse_bridge_id(data, 5)?,
+                root_path_cost: read_u32_be(data, 13)?,
+                bridge_id: parse_bridge_id(data, 17)?,
+                port_id: read_u16_be(data, 25)?,
+                message_age: read_u16_be(data, 27)?,
+                max_age: read_u16_be(data, 29)?,
+                hello_time: read_u16_be(data, 31)?,
+                forward_delay: read_u16_be(data, 33)?,
+            })
+        }
+        _ => None,
+    };
+
+    Ok(Header {
+        protocol_id,
+        version,
+        bpdu_type,
+        configuration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_configuration_bpdu() {
+        let data = [
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x80, 0x01, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x00,
+            0x00, 0x00, 0x04, 0x80, 0x01, 0x00, 0x66, 0x77, 0x88, 0x99, 0xAA, 0x80, 0x02, 0x00,
+            0x00, 0x14, 0x00, 0x02, 0x00, 0x0F, 0x00,
+        ];
+        let header = parse(&data).unwrap();
+        let configuration = header.configuration.unwrap();
+
+        assert_eq!(header.bpdu_type, BpduType::Configuration);
+        assert_eq!(configuration.flags, vec![Flag::TopologyChange]);
+        assert_eq!(configuration.root_id.priority, 0x8001);
+        assert_eq!(configuration.root_path_cost, 4);
+        assert_eq!(configuration.port_id, 0x8002);
+        assert_eq!(configuration.max_age, 20 * 256);
+    }
+
+    #[test]
+    fn parses_topology_change_notification() {
+        let header = parse(&[0x00, 0x00, 0x00, 0x80]).unwrap();
+
+        assert_eq!(header.bpdu_type, BpduType::TopologyChangeNotification);
+        assert!(header.configuration.is_none());
+    }
+}
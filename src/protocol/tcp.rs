@@ -1,8 +1,12 @@
-#[derive(Debug)]
+use std::io;
+
+use crate::read_bytes::{read_u16_be, read_u32_be};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 /**
  #### TCP Flags Overview:
-- **Reserved (RES)**: Bits set aside for future use. Typically expected to be zero.
-- **AccurateECN (AccECN)**: An extension of ECN for more accurate network congestion feedback.
+- **Reserved (RES)**: Bits set aside for future use. Typically expected to be zero. Listed once if any of the three reserved bits is set.
+- **AccurateECN (AccECN)**: An extension of ECN for more accurate network congestion feedback. The AE bit, formerly the ECN nonce (NS).
 - **Congestion Window Reduced (CWR)**: Indicates that the sender has received a packet with the ECNEcho flag set and has reduced its congestion window.
 - **ECN-Echo (ECE)**: Used by the receiver to signal to the sender that network congestion was encountered.
 - **Urgent (URG)**: Indicates that this segment contains urgent data which should be prioritized.
@@ -25,6 +29,135 @@ pub enum Flag {
     Finish,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Subtype of a Multipath TCP option (RFC 8684).
+ */
+pub enum MptcpSubtype {
+    MpCapable,
+    MpJoin,
+    Dss,
+    AddAddress,
+    RemoveAddress,
+    MpPrio,
+    MpFail,
+    MpFastclose,
+    MpTcpRst,
+    Unsupported(u8),
+}
+
+impl MptcpSubtype {
+    pub fn from_u8(subtype: u8) -> MptcpSubtype {
+        match subtype {
+            0 => MptcpSubtype::MpCapable,
+            1 => MptcpSubtype::MpJoin,
+            2 => MptcpSubtype::Dss,
+            3 => MptcpSubtype::AddAddress,
+            4 => MptcpSubtype::RemoveAddress,
+            5 => MptcpSubtype::MpPrio,
+            6 => MptcpSubtype::MpFail,
+            7 => MptcpSubtype::MpFastclose,
+            8 => MptcpSubtype::MpTcpRst,
+            _ => MptcpSubtype::Unsupported(subtype),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/**
+ ### TCP option
+ * `EndOfOptionList` (0): The remaining bytes of the header are padding.
+ * `NoOperation` (1): Padding between options.
+ * `MaximumSegmentSize` (2): The largest segment the sender can receive.
+ * `WindowScale` (3): The shift count applied to the window size (RFC 7323).
+ * `SackPermitted` (4): The sender supports selective acknowledgments (RFC 2018).
+ * `Sack` (5): The left and right edges of the received blocks.
+ * `Timestamps` (8): The timestamp value (TSval) and echo reply (TSecr) (RFC 7323).
+ * `Md5Signature` (19): The MD5 digest of the segment (RFC 2385).
+ * `AuthenticationOption` (29): The key IDs and MAC of TCP-AO (RFC 5925).
+ * `Mptcp` (30): The subtype and data of a Multipath TCP option, including the subtype byte.
+ * `FastOpen` (34, or 254 with magic 0xF989): The TFO cookie, empty for a cookie request (RFC 7413).
+ * `Unknown`: Any other option, with its kind and data.
+ * `Malformed`: An option whose length is invalid for its kind, with its data. Or an option whose length is missing or runs
+   past the header, with all bytes after the kind, in which case no further options are decoded.
+ */
+pub enum TcpOption {
+    EndOfOptionList,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    Sack(Vec<(u32, u32)>),
+    Timestamps { value: u32, echo_reply: u32 },
+    Md5Signature(Vec<u8>),
+    AuthenticationOption { key_id: u8, rnext_key_id: u8, mac: Vec<u8> },
+    Mptcp { subtype: MptcpSubtype, data: Vec<u8> },
+    FastOpen { cookie: Vec<u8> },
+    Unknown { kind: u8, data: Vec<u8> },
+    Malformed { kind: u8, data: Vec<u8> },
+}
+
+fn parse_option(kind: u8, value: &[u8]) -> TcpOption {
+    let malformed = || TcpOption::Malformed { kind, data: value.to_vec() };
+    match (kind, value.len()) {
+        (2, 2) => TcpOption::MaximumSegmentSize(u16::from_be_bytes([value[0], value[1]])),
+        (3, 1) => TcpOption::WindowScale(value[0]),
+        (4, 0) => TcpOption::SackPermitted,
+        (5, length) if length > 0 && length % 8 == 0 => TcpOption::Sack(
+            value
+                .chunks_exact(8)
+                .map(|block| (u32::from_be_bytes([block[0], block[1], block[2], block[3]]), u32::from_be_bytes([block[4], block[5], block[6], block[7]])))
+                .collect(),
+        ),
+        (8, 8) => TcpOption::Timestamps {
+            value: u32::from_be_bytes([value[0], value[1], value[2], value[3]]),
+            echo_reply: u32::from_be_bytes([value[4], value[5], value[6], value[7]]),
+        },
+        (19, 16) => TcpOption::Md5Signature(value.to_vec()),
+        (29, length) if length >= 2 => TcpOption::AuthenticationOption { key_id: value[0], rnext_key_id: value[1], mac: value[2..].to_vec() },
+        (30, length) if length >= 1 => TcpOption::Mptcp { subtype: MptcpSubtype::from_u8(value[0] >> 4), data: value.to_vec() },
+        (34, length) if length == 0 || (4..=16).contains(&length) => TcpOption::FastOpen { cookie: value.to_vec() },
+        (254, length) if length >= 2 && value[..2] == [0xF9, 0x89] => TcpOption::FastOpen { cookie: value[2..].to_vec() },
+        (2..=5, _) | (8, _) | (19, _) | (29, _) | (30, _) | (34, _) => malformed(),
+        _ => TcpOption::Unknown { kind, data: value.to_vec() },
+    }
+}
+
+/**
+ * Decode the options of a TCP header. Malformed options are reported as such instead of failing the header.
+ */
+pub fn parse_options(data: &[u8]) -> Vec<TcpOption> {
+    let mut options = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let kind = data[offset];
+        match kind {
+            0 => {
+                options.push(TcpOption::EndOfOptionList);
+                break;
+            }
+            1 => {
+                options.push(TcpOption::NoOperation);
+                offset += 1;
+            }
+            _ => {
+                let length = data.get(offset + 1).map(|length| *length as usize);
+                match length {
+                    Some(length) if length >= 2 && offset + length <= data.len() => {
+                        options.push(parse_option(kind, &data[offset + 2..offset + length]));
+                        offset += length;
+                    }
+                    _ => {
+                        options.push(TcpOption::Malformed { kind, data: data[offset + 1..].to_vec() });
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    options
+}
+
 #[derive(Debug)]
 /**
 ### TCP header structure
@@ -38,11 +171,13 @@ pub enum Flag {
     - **SYN or FIN Flag**: If the packet also includes a SYN or FIN flag (which consume an additional sequence number each), the acknowledgment number will be `S + N + 1`. 
     - **Just SYN or FIN Flag**: If a packet solely contains a SYN or FIN flag and no data, the acknowledgment number will be `S + 1`.   
 * Data Offset (4 bits): The number of 32-bit words in the TCP header. 1 word = 4 bytes. So multiply this value by 4 to get the size of the TCP header in bytes.
-* Flags (8 bits): The flags of the TCP segment.
+* Flags (12 bits): The flags of the TCP segment, from the most significant bit.
 * Window Size (16 bits): The size of the receive window.
 * Checksum (16 bits): The checksum of the TCP segment.
 * Urgent Pointer (16 bits): If the URG flag is set, this field contains a pointer to the last urgent data byte.
-* Options (variable): The options of the TCP segment.
+* Options (variable): The decoded options of the TCP segment, up to the end of option list.
+* Truncated: Only the ports and the sequence number were present, e.g. in a datagram quoted by an ICMP error.
+  The other fields are zero and the segment has no payload.
 */
 pub struct Header {
     pub source_port: u16,
@@ -54,5 +189,197 @@ pub struct Header {
     pub window_size: u16,
     pub checksum: u16,
     pub urgent_pointer: u16,
-    pub options: Vec<u8>,
+    pub options: Vec<TcpOption>,
+    pub truncated: bool,
+}
+
+impl Header {
+    /**
+     * Size of the TCP header including the options, i.e. the offset of the payload.
+     */
+    pub fn size(&self) -> usize {
+        self.data_offset as usize * 4
+    }
+
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /**
+     * The payload of the segment, given the data the header was parsed from.
+     */
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        if self.truncated {
+            return &[];
+        }
+        &data[self.size().min(data.len())..]
+    }
+}
+
+/**
+ * Parse the TCP header from the data. The data offset must cover the fixed header and lie within the data.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 20 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse TCP header",
+        ));
+    }
+
+    let data_offset = data[12] >> 4;
+    if data_offset < 5 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "TCP data offset is smaller than the header"));
+    }
+    let size = data_offset as usize * 4;
+    if size > data.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "TCP data offset exceeds the segment"));
+    }
+
+    let bits = read_u16_be(data, 12)? & 0x0FFF;
+    let flags = [
+        (0x0E00, Flag::Reserved),
+        (0x0100, Flag::AccurateECN),
+        (0x0080, Flag::CongestionWindowReduced),
+        (0x0040, Flag::ECNEcho),
+        (0x0020, Flag::Urgent),
+        (0x0010, Flag::Acknowledgment),
+        (0x0008, Flag::Push),
+        (0x0004, Flag::Reset),
+        (0x0002, Flag::Synchronize),
+        (0x0001, Flag::Finish),
+    ]
+    .into_iter()
+    .filter(|(mask, _)| bits & mask != 0)
+    .map(|(_, flag)| flag)
+    .collect();
+
+    Ok(Header {
+        source_port: read_u16_be(data, 0)?,
+        destination_port: read_u16_be(data, 2)?,
+        sequence_number: read_u32_be(data, 4)?,
+        acknowledgment_number: read_u32_be(data, 8)?,
+        data_offset,
+        flags,
+        window_size: read_u16_be(data, 14)?,
+        checksum: read_u16_be(data, 16)?,
+        urgent_pointer: read_u16_be(data, 18)?,
+        options: parse_options(&data[20..size]),
+        truncated: false,
+    })
+}
+
+/**
+ ### Parse the start of a TCP header cut short of 20 bytes
+
+ ICMP errors quote only the first 8 bytes of the transport header, enough for the ports and the sequence number.
+ */
+pub fn parse_truncated(data: &[u8]) -> io::Result<Header> {
+    if data.len() < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse the ports and sequence number of TCP header",
+        ));
+    }
+
+    Ok(Header {
+        source_port: read_u16_be(data, 0)?,
+        destination_port: read_u16_be(data, 2)?,
+        sequence_number: read_u32_be(data, 4)?,
+        acknowledgment_number: 0,
+        data_offset: 0,
+        flags: Vec::new(),
+        window_size: 0,
+        checksum: 0,
+        urgent_pointer: 0,
+        options: Vec::new(),
+        truncated: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_and_payload() {
+        let data = [
+            0xC3, 0x50, 0x00, 0xB3, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00, 0x07, 0xD0, // Ports 50000 and 179
+            0x61, 0x18, 0xFF, 0xFF, 0x12, 0x34, 0x00, 0x00, // AE, ACK and PSH set, 24 byte header
+            0x01, 0x01, 0x01, 0x00, // Options
+            0xDE, 0xAD,
+        ];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.destination_port, 179);
+        assert_eq!(header.sequence_number, 1000);
+        assert_eq!(header.flags, vec![Flag::AccurateECN, Flag::Acknowledgment, Flag::Push]);
+        assert!(!header.has_flag(Flag::Synchronize));
+        assert_eq!(header.options, [TcpOption::NoOperation, TcpOption::NoOperation, TcpOption::NoOperation, TcpOption::EndOfOptionList]);
+        assert_eq!(header.payload(&data), [0xDE, 0xAD]);
+    }
+
+    #[test]
+    fn validates_data_offset() {
+        let mut data = [0u8; 20];
+        data[12] = 0x40;
+        assert!(parse(&data).is_err());
+        data[12] = 0x60;
+        assert!(parse(&data).is_err());
+        data[12] = 0x5E;
+        assert_eq!(parse(&data).unwrap().flags, vec![Flag::Reserved]);
+    }
+
+    #[test]
+    fn parses_truncated_header() {
+        let data = [0xC3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00];
+        let header = parse_truncated(&data).unwrap();
+
+        assert_eq!((header.source_port, header.destination_port, header.sequence_number), (50000, 80, 1000));
+        assert!(header.truncated);
+        assert!(header.payload(&data).is_empty());
+        assert!(parse_truncated(&data[..7]).is_err());
+    }
+
+    #[test]
+    fn decodes_options() {
+        let data = [
+            0x02, 0x04, 0x05, 0xB4, // MSS 1460
+            0x04, 0x02, // SACK permitted
+            0x08, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, // Timestamps
+            0x01, 0x03, 0x03, 0x07, // NOP, window scale 7
+            0x05, 0x0A, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x20, // One SACK block
+            0x22, 0x02, // TFO cookie request
+            0x1E, 0x04, 0x20, 0x01, // MPTCP DSS
+            0xFD, 0x03, 0xAA, // Experimental
+        ];
+        let options = parse_options(&data);
+
+        assert_eq!(options, vec![
+            TcpOption::MaximumSegmentSize(1460),
+            TcpOption::SackPermitted,
+            TcpOption::Timestamps { value: 1, echo_reply: 2 },
+            TcpOption::NoOperation,
+            TcpOption::WindowScale(7),
+            TcpOption::Sack(vec![(16, 32)]),
+            TcpOption::FastOpen { cookie: vec![] },
+            TcpOption::Mptcp { subtype: MptcpSubtype::Dss, data: vec![0x20, 0x01] },
+            TcpOption::Unknown { kind: 0xFD, data: vec![0xAA] },
+        ]);
+    }
+
+    #[test]
+    fn reports_malformed_options() {
+        // An MSS option of the wrong length, followed by an option running past the header.
+        let options = parse_options(&[0x02, 0x03, 0x05, 0x08, 0x0A, 0x00]);
+        assert_eq!(options, vec![
+            TcpOption::Malformed { kind: 2, data: vec![0x05] },
+            TcpOption::Malformed { kind: 8, data: vec![0x0A, 0x00] },
+        ]);
+
+        let mut data = [0u8; 24];
+        data[12] = 0x60;
+        data[20..].copy_from_slice(&[0x02, 0x01, 0x00, 0x00]);
+        assert!(matches!(parse(&data).unwrap().options[..], [TcpOption::Malformed { kind: 2, .. }]));
+    }
 }
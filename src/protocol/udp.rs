@@ -0,0 +1,116 @@
+use std::io;
+
+use crate::read_bytes::read_u16_be;
+
+use super::{options::Options, Protocol};
+
+/**
+ * Dissector attached to a UDP port. It receives the UDP payload and appends the protocols it finds.
+ */
+pub type Dissector = fn(&[u8], &mut Vec<Protocol>, &Options);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### UDP length compared with the IP payload
+ * `Valid`: The length matches the IP payload.
+ * `TooShort`: The length is smaller than the UDP header. The payload is the rest of the IP payload.
+ * `Shorter`: The IP payload holds more than the length, e.g. padding. The payload ends at the length.
+ * `Longer`: The length exceeds the IP payload, e.g. in the first fragment of a datagram or a truncated capture.
+   The payload ends with the IP payload.
+ */
+pub enum Length {
+    Valid,
+    TooShort,
+    Shorter { ip_payload: usize },
+    Longer { ip_payload: usize },
+}
+
+#[derive(Debug)]
+/**
+ ### UDP header structure
+ * Source Port (16 bits): The port of the sender.
+ * Destination Port (16 bits): The port of the receiver.
+ * Length (16 bits): The length of the header and the data.
+ * Checksum (16 bits): Checksum over the datagram and the IP pseudo-header, 0 if unused over IPv4.
+ * Length Status: How the length compares with the IP payload.
+ * Payload Length: The number of payload bytes following the header.
+ */
+pub struct Header {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub length: u16,
+    pub checksum: u16,
+    pub length_status: Length,
+    pub payload_length: usize,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+
+    /**
+     * The payload of the datagram, given the IP payload the header was parsed from.
+     */
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[Header::size()..Header::size() + self.payload_length]
+    }
+}
+
+/**
+ ### Parse the UDP header from the IP payload
+
+ The length is validated against the IP payload, and a mismatch is recorded in the header instead of failing the parse.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse UDP header",
+        ));
+    }
+
+    let length = read_u16_be(data, 4)?;
+    let length_status = match length as usize {
+        length if length < Header::size() => Length::TooShort,
+        length if length < data.len() => Length::Shorter { ip_payload: data.len() },
+        length if length > data.len() => Length::Longer { ip_payload: data.len() },
+        _ => Length::Valid,
+    };
+    let payload_end = match length_status {
+        Length::Shorter { .. } => length as usize,
+        _ => data.len(),
+    };
+
+    Ok(Header {
+        source_port: read_u16_be(data, 0)?,
+        destination_port: read_u16_be(data, 2)?,
+        length,
+        checksum: read_u16_be(data, 6)?,
+        length_status,
+        payload_length: payload_end - Header::size(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_length_against_ip_payload() {
+        let data = [0x13, 0x88, 0x00, 0x35, 0x00, 0x0A, 0xAB, 0xCD, 0x01, 0x02, 0x00, 0x00];
+        let header = parse(&data).unwrap();
+
+        assert_eq!(header.destination_port, 53);
+        assert_eq!(header.checksum, 0xABCD);
+        assert_eq!(header.length_status, Length::Shorter { ip_payload: 12 });
+        assert_eq!(header.payload(&data), [0x01, 0x02]);
+
+        let header = parse(&data[..9]).unwrap();
+        assert_eq!(header.length_status, Length::Longer { ip_payload: 9 });
+        assert_eq!(header.payload(&data[..9]), [0x01]);
+
+        let data = [0x13, 0x88, 0x00, 0x35, 0x00, 0x04, 0x00, 0x00, 0x01];
+        assert_eq!(parse(&data).unwrap().length_status, Length::TooShort);
+    }
+}
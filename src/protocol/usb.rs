@@ -0,0 +1,380 @@
+use std::io;
+
+use crate::{
+    pcap::byte_order::ByteOrder,
+    read_bytes::{read_i32_with_byte_order, read_u16_with_byte_order, read_u32_with_byte_order, read_u64_with_byte_order},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### URB event type
+ * `Submission` ('S'): The URB was submitted to the host controller.
+ * `Completion` ('C'): The URB was completed.
+ * `Error` ('E'): The submission failed.
+ */
+pub enum EventType {
+    Submission,
+    Completion,
+    Error,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * USB transfer type of the endpoint.
+ */
+pub enum TransferType {
+    Isochronous,
+    Interrupt,
+    Control,
+    Bulk,
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Direction of the transfer, `In` is from the device to the host.
+ */
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ * Descriptor types that can be requested with GET_DESCRIPTOR.
+ */
+pub enum DescriptorType {
+    Device,
+    Configuration,
+    String,
+    Interface,
+    Endpoint,
+    DeviceQualifier,
+    OtherSpeedConfiguration,
+    InterfacePower,
+    Bos,
+    Unsupported(u8),
+}
+
+impl DescriptorType {
+    pub fn from_u8(descriptor_type: u8) -> DescriptorType {
+        match descriptor_type {
+            1 => DescriptorType::Device,
+            2 => DescriptorType::Configuration,
+            3 => DescriptorType::String,
+            4 => DescriptorType::Interface,
+            5 => DescriptorType::Endpoint,
+            6 => DescriptorType::DeviceQualifier,
+            7 => DescriptorType::OtherSpeedConfiguration,
+            8 => DescriptorType::InterfacePower,
+            15 => DescriptorType::Bos,
+            _ => DescriptorType::Unsupported(descriptor_type),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/**
+ ### Standard device requests
+ Defined in chapter 9 of the USB 2.0 specification. Class and vendor requests are stored as `NonStandard` with their request number.
+ */
+pub enum Request {
+    GetStatus,
+    ClearFeature { feature: u16 },
+    SetFeature { feature: u16 },
+    SetAddress { address: u16 },
+    GetDescriptor { descriptor_type: DescriptorType, index: u8, language_id: u16 },
+    SetDescriptor { descriptor_type: DescriptorType, index: u8, language_id: u16 },
+    GetConfiguration,
+    SetConfiguration { configuration: u8 },
+    GetInterface { interface: u16 },
+    SetInterface { interface: u16, alternate_setting: u16 },
+    SynchFrame { endpoint: u16 },
+    NonStandard(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ ### Control transfer setup packet
+ * bmRequestType (8 bits): Direction, type (standard, class, vendor) and recipient of the request.
+ * bRequest (8 bits): The request, decoded into `Request` for standard requests.
+ * wValue, wIndex, wLength (16 bits each, little-endian): Request specific parameters.
+ */
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: Request,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+/**
+ * Isochronous packet descriptor, only present in the memory-mapped format.
+ */
+pub struct IsoDescriptor {
+    pub status: i32,
+    pub offset: u32,
+    pub length: u32,
+}
+
+#[derive(Debug)]
+/**
+ * Fields only present in the 64 byte header of the memory-mapped format (LINKTYPE_USB_LINUX_MMAPPED).
+ */
+pub struct Mmapped {
+    pub interval: i32,
+    pub start_frame: i32,
+    pub transfer_flags: u32,
+    pub descriptor_count: u32,
+    pub iso_descriptors: Vec<IsoDescriptor>,
+}
+
+#[derive(Debug)]
+/**
+ ### usbmon pseudo-header structure
+ All multi-byte fields are in the byte order of the capturing host, which is the byte order of the pcap file.
+ * URB ID (64 bits): Identifies the URB, matches submissions with completions.
+ * Event Type (8 bits): Submission, completion or error.
+ * Transfer Type (8 bits): Isochronous, interrupt, control or bulk.
+ * Endpoint (8 bits): The endpoint number, the highest bit is the direction.
+ * Device (8 bits): The device address on the bus.
+ * Bus (16 bits): The bus number.
+ * Setup Flag (8 bits): 0 when the setup packet is present.
+ * Data Flag (8 bits): 0 when data is present.
+ * Timestamp (64 + 32 bits): Seconds and microseconds.
+ * Status (32 bits): The URB status, a negative errno value on failure.
+ * URB Length (32 bits): The length of the transfer buffer.
+ * Data Length (32 bits): The number of captured data bytes following the header.
+ * Setup packet or isochronous error count and descriptor count (64 bits).
+ */
+pub struct Header {
+    pub urb_id: u64,
+    pub event_type: EventType,
+    pub transfer_type: TransferType,
+    pub endpoint: u8,
+    pub direction: Direction,
+    pub device: u8,
+    pub bus: u16,
+    pub setup: Option<SetupPacket>,
+    pub data_present: bool,
+    pub ts_sec: i64,
+    pub ts_usec: i32,
+    pub status: i32,
+    pub urb_length: u32,
+    pub data_length: u32,
+    pub iso_error_count: Option<i32>,
+    pub iso_descriptor_count: Option<i32>,
+    pub mmapped: Option<Mmapped>,
+}
+
+impl Header {
+    /**
+     * Size of the pseudo-header including isochronous descriptors, the captured data follows right after.
+     */
+    pub fn size(&self) -> usize {
+        match &self.mmapped {
+            Some(mmapped) => 64 + 16 * mmapped.iso_descriptors.len(),
+            None => 48,
+        }
+    }
+}
+
+fn parse_setup_packet(data: &[u8]) -> SetupPacket {
+    // The setup packet is sent over the wire as is, so it is always little-endian.
+    let request_type = data[0];
+    let value = u16::from_le_bytes([data[2], data[3]]);
+    let index = u16::from_le_bytes([data[4], data[5]]);
+    let length = u16::from_le_bytes([data[6], data[7]]);
+
+    let [descriptor_index, descriptor_type] = value.to_le_bytes();
+    let request = if (request_type >> 5) & 0x03 != 0 {
+        Request::NonStandard(data[1])
+    } else {
+        match data[1] {
+            0 => Request::GetStatus,
+            1 => Request::ClearFeature { feature: value },
+            3 => Request::SetFeature { feature: value },
+            5 => Request::SetAddress { address: value },
+            6 => Request::GetDescriptor {
+                descriptor_type: DescriptorType::from_u8(descriptor_type),
+                index: descriptor_index,
+                language_id: index,
+            },
+            7 => Request::SetDescriptor {
+                descriptor_type: DescriptorType::from_u8(descriptor_type),
+                index: descriptor_index,
+                language_id: index,
+            },
+            8 => Request::GetConfiguration,
+            9 => Request::SetConfiguration { configuration: descriptor_index },
+            10 => Request::GetInterface { interface: index },
+            11 => Request::SetInterface { interface: index, alternate_setting: value },
+            12 => Request::SynchFrame { endpoint: index },
+            other => Request::NonStandard(other),
+        }
+    };
+
+    SetupPacket {
+        request_type,
+        request,
+        value,
+        index,
+        length,
+    }
+}
+
+/**
+ ### Parse the usbmon pseudo-header from the data
+
+ The header is 48 bytes long, or 64 bytes followed by the isochronous descriptors when `mmapped` is set.
+ */
+pub fn parse(data: &[u8], byte_order: &ByteOrder, mmapped: bool) -> io::Result<Header> {
+    let header_size = if mmapped { 64 } else { 48 };
+    if data.len() < header_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse usbmon header",
+        ));
+    }
+
+    let mut reader = data;
+    let urb_id = read_u64_with_byte_order(&mut reader, byte_order)?;
+
+    let event_type = match data[8] {
+        b'S' => EventType::Submission,
+        b'C' => EventType::Completion,
+        b'E' => EventType::Error,
+        other => EventType::Unsupported(other),
+    };
+    let transfer_type = match data[9] {
+        0 => TransferType::Isochronous,
+        1 => TransferType::Interrupt,
+        2 => TransferType::Control,
+        3 => TransferType::Bulk,
+        other => TransferType::Unsupported(other),
+    };
+    let direction = if data[10] & 0x80 != 0 { Direction::In } else { Direction::Out };
+    let endpoint = data[10] & 0x7F;
+    let device = data[11];
+
+    let mut reader = &data[12..];
+    let bus = read_u16_with_byte_order(&mut reader, byte_order)?;
+    let setup_present = data[14] == 0;
+    let data_present = data[15] == 0;
+
+    let mut reader = &data[16..];
+    let ts_sec = read_u64_with_byte_order(&mut reader, byte_order)? as i64;
+    let ts_usec = read_i32_with_byte_order(&mut reader, byte_order)?;
+    let status = read_i32_with_byte_order(&mut reader, byte_order)?;
+    let urb_length = read_u32_with_byte_order(&mut reader, byte_order)?;
+    let data_length = read_u32_with_byte_order(&mut reader, byte_order)?;
+
+    // The last 8 bytes hold either the setup packet or the isochronous counters.
+    let (setup, iso_error_count, iso_descriptor_count) = if setup_present {
+        (Some(parse_setup_packet(&data[40..48])), None, None)
+    } else if transfer_type == TransferType::Isochronous {
+        let error_count = read_i32_with_byte_order(&mut reader, byte_order)?;
+        let descriptor_count = read_i32_with_byte_order(&mut reader, byte_order)?;
+        (None, Some(error_count), Some(descriptor_count))
+    } else {
+        (None, None, None)
+    };
+
+    let mmapped = if mmapped {
+        let mut reader = &data[48..];
+        let interval = read_i32_with_byte_order(&mut reader, byte_order)?;
+        let start_frame = read_i32_with_byte_order(&mut reader, byte_order)?;
+        let transfer_flags = read_u32_with_byte_order(&mut reader, byte_order)?;
+        let descriptor_count = read_u32_with_byte_order(&mut reader, byte_order)?;
+
+        let mut iso_descriptors = Vec::new();
+        if transfer_type == TransferType::Isochronous {
+            for _ in 0..descriptor_count {
+                let status = read_i32_with_byte_order(&mut reader, byte_order)
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Truncated isochronous descriptor"))?;
+                let offset = read_u32_with_byte_order(&mut reader, byte_order)?;
+                let length = read_u32_with_byte_order(&mut reader, byte_order)?;
+                // Padding
+                read_u32_with_byte_order(&mut reader, byte_order)?;
+                iso_descriptors.push(IsoDescriptor { status, offset, length });
+            }
+        }
+
+        Some(Mmapped {
+            interval,
+            start_frame,
+            transfer_flags,
+            descriptor_count,
+            iso_descriptors,
+        })
+    } else {
+        None
+    };
+
+    Ok(Header {
+        urb_id,
+        event_type,
+        transfer_type,
+        endpoint,
+        direction,
+        device,
+        bus,
+        setup,
+        data_present,
+        ts_sec,
+        ts_usec,
+        status,
+        urb_length,
+        data_length,
+        iso_error_count,
+        iso_descriptor_count,
+        mmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_get_descriptor_submission() {
+        let mut data = vec![0u8; 64];
+        data[0..8].copy_from_slice(&0x1122334455667788u64.to_le_bytes());
+        data[8] = b'S';
+        data[9] = 2; // Control
+        data[10] = 0x80; // Endpoint 0, IN
+        data[11] = 3;
+        data[12..14].copy_from_slice(&1u16.to_le_bytes());
+        data[14] = 0; // Setup present
+        data[15] = b'<';
+        data[32..36].copy_from_slice(&18u32.to_le_bytes());
+        // GET_DESCRIPTOR(Device), wLength 18
+        data[40..48].copy_from_slice(&[0x80, 0x06, 0x00, 0x01, 0x00, 0x00, 0x12, 0x00]);
+
+        let header = parse(&data, &ByteOrder::LittleEndian, true).unwrap();
+
+        assert_eq!(header.urb_id, 0x1122334455667788);
+        assert_eq!(header.event_type, EventType::Submission);
+        assert_eq!(header.transfer_type, TransferType::Control);
+        assert_eq!(header.direction, Direction::In);
+        assert_eq!(header.device, 3);
+        assert_eq!(header.bus, 1);
+        assert!(!header.data_present);
+        assert_eq!(header.urb_length, 18);
+        assert_eq!(header.size(), 64);
+
+        let setup = header.setup.unwrap();
+        assert_eq!(setup.length, 18);
+        assert_eq!(
+            setup.request,
+            Request::GetDescriptor { descriptor_type: DescriptorType::Device, index: 0, language_id: 0 }
+        );
+    }
+
+    #[test]
+    fn rejects_short_header() {
+        assert!(parse(&[0u8; 40], &ByteOrder::LittleEndian, false).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::io;
+
+use crate::read_bytes::read_u32_be;
+
+#[derive(Debug)]
+/**
+ ### VXLAN header structure (RFC 7348)
+ * Flags (8 bits): The I flag (0x08) is set when the VNI is valid. The other bits are reserved.
+ * Reserved (24 bits)
+ * VXLAN Network Identifier (VNI) (24 bits): The overlay network the frame belongs to.
+ * Reserved (8 bits)
+ */
+pub struct Header {
+    pub flags: u8,
+    pub vni: u32,
+}
+
+impl Header {
+    pub fn size() -> usize {
+        8
+    }
+
+    pub fn vni_valid(&self) -> bool {
+        self.flags & 0x08 != 0
+    }
+}
+
+/**
+ ### Parse the VXLAN header from the data
+
+ The header is followed by the inner Ethernet frame.
+ */
+pub fn parse(data: &[u8]) -> io::Result<Header> {
+    if data.len() < Header::size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not enough data to parse VXLAN header",
+        ));
+    }
+
+    Ok(Header {
+        flags: data[0],
+        vni: read_u32_be(data, 4)? >> 8,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vni() {
+        let header = parse(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x30, 0x39, 0x00]).unwrap();
+
+        assert!(header.vni_valid());
+        assert_eq!(header.vni, 12345);
+    }
+}
@@ -28,6 +28,18 @@ pub fn read_u16_with_byte_order<R: Read>(reader: &mut R, byte_order: &ByteOrder)
     }
 }
 
+/**
+ * Read and consume 8 bytes from the reader and return a u64
+ */
+pub fn read_u64_with_byte_order<R: Read>(reader: &mut R, byte_order: &ByteOrder) -> io::Result<u64> {
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+    match byte_order {
+        ByteOrder::BigEndian => Ok(u64::from_be_bytes(buffer)),
+        ByteOrder::LittleEndian => Ok(u64::from_le_bytes(buffer)),
+    }
+}
+
 /**
  * Read and consume 4 bytes from the reader and return a i32
  */
@@ -38,4 +50,34 @@ pub fn read_i32_with_byte_order<R: Read>(reader: &mut R, byte_order: &ByteOrder)
         ByteOrder::BigEndian => Ok(i32::from_be_bytes(buffer)),
         ByteOrder::LittleEndian => Ok(i32::from_le_bytes(buffer)),
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Read 2 bytes in network byte order from the slice at the given offset and return a u16
+ */
+pub fn read_u16_be(data: &[u8], offset: usize) -> io::Result<u16> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough data to read u16")),
+    }
+}
+
+/**
+ * Read 4 bytes in network byte order from the slice at the given offset and return a u32
+ */
+pub fn read_u32_be(data: &[u8], offset: usize) -> io::Result<u32> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough data to read u32")),
+    }
+}
+
+/**
+ * Read 2 bytes in little-endian byte order from the slice at the given offset and return a u16
+ */
+pub fn read_u16_le(data: &[u8], offset: usize) -> io::Result<u16> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough data to read u16")),
+    }
+}